@@ -3,8 +3,14 @@ use ray_tracing::services::scene_import_service::SceneImportService;
 
 fn main() {
     match SceneImportService::import_scene() {
-        Ok(scene) => {
-            RenderService::generate_image(&scene);
+        Ok(mut scene) => {
+            if scene.turntable.is_some() {
+                RenderService::generate_turntable(&mut scene);
+            } else if scene.animation.is_some() {
+                RenderService::generate_animation(&mut scene);
+            } else {
+                RenderService::generate_image(&scene);
+            }
         }
         Err(err) => {
             eprintln!("Failed to load scene: {}", err);
@@ -0,0 +1,70 @@
+use image::{Rgb, RgbImage};
+use serde::Deserialize;
+use crate::models::framebuffer::Framebuffer;
+
+/// Stops sampling pixels once their color is known precisely enough,
+/// so flat areas get few samples and noisy ones up to the sample count of the camera.
+/// A pixel that stopped keeps its color, so it never needs samples again.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct Adaptive {
+    /// Largest standard error of the pixel color, in units of the color range, at which a pixel stops
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+    /// Samples every pixel gets before its error is estimated
+    #[serde(default = "default_min_samples")]
+    pub min_samples: u32,
+    /// Also saves an image showing the number of samples taken for each pixel
+    #[serde(default)]
+    pub heatmap: bool,
+}
+
+fn default_threshold() -> f64 {
+    0.005
+}
+
+fn default_min_samples() -> u32 {
+    8
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self {
+            threshold: default_threshold(),
+            min_samples: default_min_samples(),
+            heatmap: false,
+        }
+    }
+}
+
+impl Adaptive {
+    /// Returns the standard error of the pixel color, taking the largest of the channels
+    pub fn error(framebuffer: &Framebuffer, x: u32, y: u32) -> f64 {
+        let variance = framebuffer.variance(x, y);
+        variance.r.max(variance.g).max(variance.b).sqrt()
+    }
+
+    /// Returns if the pixel needs no more samples
+    pub fn is_converged(&self, framebuffer: &Framebuffer, x: u32, y: u32) -> bool {
+        let samples = framebuffer.samples[framebuffer.index(x, y)];
+        samples >= self.min_samples.max(2) && Self::error(framebuffer, x, y) <= self.threshold
+    }
+
+    /// Returns if any pixel still needs samples
+    pub fn needs_samples(&self, framebuffer: &Framebuffer) -> bool {
+        (0..framebuffer.height).any(|y| (0..framebuffer.width).any(|x| !self.is_converged(framebuffer, x, y)))
+    }
+
+    /// Shows the number of samples per pixel, from blue for none over green to red for the given maximum
+    pub fn heatmap(framebuffer: &Framebuffer, max_samples: u32) -> RgbImage {
+        RgbImage::from_fn(framebuffer.width, framebuffer.height, |x, y| {
+            let samples = framebuffer.samples[framebuffer.index(x, y)];
+            let t = (samples as f64 / max_samples.max(1) as f64).clamp(0.0, 1.0);
+            let (r, g, b) = if t < 0.5 {
+                (0.0, t * 2.0, 1.0 - t * 2.0)
+            } else {
+                (t * 2.0 - 1.0, 2.0 - t * 2.0, 0.0)
+            };
+            Rgb([(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8])
+        })
+    }
+}
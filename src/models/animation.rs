@@ -0,0 +1,485 @@
+use std::io;
+use serde::Deserialize;
+use crate::models::camera::Camera;
+use crate::models::color::Color;
+use crate::models::group::Group;
+use crate::models::lights::Lights;
+use crate::models::material::{MaterialSolid, MaterialTextured, Phong, Reflectance, Refraction, Transmittance};
+use crate::models::matrix::Matrix4;
+use crate::models::point::Point;
+use crate::models::surface::{Surfaces, SurfaceType};
+use crate::models::transform::Transform;
+
+/// Turns a scene into a sequence of frames.
+/// Every track changes one property of the scene over the frames 1 to `frames`,
+/// between its keyframes the value is interpolated, before the first and after
+/// the last keyframe the value stays the same.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct Animation {
+    pub frames: u32,
+    #[serde(rename = "$value", default)]
+    pub tracks: Vec<Track>,
+}
+
+/// How the values between two keyframes are computed
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Catmull-Rom spline through the keyframes
+    Cubic,
+    /// Starts and stops slowly at every keyframe
+    Ease,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LightType {
+    Ambient,
+    Point,
+    Parallel,
+    Spot,
+}
+
+/// Material parameters that can be animated, the color is animated with its own track
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MaterialParameter {
+    Ka,
+    Kd,
+    Ks,
+    Exponent,
+    Reflectance,
+    Transmittance,
+    Iof,
+}
+
+/// A single animated property. Surfaces are addressed by their position
+/// in the top level list of surfaces, lights by their position among the lights of the same type.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub enum Track {
+    #[serde(rename = "camera_position")]
+    CameraPosition(VectorTrack),
+    #[serde(rename = "camera_lookat")]
+    CameraLookAt(VectorTrack),
+    #[serde(rename = "light_position")]
+    LightPosition(LightVectorTrack),
+    #[serde(rename = "light_color")]
+    LightColor(LightColorTrack),
+    /// Translation applied after the transformation of the surface, in world units
+    #[serde(rename = "surface_translate")]
+    SurfaceTranslate(SurfaceVectorTrack),
+    /// Rotation in degrees around x, then y, then z, about the world origin
+    /// and applied after the transformation of the surface
+    #[serde(rename = "surface_rotate")]
+    SurfaceRotate(SurfaceVectorTrack),
+    /// Scaling applied after the transformation of the surface
+    #[serde(rename = "surface_scale")]
+    SurfaceScale(SurfaceVectorTrack),
+    /// Color of a solid material. Every material of the surface, including those of
+    /// the children of groups and CSG, must be solid.
+    #[serde(rename = "material_color")]
+    MaterialColor(MaterialColorTrack),
+    /// Parameter of a solid or textured material. Instances need a material override
+    /// of either kind, the shared geometry is never changed.
+    #[serde(rename = "material_parameter")]
+    MaterialParameter(MaterialParameterTrack),
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct VectorKey {
+    pub frame: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct ColorKey {
+    pub frame: f64,
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct ScalarKey {
+    pub frame: f64,
+    pub value: f64,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct VectorTrack {
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(rename = "$value", default)]
+    pub keys: Vec<VectorKey>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct LightVectorTrack {
+    pub light: LightType,
+    #[serde(default)]
+    pub index: usize,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(rename = "$value", default)]
+    pub keys: Vec<VectorKey>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct LightColorTrack {
+    pub light: LightType,
+    #[serde(default)]
+    pub index: usize,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(rename = "$value", default)]
+    pub keys: Vec<ColorKey>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct SurfaceVectorTrack {
+    pub surface: usize,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(rename = "$value", default)]
+    pub keys: Vec<VectorKey>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct MaterialColorTrack {
+    pub surface: usize,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(rename = "$value", default)]
+    pub keys: Vec<ColorKey>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct MaterialParameterTrack {
+    pub surface: usize,
+    pub name: MaterialParameter,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(rename = "$value", default)]
+    pub keys: Vec<ScalarKey>,
+}
+
+/// Keyframe of a track, every value is stored as up to three numbers
+pub trait Key {
+    fn frame(&self) -> f64;
+    fn value(&self) -> [f64; 3];
+}
+
+impl Key for VectorKey {
+    fn frame(&self) -> f64 {
+        self.frame
+    }
+
+    fn value(&self) -> [f64; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+impl Key for ColorKey {
+    fn frame(&self) -> f64 {
+        self.frame
+    }
+
+    fn value(&self) -> [f64; 3] {
+        [self.r, self.g, self.b]
+    }
+}
+
+impl Key for ScalarKey {
+    fn frame(&self) -> f64 {
+        self.frame
+    }
+
+    fn value(&self) -> [f64; 3] {
+        [self.value, 0.0, 0.0]
+    }
+}
+
+/// Interpolates the keyframes at the given frame, the keyframes may be listed in any order.
+/// Returns None if there are no keyframes.
+pub fn interpolate<K: Key>(keys: &[K], interpolation: Interpolation, frame: f64) -> Option<[f64; 3]> {
+    let mut keys: Vec<(f64, [f64; 3])> = keys.iter().map(|k| (k.frame(), k.value())).collect();
+    keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let first = keys.first()?;
+    let last = keys.last()?;
+    if frame <= first.0 {
+        return Some(first.1);
+    }
+    if frame >= last.0 {
+        return Some(last.1);
+    }
+
+    // Index of the keyframe after the frame, there is always one before it
+    let next = keys.iter().position(|k| k.0 > frame)?;
+    let (t1, p1) = keys[next - 1];
+    let (t2, p2) = keys[next];
+    let s = (frame - t1) / (t2 - t1);
+
+    let weights = match interpolation {
+        Interpolation::Linear => [0.0, 1.0 - s, s, 0.0],
+        Interpolation::Ease => {
+            let s = s * s * (3.0 - 2.0 * s);
+            [0.0, 1.0 - s, s, 0.0]
+        }
+        Interpolation::Cubic => {
+            let s2 = s * s;
+            let s3 = s2 * s;
+            [
+                (-s3 + 2.0 * s2 - s) / 2.0,
+                (3.0 * s3 - 5.0 * s2 + 2.0) / 2.0,
+                (-3.0 * s3 + 4.0 * s2 + s) / 2.0,
+                (s3 - s2) / 2.0,
+            ]
+        }
+    };
+
+    // The outer keyframes of the spline repeat the end points
+    let p0 = if next >= 2 { keys[next - 2].1 } else { p1 };
+    let p3 = keys.get(next + 1).map_or(p2, |k| k.1);
+
+    let mut value = [0.0; 3];
+    for (i, v) in value.iter_mut().enumerate() {
+        *v = weights[0] * p0[i] + weights[1] * p1[i] + weights[2] * p2[i] + weights[3] * p3[i];
+    }
+    Some(value)
+}
+
+impl Animation {
+    /// Checks that all tracks refer to existing lights and surfaces and that the materials of
+    /// animated surfaces have the animated properties. Wraps every animated surface without
+    /// a transformation of its own into a group, so it can be moved.
+    pub fn prepare(&self, lights: &Lights, surfaces: &mut Surfaces) -> io::Result<()> {
+        for track in &self.tracks {
+            match track {
+                Track::LightPosition(t) => {
+                    let count = match t.light {
+                        LightType::Point => lights.point_light.len(),
+                        LightType::Spot => lights.spot_light.len(),
+                        LightType::Ambient | LightType::Parallel => {
+                            return Err(invalid("Only point and spot lights have a position"));
+                        }
+                    };
+                    if t.index >= count {
+                        return Err(invalid(&format!("Animated light {} does not exist", t.index)));
+                    }
+                }
+                Track::LightColor(t) => {
+                    let count = match t.light {
+                        LightType::Ambient => lights.ambient_light.len(),
+                        LightType::Point => lights.point_light.len(),
+                        LightType::Parallel => lights.parallel_light.len(),
+                        LightType::Spot => lights.spot_light.len(),
+                    };
+                    if t.index >= count {
+                        return Err(invalid(&format!("Animated light {} does not exist", t.index)));
+                    }
+                }
+                Track::SurfaceTranslate(SurfaceVectorTrack { surface, .. })
+                | Track::SurfaceRotate(SurfaceVectorTrack { surface, .. })
+                | Track::SurfaceScale(SurfaceVectorTrack { surface, .. }) => {
+                    let target = surfaces.surfaces.get_mut(*surface)
+                        .ok_or_else(|| invalid(&format!("Animated surface {} does not exist", surface)))?;
+                    if !matches!(target, SurfaceType::Instance(_) | SurfaceType::Group(_)) {
+                        let placeholder = SurfaceType::Group(Box::new(Group::new(Transform::default(), Vec::new())));
+                        let leaf = std::mem::replace(target, placeholder);
+                        *target = SurfaceType::Group(Box::new(Group::new(Transform::default(), vec![leaf])));
+                    }
+                }
+                Track::MaterialColor(MaterialColorTrack { surface, .. }) => {
+                    let target = surfaces.surfaces.get_mut(*surface)
+                        .ok_or_else(|| invalid(&format!("Animated surface {} does not exist", surface)))?;
+                    let mut animatable = true;
+                    for_each_material(target, &mut |solid, _| animatable &= solid.is_some());
+                    if !animatable {
+                        return Err(invalid(&format!("The color of surface {} can only be animated on solid materials", surface)));
+                    }
+                }
+                Track::MaterialParameter(MaterialParameterTrack { surface, .. }) => {
+                    let target = surfaces.surfaces.get_mut(*surface)
+                        .ok_or_else(|| invalid(&format!("Animated surface {} does not exist", surface)))?;
+                    let mut animatable = true;
+                    for_each_material(target, &mut |solid, textured| animatable &= solid.is_some() || textured.is_some());
+                    if !animatable {
+                        return Err(invalid(&format!("The material of surface {} can only be animated on solid and textured materials", surface)));
+                    }
+                }
+                Track::CameraPosition(_) | Track::CameraLookAt(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets all animated properties of the scene to their value at the given frame
+    pub fn apply(&self, frame: f64, camera: &mut Camera, lights: &mut Lights, surfaces: &mut Surfaces) {
+        let mut placements: Vec<Placement> = Vec::new();
+
+        for track in &self.tracks {
+            match track {
+                Track::CameraPosition(t) => {
+                    if let Some([x, y, z]) = interpolate(&t.keys, t.interpolation, frame) {
+                        camera.position = Point::new(x, y, z);
+                    }
+                }
+                Track::CameraLookAt(t) => {
+                    if let Some([x, y, z]) = interpolate(&t.keys, t.interpolation, frame) {
+                        camera.look_at = Point::new(x, y, z);
+                    }
+                }
+                Track::LightPosition(t) => {
+                    if let Some([x, y, z]) = interpolate(&t.keys, t.interpolation, frame) {
+                        let position = match t.light {
+                            LightType::Point => lights.point_light.get_mut(t.index).map(|l| &mut l.position),
+                            LightType::Spot => lights.spot_light.get_mut(t.index).map(|l| &mut l.position),
+                            LightType::Ambient | LightType::Parallel => None,
+                        };
+                        if let Some(position) = position {
+                            *position = Point::new(x, y, z);
+                        }
+                    }
+                }
+                Track::LightColor(t) => {
+                    if let Some([r, g, b]) = interpolate(&t.keys, t.interpolation, frame) {
+                        let color = match t.light {
+                            LightType::Ambient => lights.ambient_light.get_mut(t.index).map(|l| &mut l.color),
+                            LightType::Point => lights.point_light.get_mut(t.index).map(|l| &mut l.color),
+                            LightType::Parallel => lights.parallel_light.get_mut(t.index).map(|l| &mut l.color),
+                            LightType::Spot => lights.spot_light.get_mut(t.index).map(|l| &mut l.color),
+                        };
+                        if let Some(color) = color {
+                            *color = Color::new(r, g, b);
+                        }
+                    }
+                }
+                Track::SurfaceTranslate(t) => {
+                    if let Some([x, y, z]) = interpolate(&t.keys, t.interpolation, frame) {
+                        Placement::find(&mut placements, t.surface).translation = Matrix4::translation(x, y, z);
+                    }
+                }
+                Track::SurfaceRotate(t) => {
+                    if let Some([x, y, z]) = interpolate(&t.keys, t.interpolation, frame) {
+                        Placement::find(&mut placements, t.surface).rotation =
+                            Matrix4::rotation_z(z) * Matrix4::rotation_y(y) * Matrix4::rotation_x(x);
+                    }
+                }
+                Track::SurfaceScale(t) => {
+                    if let Some([x, y, z]) = interpolate(&t.keys, t.interpolation, frame) {
+                        Placement::find(&mut placements, t.surface).scaling = Matrix4::scaling(x, y, z);
+                    }
+                }
+                Track::MaterialColor(t) => {
+                    if let (Some([r, g, b]), Some(surface)) = (interpolate(&t.keys, t.interpolation, frame), surfaces.surfaces.get_mut(t.surface)) {
+                        for_each_material(surface, &mut |solid, _| {
+                            if let Some(m) = solid {
+                                m.color = Color::new(r, g, b);
+                            }
+                        });
+                    }
+                }
+                Track::MaterialParameter(t) => {
+                    if let (Some([value, _, _]), Some(surface)) = (interpolate(&t.keys, t.interpolation, frame), surfaces.surfaces.get_mut(t.surface)) {
+                        for_each_material(surface, &mut |solid, textured| {
+                            if let Some(m) = solid {
+                                set_parameter(t.name, value, &mut m.phong, &mut m.reflectance, &mut m.transmittance, &mut m.refraction);
+                            }
+                            if let Some(m) = textured {
+                                set_parameter(t.name, value, &mut m.phong, &mut m.reflectance, &mut m.transmittance, &mut m.refraction);
+                            }
+                        });
+                    }
+                }
+            }
+        }
+
+        for placement in placements {
+            let matrix = placement.translation * placement.rotation * placement.scaling;
+            match surfaces.surfaces.get_mut(placement.surface) {
+                Some(SurfaceType::Instance(instance)) => instance.place(matrix),
+                Some(SurfaceType::Group(group)) => group.place(matrix),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Animated movement of a surface within a frame
+struct Placement {
+    surface: usize,
+    translation: Matrix4,
+    rotation: Matrix4,
+    scaling: Matrix4,
+}
+
+impl Placement {
+    /// Returns the placement of the surface, creating one that keeps the surface in place if needed
+    fn find(placements: &mut Vec<Placement>, surface: usize) -> &mut Placement {
+        let index = match placements.iter().position(|p| p.surface == surface) {
+            Some(index) => index,
+            None => {
+                placements.push(Placement {
+                    surface,
+                    translation: Matrix4::identity(),
+                    rotation: Matrix4::identity(),
+                    scaling: Matrix4::identity(),
+                });
+                placements.len() - 1
+            }
+        };
+        &mut placements[index]
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Calls the function with the materials of the surface.
+/// For CSG and groups these are the materials of all their children.
+fn for_each_material(
+    surface: &mut SurfaceType,
+    f: &mut dyn FnMut(&mut Option<MaterialSolid>, &mut Option<MaterialTextured>),
+) {
+    match surface {
+        SurfaceType::Sphere(sphere) => f(&mut sphere.material_solid, &mut sphere.material_textured),
+        SurfaceType::Mesh(mesh) => f(&mut mesh.material_solid, &mut mesh.material_textured),
+        SurfaceType::Instance(instance) => f(&mut instance.material_solid, &mut instance.material_textured),
+        SurfaceType::Csg(csg) => {
+            for child in &mut csg.children {
+                for_each_material(child, f);
+            }
+        }
+        SurfaceType::Group(group) => {
+            for child in &mut group.surfaces.surfaces {
+                for_each_material(child, f);
+            }
+        }
+    }
+}
+
+fn set_parameter(
+    name: MaterialParameter,
+    value: f64,
+    phong: &mut Phong,
+    reflectance: &mut Reflectance,
+    transmittance: &mut Transmittance,
+    refraction: &mut Refraction,
+) {
+    match name {
+        MaterialParameter::Ka => phong.ka = value,
+        MaterialParameter::Kd => phong.kd = value,
+        MaterialParameter::Ks => phong.ks = value,
+        MaterialParameter::Exponent => phong.exponent = value,
+        MaterialParameter::Reflectance => reflectance.r = value,
+        MaterialParameter::Transmittance => transmittance.t = value,
+        MaterialParameter::Iof => refraction.iof = value,
+    }
+}
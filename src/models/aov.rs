@@ -0,0 +1,319 @@
+use image::{Rgb, Rgb32FImage, RgbImage};
+use serde::Deserialize;
+use crate::models::color::Color;
+use crate::models::lights::Lights;
+use crate::models::material::Material;
+use crate::models::random::Random;
+use crate::models::surface::SurfaceType;
+use crate::models::vector::Vector;
+
+/// How the passes are written
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AovFormat {
+    /// One 8-bit PNG per pass with the values mapped to visible colors
+    #[default]
+    Png,
+    /// One floating point EXR per pass with the exact values
+    Exr,
+    /// One EXR file with the image and every pass as a separate layer
+    Multilayer,
+}
+
+/// Additional passes that are written alongside the image for compositing.
+/// The passes are taken from the same camera rays as the image and averaged over the samples,
+/// only the IDs are taken from the first sample of each pixel.
+#[derive(Debug, Deserialize, PartialEq, Clone, Default)]
+pub struct Aovs {
+    #[serde(default)]
+    pub format: AovFormat,
+    /// Distance from the camera to the visible surface, 0 where nothing is hit
+    #[serde(default)]
+    pub depth: bool,
+    /// World space normal of the visible surface
+    #[serde(default)]
+    pub normal: bool,
+    /// Color of the material without lighting, the background color where nothing is hit
+    #[serde(default)]
+    pub albedo: bool,
+    /// Position of the visible surface in the surfaces of the scene, counting from 1
+    #[serde(default)]
+    pub object_id: bool,
+    /// Number of the material in order of appearance in the scene, counting from 1
+    #[serde(default)]
+    pub material_id: bool,
+    /// Texture coordinates of the visible surface
+    #[serde(default)]
+    pub uv: bool,
+    /// One pass per light with its direct lighting of the visible surface
+    #[serde(default)]
+    pub lights: bool,
+}
+
+/// Content of a pass
+#[derive(Debug, Clone, PartialEq)]
+pub enum AovKind {
+    /// The rendered image itself
+    Beauty,
+    Depth,
+    Normal,
+    Albedo,
+    ObjectId,
+    MaterialId,
+    Uv,
+    /// Contribution of the light with the given index, see `light_names`
+    Light(usize, String),
+}
+
+impl AovKind {
+    /// Name of the pass, used for file and layer names
+    pub fn name(&self) -> &str {
+        match self {
+            AovKind::Beauty => "beauty",
+            AovKind::Depth => "depth",
+            AovKind::Normal => "normal",
+            AovKind::Albedo => "albedo",
+            AovKind::ObjectId => "object_id",
+            AovKind::MaterialId => "material_id",
+            AovKind::Uv => "uv",
+            AovKind::Light(_, name) => name,
+        }
+    }
+
+    /// Names of the channels written to EXR files, taken from the channels of the image in order
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            AovKind::Depth => &["Z"],
+            AovKind::ObjectId | AovKind::MaterialId => &["id"],
+            AovKind::Uv => &["U", "V"],
+            _ => &["R", "G", "B"],
+        }
+    }
+}
+
+/// A pass with its values as floating point image.
+/// Passes with less than three channels leave the remaining channels unused.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AovPass {
+    pub kind: AovKind,
+    pub image: Rgb32FImage,
+}
+
+impl AovPass {
+    /// Maps the values of the pass to colors that can be viewed in an 8-bit image
+    pub fn to_rgb(&self) -> RgbImage {
+        // Depth is spread over the range of distances in the image
+        let hits = self.image.pixels().map(|p| p[0]).filter(|d| *d > 0.0);
+        let (min_depth, max_depth) = hits.fold((f32::MAX, 0.0f32), |(min, max), d| (min.min(d), max.max(d)));
+        let depth_range = (max_depth - min_depth).max(1e-6);
+
+        RgbImage::from_fn(self.image.width(), self.image.height(), |x, y| {
+            let [r, g, b] = self.image.get_pixel(x, y).0;
+            let color = match self.kind {
+                // Near surfaces are bright, distant ones dark
+                AovKind::Depth if r > 0.0 => {
+                    let value = 1.0 - (r - min_depth) / depth_range * 0.8;
+                    [value, value, value]
+                }
+                AovKind::Depth => [0.0, 0.0, 0.0],
+                AovKind::Normal if [r, g, b] != [0.0, 0.0, 0.0] => [r * 0.5 + 0.5, g * 0.5 + 0.5, b * 0.5 + 0.5],
+                AovKind::Normal => [0.0, 0.0, 0.0],
+                AovKind::ObjectId | AovKind::MaterialId => id_color(r as u64),
+                AovKind::Uv => [r.fract(), g.fract(), 0.0],
+                _ => [r, g, b],
+            };
+            Rgb(color.map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8))
+        })
+    }
+}
+
+/// Returns a color that is easy to tell apart from the ones of other IDs, black for 0
+fn id_color(id: u64) -> [f32; 3] {
+    if id == 0 {
+        return [0.0, 0.0, 0.0];
+    }
+    let mut random = Random::new(id, 0);
+    [0; 3].map(|_| 0.2 + 0.8 * random.next_f64() as f32)
+}
+
+/// Values of the passes for a single camera ray
+#[derive(Debug, Clone, PartialEq)]
+pub struct AovSample {
+    /// Distance to the visible surface, None if nothing is hit
+    pub depth: Option<f64>,
+    pub normal: Vector,
+    pub albedo: Color,
+    pub object_id: u32,
+    pub material_id: u32,
+    pub uv: (f64, f64),
+    /// Direct lighting of every light in the order of `light_names`
+    pub lights: Vec<Color>,
+}
+
+impl AovSample {
+    /// Values for a ray that does not hit any surface
+    pub fn miss(background_color: Color) -> Self {
+        Self {
+            depth: None,
+            normal: Vector::new(0.0, 0.0, 0.0),
+            albedo: background_color,
+            object_id: 0,
+            material_id: 0,
+            uv: (0.0, 0.0),
+            lights: Vec::new(),
+        }
+    }
+}
+
+/// The selected passes of an image, filled pixel by pixel
+#[derive(Debug, Clone, PartialEq)]
+pub struct AovImages {
+    pub passes: Vec<AovPass>,
+}
+
+impl AovImages {
+    /// Creates empty images for the passes selected in the settings
+    pub fn new(settings: &Aovs, lights: &Lights, width: u32, height: u32) -> Self {
+        let mut kinds = Vec::new();
+        let selected = [
+            (settings.depth, AovKind::Depth),
+            (settings.normal, AovKind::Normal),
+            (settings.albedo, AovKind::Albedo),
+            (settings.object_id, AovKind::ObjectId),
+            (settings.material_id, AovKind::MaterialId),
+            (settings.uv, AovKind::Uv),
+        ];
+        for (enabled, kind) in selected {
+            if enabled {
+                kinds.push(kind);
+            }
+        }
+        if settings.lights {
+            kinds.extend(light_names(lights).into_iter().enumerate().map(|(i, name)| AovKind::Light(i, name)));
+        }
+
+        let passes = kinds
+            .into_iter()
+            .map(|kind| AovPass { kind, image: Rgb32FImage::new(width, height) })
+            .collect();
+        Self { passes }
+    }
+
+    /// Sets the pixel of every pass from the samples taken for it
+    pub fn set_pixel(&mut self, x: u32, y: u32, samples: &[AovSample]) {
+        let Some(first) = samples.first() else {
+            return;
+        };
+        let hits: Vec<&AovSample> = samples.iter().filter(|s| s.depth.is_some()).collect();
+        let hit_weight = 1.0 / hits.len().max(1) as f64;
+        let sample_weight = 1.0 / samples.len() as f64;
+
+        for pass in &mut self.passes {
+            let value = match &pass.kind {
+                AovKind::Beauty => continue,
+                AovKind::Depth => {
+                    let depth = hits.iter().filter_map(|s| s.depth).sum::<f64>() * hit_weight;
+                    [depth, depth, depth]
+                }
+                AovKind::Normal => {
+                    let sum = hits.iter().fold(Vector::new(0.0, 0.0, 0.0), |sum, s| sum + s.normal);
+                    let normal = if sum.length() > 0.0 { sum.normalize() } else { sum };
+                    [normal.x, normal.y, normal.z]
+                }
+                AovKind::Albedo => {
+                    let albedo = samples.iter().fold(Color::BLACK, |sum, s| sum + s.albedo) * sample_weight;
+                    [albedo.r, albedo.g, albedo.b]
+                }
+                AovKind::ObjectId => [first.object_id as f64; 3],
+                AovKind::MaterialId => [first.material_id as f64; 3],
+                AovKind::Uv => {
+                    let (u, v) = hits.iter().fold((0.0, 0.0), |sum, s| (sum.0 + s.uv.0, sum.1 + s.uv.1));
+                    [u * hit_weight, v * hit_weight, 0.0]
+                }
+                AovKind::Light(index, _) => {
+                    let light = samples
+                        .iter()
+                        .filter_map(|s| s.lights.get(*index).copied())
+                        .fold(Color::BLACK, |sum, c| sum + c) * sample_weight;
+                    [light.r, light.g, light.b]
+                }
+            };
+            pass.image.put_pixel(x, y, Rgb(value.map(|v| v as f32)));
+        }
+    }
+}
+
+/// Names of the light passes. The lights are shaded in this order:
+/// ambient lights, then parallel lights, point lights and spot lights.
+pub fn light_names(lights: &Lights) -> Vec<String> {
+    let ambient = (0..lights.ambient_light.len()).map(|i| format!("ambient_light_{}", i));
+    let parallel = (0..lights.parallel_light.len()).map(|i| format!("parallel_light_{}", i));
+    let point = (0..lights.point_light.len()).map(|i| format!("point_light_{}", i));
+    let spot = (0..lights.spot_light.len()).map(|i| format!("spot_light_{}", i));
+    ambient.chain(parallel).chain(point).chain(spot).collect()
+}
+
+/// Collects every distinct material of the surfaces in order of appearance.
+/// The position of a material in the list gives its material ID.
+pub fn material_table(surfaces: &[SurfaceType]) -> Vec<Material> {
+    let mut materials = Vec::new();
+    for surface in surfaces {
+        collect_materials(surface, &mut materials);
+    }
+    materials
+}
+
+/// Returns the material ID of the material, counting from 1, or 0 if it is not in the table
+pub fn material_id(materials: &[Material], material: &Material) -> u32 {
+    materials
+        .iter()
+        .position(|m| same_material(m, material))
+        .map_or(0, |i| i as u32 + 1)
+}
+
+fn collect_materials(surface: &SurfaceType, materials: &mut Vec<Material>) {
+    let mut add = |material: Material| {
+        if !materials.iter().any(|m| same_material(m, &material)) {
+            materials.push(material);
+        }
+    };
+
+    match surface {
+        SurfaceType::Sphere(sphere) => add(sphere.material()),
+        SurfaceType::Mesh(mesh) => add(mesh.material()),
+        SurfaceType::Csg(csg) => {
+            for child in &csg.children {
+                collect_materials(child, materials);
+            }
+        }
+        SurfaceType::Instance(instance) => match (instance.material(), &instance.shared) {
+            (Some(material), _) => add(material),
+            (None, Some(geometry)) => {
+                for child in &geometry.surfaces {
+                    collect_materials(child, materials);
+                }
+            }
+            (None, None) => {}
+        },
+        SurfaceType::Group(group) => {
+            for child in &group.surfaces.surfaces {
+                collect_materials(child, materials);
+            }
+        }
+    }
+}
+
+/// Compares materials by their parameters, textures only by name
+fn same_material(a: &Material, b: &Material) -> bool {
+    match (a, b) {
+        (Material::Textured(a), Material::Textured(b)) => {
+            a.texture.name == b.texture.name
+                && a.phong == b.phong
+                && a.reflectance == b.reflectance
+                && a.transmittance == b.transmittance
+                && a.refraction == b.refraction
+                && a.shading == b.shading
+        }
+        _ => a == b,
+    }
+}
@@ -0,0 +1,243 @@
+use std::f64::consts::PI;
+use crate::models::color::Color;
+use crate::models::vector::Vector;
+
+/// Direction chosen by sampling a BSDF
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BsdfSample {
+    /// Direction the light arrives from, pointing away from the surface
+    pub direction: Vector,
+    /// Value of the BSDF times the cosine to the normal, divided by the probability density
+    pub weight: Color,
+    /// Probability density of the direction, 1 for perfectly smooth surfaces that only scatter into one direction
+    pub pdf: f64,
+    /// If the direction was reflected or refracted by a perfectly smooth surface
+    pub specular: bool,
+}
+
+/// Scattering function of a surface. All directions point away from the surface
+/// and the normal points to the outside of the object.
+pub trait Bsdf {
+    /// Returns the part of the light arriving from `incoming` that leaves towards `outgoing`.
+    /// Perfectly smooth surfaces return black, their directions can only be sampled.
+    fn eval(&self, outgoing: Vector, incoming: Vector, normal: Vector) -> Color;
+
+    /// Chooses the direction the light arrives from in proportion to the BSDF, with a pair of
+    /// random numbers for the direction and one more to choose between reflection and refraction.
+    /// Returns None if the sampled direction is absorbed.
+    fn sample(&self, outgoing: Vector, normal: Vector, u: (f64, f64), u_lobe: f64) -> Option<BsdfSample>;
+
+    /// Returns the probability density of `sample` choosing the incoming direction
+    fn pdf(&self, outgoing: Vector, incoming: Vector, normal: Vector) -> f64;
+
+    /// Samples reflection and refraction together, each weighted by its share of the light,
+    /// instead of choosing one of them. Gives less noise for the ray tracer, which follows both.
+    fn sample_lobes(&self, outgoing: Vector, normal: Vector, u: (f64, f64)) -> Vec<BsdfSample> {
+        self.sample(outgoing, normal, u, 0.5).into_iter().collect()
+    }
+}
+
+/// Orthonormal basis around a normal, for working in a space where the normal is the z axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub tangent: Vector,
+    pub bitangent: Vector,
+    pub normal: Vector,
+}
+
+impl Frame {
+    /// Builds the basis without branches or trigonometry.
+    /// source: Duff et al., Building an Orthonormal Basis, Revisited
+    pub fn from_normal(normal: Vector) -> Self {
+        let normal = normal.normalize();
+        let sign = 1.0_f64.copysign(normal.z);
+        let a = -1.0 / (sign + normal.z);
+        let b = normal.x * normal.y * a;
+        Self {
+            tangent: Vector::new(1.0 + sign * normal.x * normal.x * a, sign * b, -sign * normal.x),
+            bitangent: Vector::new(b, sign + normal.y * normal.y * a, -normal.y),
+            normal,
+        }
+    }
+
+    pub fn to_local(&self, v: Vector) -> Vector {
+        Vector::new(v.dot(self.tangent), v.dot(self.bitangent), v.dot(self.normal))
+    }
+
+    pub fn to_world(&self, v: Vector) -> Vector {
+        self.tangent * v.x + self.bitangent * v.y + self.normal * v.z
+    }
+}
+
+/// GGX (Trowbridge-Reitz) distribution of microfacet normals with Smith masking and shadowing.
+/// Works in the local space of a `Frame`, where the macroscopic normal is the z axis.
+/// source: Walter et al., Microfacet Models for Refraction through Rough Surfaces
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ggx {
+    pub alpha: f64,
+}
+
+impl Ggx {
+    /// Below this width the surface is treated as perfectly smooth
+    const MIN_ALPHA: f64 = 1e-3;
+
+    /// Creates the distribution for a roughness between 0 and 1, which is squared
+    /// so that the surface changes evenly over the range
+    pub fn from_roughness(roughness: f64) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        Self { alpha: roughness * roughness }
+    }
+
+    /// Returns if the surface is so smooth that it only reflects and refracts in the ideal directions
+    pub fn is_smooth(&self) -> bool {
+        self.alpha < Self::MIN_ALPHA
+    }
+
+    /// Density of microfacets with the normal `h`
+    pub fn d(&self, h: Vector) -> f64 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+        let cos2 = h.z * h.z;
+        let tan2 = (1.0 - cos2) / cos2;
+        let alpha2 = self.alpha * self.alpha;
+        alpha2 / (PI * cos2 * cos2 * (alpha2 + tan2).powi(2))
+    }
+
+    fn lambda(&self, w: Vector) -> f64 {
+        let cos2 = w.z * w.z;
+        if cos2 == 0.0 {
+            return f64::INFINITY;
+        }
+        let tan2 = (1.0 - cos2) / cos2;
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Part of the microfacets with normal `h` that is visible from the direction
+    pub fn g1(&self, w: Vector, h: Vector) -> f64 {
+        if w.dot(h) * w.z <= 0.0 {
+            return 0.0;
+        }
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Part of the microfacets with normal `h` that is visible from both directions
+    pub fn g(&self, outgoing: Vector, incoming: Vector, h: Vector) -> f64 {
+        if outgoing.dot(h) * outgoing.z <= 0.0 || incoming.dot(h) * incoming.z <= 0.0 {
+            return 0.0;
+        }
+        1.0 / (1.0 + self.lambda(outgoing) + self.lambda(incoming))
+    }
+
+    /// Samples a microfacet normal that is visible from the direction, which has to be above the surface.
+    /// source: Heitz, Sampling the GGX Distribution of Visible Normals
+    pub fn sample_visible_normal(&self, w: Vector, (u1, u2): (f64, f64)) -> Vector {
+        let stretched = Vector::new(self.alpha * w.x, self.alpha * w.y, w.z).normalize();
+        let length2 = stretched.x * stretched.x + stretched.y * stretched.y;
+        let t1 = if length2 > 0.0 {
+            Vector::new(-stretched.y, stretched.x, 0.0) / length2.sqrt()
+        } else {
+            Vector::new(1.0, 0.0, 0.0)
+        };
+        let t2 = stretched.cross(t1);
+
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + stretched.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let p3 = (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+        let h = t1 * p1 + t2 * p2 + stretched * p3;
+
+        Vector::new(self.alpha * h.x, self.alpha * h.y, h.z.max(0.0)).normalize()
+    }
+
+    /// Probability density of `sample_visible_normal` choosing the normal `h`
+    pub fn visible_pdf(&self, w: Vector, h: Vector) -> f64 {
+        if w.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(w, h) * w.dot(h).max(0.0) * self.d(h) / w.z
+    }
+
+    /// Microfacet reflection between two directions above the surface, without the Fresnel term
+    pub fn reflection(&self, wo: Vector, wi: Vector) -> f64 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalize();
+        self.d(h) * self.g(wo, wi, h) / (4.0 * wo.z * wi.z)
+    }
+
+    /// Probability density of reflecting `wo` about a visible normal into `wi`
+    pub fn reflection_pdf(&self, wo: Vector, wi: Vector) -> f64 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalize();
+        self.visible_pdf(wo, h) / (4.0 * wo.dot(h))
+    }
+}
+
+/// Chooses a direction above the surface with a density proportional to the cosine to the normal,
+/// in the local space of a `Frame`
+pub fn sample_cosine_hemisphere((u1, u2): (f64, f64)) -> Vector {
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    Vector::new(r * phi.cos(), r * phi.sin(), (1.0 - u1).max(0.0).sqrt())
+}
+
+/// Schlick's approximation of the Fresnel reflectance, with the reflectance `f0` at normal incidence
+pub fn fresnel_schlick(f0: Color, cos_i: f64) -> Color {
+    let weight = (1.0 - cos_i.clamp(0.0, 1.0)).powi(5);
+    f0 * (1.0 - weight) + Color::WHITE * weight
+}
+
+/// Reflects the direction about the normal, both pointing away from the surface
+pub fn reflect(w: Vector, normal: Vector) -> Vector {
+    normal * (2.0 * w.dot(normal)) - w
+}
+
+/// Refracts the direction through a surface with the given normal on the side of `w`,
+/// where `eta` is the index of refraction behind the surface divided by the one in front.
+/// Returns None for total internal reflection.
+pub fn refract(w: Vector, normal: Vector, eta: f64) -> Option<Vector> {
+    let cos_i = w.dot(normal);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-w / eta + normal * (cos_i / eta - cos_t))
+}
+
+/// Fresnel reflectance of a dielectric for light arriving at the cosine `cos_i` to the normal,
+/// with `eta` the index of refraction behind the surface divided by the one in front
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
+
+/// Fresnel reflectance of a conductor with the complex index of refraction `eta + i k`
+/// source: https://seblagarde.wordpress.com/2013/04/29/memo-on-fresnel-equations/
+pub fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i.clamp(0.0, 1.0) * a;
+    let perpendicular = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let parallel = perpendicular * (t3 - t4) / (t3 + t4);
+    (parallel + perpendicular) / 2.0
+}
@@ -0,0 +1,96 @@
+use std::path::Path;
+use image::ImageError;
+use serde::Deserialize;
+use crate::models::material::Texture;
+use crate::models::vector::Vector;
+
+/// Tangent-space normal map. The red, green and blue of a texel are the shading normal
+/// along the tangent (towards larger u), the bitangent (towards larger v) and the normal of the surface.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct NormalMap {
+    pub texture: Texture,
+    /// 0 keeps the normal of the surface, 1 takes the normals of the map
+    #[serde(default = "default_strength")]
+    pub strength: f64,
+}
+
+/// Grayscale height map, whose slopes tilt the shading normal as if the surface was bumpy
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct BumpMap {
+    pub texture: Texture,
+    /// Height of white above black, in units of the scene
+    #[serde(default = "default_height")]
+    pub height: f64,
+}
+
+fn default_strength() -> f64 {
+    1.0
+}
+
+fn default_height() -> f64 {
+    0.02
+}
+
+/// Derivatives of a point on a surface by the texture coordinates u and v
+pub type Tangents = (Vector, Vector);
+
+impl NormalMap {
+    /// Returns the shading normal at the texture coordinates.
+    /// The normal stays the same as long as the texture is not loaded.
+    pub fn perturb(&self, normal: Vector, (dpdu, dpdv): Tangents, uv: (f64, f64)) -> Vector {
+        let Some(texel) = self.texture.sample(uv) else {
+            return normal;
+        };
+
+        // Orthonormal frame around the normal, keeping the handedness of the texture coordinates
+        let tangent = dpdu - normal * normal.dot(dpdu);
+        if tangent.length() < 1e-12 {
+            return normal;
+        }
+        let tangent = tangent.normalize();
+        let bitangent = normal.cross(tangent);
+        let bitangent = if bitangent.dot(dpdv) < 0.0 { -bitangent } else { bitangent };
+
+        let x = (2.0 * texel.r - 1.0) * self.strength;
+        let y = (2.0 * texel.g - 1.0) * self.strength;
+        let z = 1.0 + (2.0 * texel.b - 2.0) * self.strength;
+        (tangent * x + bitangent * y + normal * z.max(1e-3)).normalize()
+    }
+}
+
+impl BumpMap {
+    /// Returns the shading normal at the texture coordinates, the normal of the surface displaced by the heights.
+    /// The normal stays the same as long as the texture is not loaded.
+    pub fn perturb(&self, normal: Vector, (dpdu, dpdv): Tangents, (u, v): (f64, f64)) -> Vector {
+        let Some(data) = self.texture.data.as_ref().filter(|data| data.width() > 0 && data.height() > 0) else {
+            return normal;
+        };
+        let height = |uv: (f64, f64)| {
+            self.texture.sample(uv).map_or(0.0, |c| (c.r + c.g + c.b) / 3.0) * self.height
+        };
+
+        // Slopes of the heights over one texel to either side
+        let (du, dv) = (1.0 / data.width() as f64, 1.0 / data.height() as f64);
+        let dhdu = (height((u + du, v)) - height((u - du, v))) / (2.0 * du);
+        let dhdv = (height((u, v + dv)) - height((u, v - dv))) / (2.0 * dv);
+
+        // source: Pharr, Jakob and Humphreys, Physically Based Rendering, 10.5.1 Bump Mapping
+        let displaced = (dpdu + normal * dhdu).cross(dpdv + normal * dhdv);
+        if displaced.length() < 1e-12 {
+            return normal;
+        }
+        let displaced = displaced.normalize();
+        if displaced.dot(normal) < 0.0 { -displaced } else { displaced }
+    }
+}
+
+/// Loads the textures of the normal and bump map, if the material has them
+pub fn load_maps(normal_map: &mut Option<NormalMap>, bump_map: &mut Option<BumpMap>, base_path: &Path) -> Result<(), ImageError> {
+    if let Some(map) = normal_map {
+        map.texture.load(base_path)?;
+    }
+    if let Some(map) = bump_map {
+        map.texture.load(base_path)?;
+    }
+    Ok(())
+}
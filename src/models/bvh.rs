@@ -0,0 +1,196 @@
+use crate::models::point::Point;
+use crate::models::ray::Ray;
+use crate::models::triangle::{Triangle, TriangleHit};
+
+/// Maximum number of triangles stored in a leaf node
+const LEAF_SIZE: usize = 4;
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Aabb {
+    pub fn empty() -> Self {
+        Self {
+            min: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_triangle(triangle: &Triangle) -> Self {
+        Self::empty()
+            .grow(triangle.v0)
+            .grow(triangle.v1)
+            .grow(triangle.v2)
+    }
+
+    /// Returns the box extended to contain the point
+    pub fn grow(self, p: Point) -> Self {
+        Self {
+            min: Point::new(self.min.x.min(p.x), self.min.y.min(p.y), self.min.z.min(p.z)),
+            max: Point::new(self.max.x.max(p.x), self.max.y.max(p.y), self.max.z.max(p.z)),
+        }
+    }
+
+    pub fn union(self, other: Aabb) -> Self {
+        self.grow(other.min).grow(other.max)
+    }
+
+    pub fn centroid(&self) -> Point {
+        Point::new(
+            (self.min.x + self.max.x) * 0.5,
+            (self.min.y + self.max.y) * 0.5,
+            (self.min.z + self.max.z) * 0.5,
+        )
+    }
+
+    /// Returns the index of the axis along which the box is the widest
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.max - self.min;
+        if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Slab test, returns true if the ray passes through the box within [t_min, t_max]
+    pub fn hit(&self, ray: &Ray, t_max: f64) -> bool {
+        let mut t0 = ray.t_min;
+        let mut t1 = t_max;
+
+        let axes = [
+            (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
+        ];
+
+        for (origin, direction, min, max) in axes {
+            let inv = 1.0 / direction;
+            let mut near = (min - origin) * inv;
+            let mut far = (max - origin) * inv;
+            if near > far {
+                std::mem::swap(&mut near, &mut far);
+            }
+            // NaN (ray parallel to and on the slab) keeps the previous bound
+            t0 = if near > t0 { near } else { t0 };
+            t1 = if far < t1 { far } else { t1 };
+            if t0 > t1 {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct BvhNode {
+    bounds: Aabb,
+    /// Index of the first child for inner nodes or of the first triangle for leaves
+    first: usize,
+    /// Number of triangles, zero for inner nodes
+    count: usize,
+}
+
+/// Bounding volume hierarchy over the triangles of a mesh.
+/// The triangles are not stored in the tree, only their indices.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+}
+
+impl Bvh {
+    /// Builds the hierarchy by recursively splitting the triangles at the
+    /// median of their centroids along the longest axis
+    pub fn build(triangles: &[Triangle]) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            indices: (0..triangles.len()).collect(),
+        };
+
+        if !triangles.is_empty() {
+            let bounds: Vec<Aabb> = triangles.iter().map(Aabb::from_triangle).collect();
+            bvh.nodes.push(BvhNode { bounds: Aabb::empty(), first: 0, count: 0 });
+            bvh.build_node(0, 0, triangles.len(), &bounds);
+        }
+
+        bvh
+    }
+
+    fn build_node(&mut self, node: usize, start: usize, end: usize, bounds: &[Aabb]) {
+        let node_bounds = self.indices[start..end]
+            .iter()
+            .fold(Aabb::empty(), |acc, &i| acc.union(bounds[i]));
+        self.nodes[node].bounds = node_bounds;
+
+        if end - start <= LEAF_SIZE {
+            self.nodes[node].first = start;
+            self.nodes[node].count = end - start;
+            return;
+        }
+
+        let centroid_bounds = self.indices[start..end]
+            .iter()
+            .fold(Aabb::empty(), |acc, &i| acc.grow(bounds[i].centroid()));
+        let axis = centroid_bounds.longest_axis();
+        let key = |i: &usize| {
+            let c = bounds[*i].centroid();
+            [c.x, c.y, c.z][axis]
+        };
+
+        let mid = start + (end - start) / 2;
+        self.indices[start..end].select_nth_unstable_by(mid - start, |a, b| key(a).total_cmp(&key(b)));
+
+        let left = self.nodes.len();
+        self.nodes.push(BvhNode { bounds: Aabb::empty(), first: 0, count: 0 });
+        self.nodes.push(BvhNode { bounds: Aabb::empty(), first: 0, count: 0 });
+        self.nodes[node].first = left;
+
+        self.build_node(left, start, mid, bounds);
+        self.build_node(left + 1, mid, end, bounds);
+    }
+
+    /// Finds the closest triangle hit by the ray and returns its index with the hit.
+    /// The intersection data is only built by the caller, once for the closest hit.
+    pub fn intersect(&self, triangles: &[Triangle], ray: &Ray) -> Option<(usize, TriangleHit)> {
+        let mut closest: Option<(usize, TriangleHit)> = None;
+        let mut stack = Vec::new();
+
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let t_max = closest.map_or(ray.t_max, |(_, hit)| hit.t);
+
+            if !node.bounds.hit(ray, t_max) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(node.first);
+                stack.push(node.first + 1);
+                continue;
+            }
+
+            for &i in &self.indices[node.first..node.first + node.count] {
+                // Only hits in front of the closest one found so far are of interest
+                let t_max = closest.map_or(ray.t_max, |(_, hit)| hit.t);
+                let narrowed = Ray { t_max, ..*ray };
+                if let Some(hit) = triangles[i].hit(&narrowed) {
+                    closest = Some((i, hit));
+                }
+            }
+        }
+
+        closest
+    }
+}
@@ -1,7 +1,10 @@
 use serde::Deserialize;
+use crate::models::adaptive::Adaptive;
 use crate::models::point::Point;
 use crate::models::vector::Vector;
 use crate::models::ray::Ray;
+use crate::models::sampler::SamplerSettings;
+use crate::models::spectrum::Spectral;
 
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct Camera {
@@ -12,6 +15,25 @@ pub struct Camera {
     pub horizontal_fov: Fov,
     pub resolution: Resolution,
     pub max_bounces: MaxBounces,
+    #[serde(default)]
+    pub samples: Samples,
+    #[serde(default)]
+    pub lens: Option<Lens>,
+    #[serde(default)]
+    pub projection: Projection,
+    #[serde(default)]
+    pub shutter: Shutter,
+    #[serde(default)]
+    pub region: Option<Region>,
+    /// Takes only as many samples per pixel as needed, up to the number of samples
+    #[serde(default)]
+    pub adaptive: Option<Adaptive>,
+    /// Where the random numbers of the samples come from
+    #[serde(default)]
+    pub sampler: SamplerSettings,
+    /// Traces a wavelength per sample, for the dispersion of light
+    #[serde(default)]
+    pub spectral: Option<Spectral>,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -30,38 +52,344 @@ pub struct MaxBounces {
     pub n: u32,
 }
 
+/// Number of samples traced per pixel
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Samples {
+    pub n: u32,
+}
+
+impl Default for Samples {
+    fn default() -> Self {
+        Self { n: 1 }
+    }
+}
+
+/// Time interval in which the shutter is open. Objects moving
+/// during this interval appear blurred along their path.
+#[derive(Debug, Deserialize, PartialEq, Default)]
+pub struct Shutter {
+    pub open: f64,
+    pub close: f64,
+}
+
+impl Shutter {
+    /// Maps a uniform sample in [0, 1) to a point in time while the shutter is open
+    pub fn sample_time(&self, sample: f64) -> f64 {
+        self.open + (self.close - self.open) * sample
+    }
+}
+
+/// Maps pixels to ray directions. Defaults to a perspective projection.
+#[derive(Debug, Deserialize, PartialEq, Default)]
+pub struct Projection {
+    #[serde(rename = "type", default)]
+    pub kind: ProjectionType,
+    /// Width of the visible area in world units for an orthographic projection
+    #[serde(default)]
+    pub view_width: Option<f64>,
+    #[serde(default)]
+    pub mapping: FisheyeMapping,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectionType {
+    #[default]
+    Perspective,
+    Orthographic,
+    Fisheye,
+    Equirectangular,
+}
+
+/// How the angle to the viewing direction maps to the distance from the image center.
+/// For a fisheye projection, `horizontal_fov` is the angle at the left and right image border.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FisheyeMapping {
+    /// r = f * theta
+    #[default]
+    Equidistant,
+    /// r = 2 * f * sin(theta / 2)
+    Equisolid,
+}
+
+/// Part of the image that is rendered, given either as a rectangle in pixels
+/// or as a window of the image between 0 and 1, both measured from the top left corner.
+/// The pixel rectangle is used if `width` and `height` are given.
+#[derive(Debug, Deserialize, PartialEq, Clone, Default)]
+pub struct Region {
+    #[serde(default)]
+    pub x: u32,
+    #[serde(default)]
+    pub y: u32,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub left: Option<f64>,
+    #[serde(default)]
+    pub top: Option<f64>,
+    #[serde(default)]
+    pub right: Option<f64>,
+    #[serde(default)]
+    pub bottom: Option<f64>,
+    /// Outputs only the region instead of a full-size image with the background around it
+    #[serde(default)]
+    pub crop: bool,
+}
+
+impl Region {
+    /// Returns the left and top pixel and the size of the region, limited to the image
+    /// and at least one pixel large
+    pub fn pixels(&self, resolution: &Resolution) -> (u32, u32, u32, u32) {
+        let (image_width, image_height) = (resolution.horizontal.max(1), resolution.vertical.max(1));
+
+        let (x0, y0, x1, y1) = match (self.width, self.height) {
+            (Some(width), Some(height)) => (self.x, self.y, self.x.saturating_add(width), self.y.saturating_add(height)),
+            _ => {
+                let to_pixel = |v: Option<f64>, default: f64, size: u32| {
+                    (v.unwrap_or(default).clamp(0.0, 1.0) * size as f64).round() as u32
+                };
+                (
+                    to_pixel(self.left, 0.0, image_width),
+                    to_pixel(self.top, 0.0, image_height),
+                    to_pixel(self.right, 1.0, image_width),
+                    to_pixel(self.bottom, 1.0, image_height),
+                )
+            }
+        };
+
+        let x0 = x0.min(image_width - 1);
+        let y0 = y0.min(image_height - 1);
+        let x1 = x1.clamp(x0 + 1, image_width);
+        let y1 = y1.clamp(y0 + 1, image_height);
+        (x0, y0, x1 - x0, y1 - y0)
+    }
+}
+
+/// Thin lens for depth of field. Without a lens the camera is a pinhole camera.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Lens {
+    /// Radius of the aperture, zero keeps everything in focus
+    pub aperture: f64,
+    /// Distance from the camera to the plane in focus
+    #[serde(default)]
+    pub focus_distance: Option<f64>,
+    /// Point in focus, used instead of the focus distance
+    #[serde(default)]
+    pub focus_point: Option<Point>,
+    #[serde(default)]
+    pub bokeh: Bokeh,
+    /// Number of aperture blades for a polygonal bokeh
+    #[serde(default = "Lens::default_blades")]
+    pub blades: u32,
+    /// Rotation of the aperture polygon in degrees
+    #[serde(default)]
+    pub rotation: f64,
+}
+
+/// Shape of the aperture, which determines the shape of out of focus highlights
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Bokeh {
+    #[default]
+    Disk,
+    Polygon,
+}
+
+impl Lens {
+    fn default_blades() -> u32 {
+        6
+    }
+
+    /// Maps a uniform sample in [0, 1)^2 to a point on the aperture (in units of the aperture radius)
+    pub fn sample_aperture(&self, sample: (f64, f64)) -> (f64, f64) {
+        match self.bokeh {
+            Bokeh::Disk => Self::sample_disk(sample),
+            Bokeh::Polygon => self.sample_polygon(sample),
+        }
+    }
+
+    /// Concentric mapping of the unit square to the unit disk, keeps the samples evenly spread
+    /// source: Shirley & Chiu, A Low Distortion Map Between Disk and Square
+    fn sample_disk((u, v): (f64, f64)) -> (f64, f64) {
+        let a = 2.0 * u - 1.0;
+        let b = 2.0 * v - 1.0;
+
+        if a == 0.0 && b == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, std::f64::consts::FRAC_PI_4 * (b / a))
+        } else {
+            (b, std::f64::consts::FRAC_PI_2 - std::f64::consts::FRAC_PI_4 * (a / b))
+        };
+
+        (r * theta.cos(), r * theta.sin())
+    }
+
+    /// Uniformly samples a regular polygon inscribed in the unit circle.
+    /// The first coordinate picks one of the triangles between the center and an edge,
+    /// the remaining part of it and the second coordinate sample that triangle.
+    fn sample_polygon(&self, (u, v): (f64, f64)) -> (f64, f64) {
+        let blades = self.blades.max(3) as f64;
+        let scaled = u * blades;
+        let sector = scaled.floor();
+        let u = scaled - sector;
+
+        let step = 2.0 * std::f64::consts::PI / blades;
+        let start = self.rotation.to_radians() + sector * step;
+        let (p0, p1) = ((start.cos(), start.sin()), ((start + step).cos(), (start + step).sin()));
+
+        // Uniform point in the triangle (center, p0, p1)
+        let a = u.sqrt();
+        let x = a * ((1.0 - v) * p0.0 + v * p1.0);
+        let y = a * ((1.0 - v) * p0.1 + v * p1.1);
+        (x, y)
+    }
+}
+
 impl Camera {
-    /// Generates a ray for a given pixel (u, v) with camera transformations applied
-    pub fn generate_ray(&self, u: u32, v: u32) -> Ray {
+    /// Generates a ray for a given pixel (u, v) with camera transformations applied.
+    /// Returns None if the pixel lies outside the image circle of a fisheye projection.
+    pub fn generate_ray(&self, u: u32, v: u32) -> Option<Ray> {
+        self.generate_ray_sample(u, v, (0.5, 0.5), (0.5, 0.5))
+    }
+
+    /// Generates a ray through a position inside the pixel (u, v).
+    /// `pixel_sample` is the offset inside the pixel and `lens_sample` the position
+    /// on the lens, both in [0, 1)^2. The lens position is ignored for a pinhole camera
+    /// and for the panoramic (fisheye and equirectangular) projections.
+    pub fn generate_ray_sample(&self, u: u32, v: u32, pixel_sample: (f64, f64), lens_sample: (f64, f64)) -> Option<Ray> {
         // Calculate normalized pixel coordinates
-        let x_n = (u as f64 + 0.5) / self.resolution.horizontal as f64;
-        let y_n = (v as f64 + 0.5) / self.resolution.vertical as f64;
+        let x_n = (u as f64 + pixel_sample.0) / self.resolution.horizontal as f64;
+        let y_n = (v as f64 + pixel_sample.1) / self.resolution.vertical as f64;
+        let aspect = self.resolution.vertical as f64 / self.resolution.horizontal as f64;
 
-        // Calculate FOV components
-        let fov_x = self.horizontal_fov.angle.to_radians();
-        let fov_y = fov_x * (self.resolution.vertical as f64 / self.resolution.horizontal as f64);
+        // Screen coordinates in [-1, 1], y pointing up
+        let x_s = 2.0 * x_n - 1.0;
+        let y_s = 1.0 - 2.0 * y_n;
 
-        // Compute coordinates in camera space
-        let x_i = (2.0 * x_n - 1.0) * fov_x.tan();
-        let y_i = (1.0 - 2.0 * y_n) * fov_y.tan();
+        // Origin offset and direction in camera space
+        let (offset_cam, direction_cam) = match self.projection.kind {
+            ProjectionType::Perspective => ((0.0, 0.0), self.perspective_direction(x_s, y_s, aspect)),
+            ProjectionType::Orthographic => {
+                let half_width = self.projection.view_width.unwrap_or(2.0) * 0.5;
+                ((x_s * half_width, y_s * half_width * aspect), Vector::new(0.0, 0.0, -1.0))
+            }
+            ProjectionType::Fisheye => ((0.0, 0.0), self.fisheye_direction(x_s, y_s * aspect)?),
+            ProjectionType::Equirectangular => ((0.0, 0.0), Self::equirectangular_direction(x_s, y_s)),
+        };
 
-        // Compute camera basis vectors (coordinate system)
-        // source: tutorial 2 - page 25
-        let z = (self.position - self.look_at).normalize();
-        let x = self.up.cross(z).normalize();
-        let y = z.cross(x).normalize();
+        let (x, y, z) = self.basis();
 
-        // Transform camera space direction to world space
-        let direction_cam = Vector::new(x_i, y_i, -1.0);
+        // Transform camera space position and direction to world space
+        let origin = self.position + x * offset_cam.0 + y * offset_cam.1;
         let direction_world = x * direction_cam.x
             + y * direction_cam.y
             + z * direction_cam.z;
 
-        Ray::new(
-            self.position,
-            direction_world.normalize(),
+        let lens = self.lens.as_ref().filter(|lens| lens.aperture > 0.0);
+        let planar = matches!(self.projection.kind, ProjectionType::Perspective | ProjectionType::Orthographic);
+
+        let Some(lens) = lens.filter(|_| planar) else {
+            return Some(Ray::new(
+                origin,
+                direction_world.normalize(),
+                0.01,
+                f64::INFINITY,
+            ));
+        };
+
+        // Thin lens: all rays through the pixel meet again on the focus plane.
+        // The camera space direction has z = -1, so scaling it by the focus distance
+        // ends on the plane at that distance in front of the camera.
+        // source: Physically Based Rendering 6.2.3
+        let focus = origin + direction_world * self.focus_distance(lens);
+
+        let (lens_x, lens_y) = lens.sample_aperture(lens_sample);
+        let lens_origin = origin + x * (lens_x * lens.aperture) + y * (lens_y * lens.aperture);
+
+        Some(Ray::new(
+            lens_origin,
+            (focus - lens_origin).normalize(),
             0.01,
             f64::INFINITY,
+        ))
+    }
+
+    /// Direction through the image plane at distance 1 in front of the camera
+    fn perspective_direction(&self, x_s: f64, y_s: f64, aspect: f64) -> Vector {
+        // Calculate FOV components
+        let fov_x = self.horizontal_fov.angle.to_radians();
+        let fov_y = fov_x * aspect;
+
+        // Compute coordinates in camera space
+        let x_i = x_s * fov_x.tan();
+        let y_i = y_s * fov_y.tan();
+
+        Vector::new(x_i, y_i, -1.0)
+    }
+
+    /// Direction for a fisheye lens, the image circle touches the left and right border.
+    /// source: https://en.wikipedia.org/wiki/Fisheye_lens#Mapping_function
+    fn fisheye_direction(&self, x_s: f64, y_s: f64) -> Option<Vector> {
+        let r = (x_s * x_s + y_s * y_s).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+
+        let max_theta = self.horizontal_fov.angle.to_radians();
+        let theta = match self.projection.mapping {
+            FisheyeMapping::Equidistant => r * max_theta,
+            FisheyeMapping::Equisolid => 2.0 * (r * (max_theta * 0.5).sin()).clamp(-1.0, 1.0).asin(),
+        };
+        if theta > std::f64::consts::PI {
+            return None;
+        }
+
+        let phi = y_s.atan2(x_s);
+        Some(Vector::new(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            -theta.cos(),
+        ))
+    }
+
+    /// Direction for a 360 degree panorama, x maps to the longitude and y to the latitude
+    fn equirectangular_direction(x_s: f64, y_s: f64) -> Vector {
+        let longitude = x_s * std::f64::consts::PI;
+        let latitude = y_s * std::f64::consts::FRAC_PI_2;
+
+        Vector::new(
+            longitude.sin() * latitude.cos(),
+            latitude.sin(),
+            -longitude.cos() * latitude.cos(),
         )
     }
-}
\ No newline at end of file
+
+    /// Compute camera basis vectors (coordinate system)
+    /// source: tutorial 2 - page 25
+    pub fn basis(&self) -> (Vector, Vector, Vector) {
+        let z = (self.position - self.look_at).normalize();
+        let x = self.up.cross(z).normalize();
+        let y = z.cross(x).normalize();
+        (x, y, z)
+    }
+
+    /// Distance of the focus plane, measured along the viewing direction.
+    /// Defaults to the distance of the look at point.
+    fn focus_distance(&self, lens: &Lens) -> f64 {
+        let view_dir = (self.look_at - self.position).normalize();
+
+        if let Some(point) = lens.focus_point {
+            (point - self.position).dot(view_dir)
+        } else if let Some(distance) = lens.focus_distance {
+            distance
+        } else {
+            (self.look_at - self.position).dot(view_dir)
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+
+/// Saves the state of the render to a file while rendering, so an interrupted render
+/// can be resumed. If the checkpoint file exists when rendering starts, the render
+/// continues from it and gives the same image as an uninterrupted run.
+/// The file is removed once the render is finished.
+/// Without `seconds` and `passes` the checkpoint is saved every `DEFAULT_SECONDS`.
+#[derive(Debug, Deserialize, PartialEq, Clone, Default)]
+pub struct Checkpoint {
+    /// Name of the checkpoint file in the output folder, by default named after the output file
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Saves the checkpoint whenever this many seconds passed since the last save
+    #[serde(default)]
+    pub seconds: Option<f64>,
+    /// Saves the checkpoint after every this many passes
+    #[serde(default)]
+    pub passes: Option<u32>,
+}
+
+/// Seconds between saves if neither an interval in seconds nor in passes is set
+pub const DEFAULT_SECONDS: f64 = 60.0;
+
+impl Checkpoint {
+    /// Returns the path of the checkpoint file for the given output file
+    pub fn path(&self, output_file: &str) -> PathBuf {
+        let name = match &self.file {
+            Some(file) => PathBuf::from(file),
+            None => Path::new(output_file).with_extension("checkpoint"),
+        };
+        Path::new("output").join(name)
+    }
+
+    /// Returns if the checkpoint should be saved after the given pass
+    pub fn should_save(&self, pass: u32, seconds_since_save: f64) -> bool {
+        if self.passes.is_none() && self.seconds.is_none() {
+            return seconds_since_save >= DEFAULT_SECONDS;
+        }
+        self.passes.is_some_and(|n| n > 0 && pass.is_multiple_of(n))
+            || self.seconds.is_some_and(|s| seconds_since_save >= s)
+    }
+}
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use crate::models::intersection::Intersection;
+use crate::models::ray::Ray;
+use crate::models::surface::{Surface, SurfaceType};
+use crate::models::vector::Vector;
+
+/// Hits of one child closer than this along the ray cross its boundary only once,
+/// as where the ray passes through an edge shared by two triangles of a mesh
+const COINCIDENT_HITS: f64 = 1e-7;
+
+/// Boolean operation used to combine the children of a CSG node
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// Constructive solid geometry node combining closed child surfaces.
+/// For a difference, every child after the first is subtracted from the first one.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Csg {
+    pub operation: CsgOperation,
+    #[serde(rename = "$value", default)]
+    pub children: Vec<SurfaceType>,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, children: Vec<SurfaceType>) -> Self {
+        Self { operation, children }
+    }
+
+    /// Returns true if a point lies inside the combined solid,
+    /// given whether it lies inside each of the children.
+    fn is_inside(&self, inside: &[bool]) -> bool {
+        match self.operation {
+            CsgOperation::Union => inside.iter().any(|&i| i),
+            CsgOperation::Intersection => !inside.is_empty() && inside.iter().all(|&i| i),
+            CsgOperation::Difference => match inside.split_first() {
+                Some((first, rest)) => *first && !rest.iter().any(|&i| i),
+                None => false,
+            },
+        }
+    }
+}
+
+impl Surface for Csg {
+    /// Returns the closest boundary point of the combined solid
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.intersect_all(ray).into_iter().next()
+    }
+
+    /// Walks along the ray through the sorted entry and exit points of all children
+    /// and keeps the ones where the ray crosses the boundary of the combined solid.
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        // The children are intersected along the whole ray so that the
+        // inside state at the start of the ray can be derived from the first hit.
+        let probe = Ray { t_max: f64::INFINITY, ..*ray };
+
+        let mut inside = vec![false; self.children.len()];
+        let mut events: Vec<(usize, Intersection)> = Vec::new();
+
+        for (index, child) in self.children.iter().enumerate() {
+            let hits = merge_coincident(child.intersect_all(&probe), ray.direction);
+
+            // If the first hit leaves the child, the ray starts inside of it
+            if let Some(first) = hits.first() {
+                inside[index] = first.normal.dot(ray.direction) > 0.0;
+            }
+
+            events.extend(hits.into_iter().map(|hit| (index, hit)));
+        }
+
+        events.sort_by(|a, b| a.1.t.total_cmp(&b.1.t));
+
+        let mut result = Vec::new();
+        let mut combined = self.is_inside(&inside);
+
+        for (index, mut hit) in events {
+            if hit.t >= ray.t_max {
+                break;
+            }
+
+            // Entering a child if the ray travels against the surface normal
+            inside[index] = hit.normal.dot(ray.direction) < 0.0;
+
+            let now_inside = self.is_inside(&inside);
+            if now_inside != combined {
+                // Subtracted children contribute their inner side to the boundary
+                if self.operation == CsgOperation::Difference && index > 0 {
+                    hit.normal = -hit.normal;
+                }
+                result.push(hit);
+                combined = now_inside;
+            }
+        }
+
+        result
+    }
+}
+
+/// Merges runs of hits sorted by distance that lie within `COINCIDENT_HITS` of each other.
+/// A run keeps its first hit that agrees with the majority of the run about entering or leaving,
+/// a run that enters as often as it leaves only touches the surface and is dropped.
+fn merge_coincident(hits: Vec<Intersection>, direction: Vector) -> Vec<Intersection> {
+    let mut merged = Vec::with_capacity(hits.len());
+    let mut hits = hits.into_iter().peekable();
+
+    while let Some(first) = hits.next() {
+        let mut run = vec![first];
+        while let Some(next) = hits.next_if(|hit| hit.t - run[0].t < COINCIDENT_HITS) {
+            run.push(next);
+        }
+
+        let entering = |hit: &Intersection| hit.normal.dot(direction) < 0.0;
+        let entries = run.iter().filter(|hit| entering(hit)).count();
+        let exits = run.len() - entries;
+        if entries != exits {
+            let enters = entries > exits;
+            merged.extend(run.into_iter().find(|hit| entering(hit) == enters));
+        }
+    }
+
+    merged
+}
@@ -0,0 +1,169 @@
+use image::{Rgb, Rgb32FImage};
+use serde::Deserialize;
+
+/// Removes noise from the rendered image with a joint bilateral filter.
+/// Each pixel is averaged with the pixels around it that show the same surface,
+/// judged by the albedo, normal and depth passes, and whose colors only differ by noise,
+/// judged by the variance of the pixels. Needs at least two samples per pixel to estimate the noise.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct Denoiser {
+    /// Number of pixels in every direction that are averaged
+    #[serde(default = "default_radius")]
+    pub radius: u32,
+    /// How much color difference is still treated as noise, in multiples of the noise level
+    #[serde(default = "default_strength")]
+    pub strength: f64,
+    /// Tolerated difference between the normals of two pixels
+    #[serde(default = "default_normal_sigma")]
+    pub normal_sigma: f64,
+    /// Tolerated difference between the albedos of two pixels
+    #[serde(default = "default_albedo_sigma")]
+    pub albedo_sigma: f64,
+    /// Tolerated difference between the depths of two pixels, relative to their depth
+    #[serde(default = "default_depth_sigma")]
+    pub depth_sigma: f64,
+}
+
+fn default_radius() -> u32 {
+    5
+}
+
+fn default_strength() -> f64 {
+    2.0
+}
+
+fn default_normal_sigma() -> f64 {
+    0.5
+}
+
+fn default_albedo_sigma() -> f64 {
+    0.2
+}
+
+fn default_depth_sigma() -> f64 {
+    0.1
+}
+
+/// Passes that tell the denoiser which pixels show the same surface. They only depend on
+/// the scene, so they are rendered once and used for every image written during a render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guides {
+    pub albedo: Rgb32FImage,
+    pub normal: Rgb32FImage,
+    /// Distance to the camera in the first channel
+    pub depth: Rgb32FImage,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            radius: default_radius(),
+            strength: default_strength(),
+            normal_sigma: default_normal_sigma(),
+            albedo_sigma: default_albedo_sigma(),
+            depth_sigma: default_depth_sigma(),
+        }
+    }
+}
+
+impl Denoiser {
+    /// Filters the image. All images must have the same size, the depth is read from the first channel.
+    pub fn denoise(
+        &self,
+        color: &Rgb32FImage,
+        variance: &Rgb32FImage,
+        albedo: &Rgb32FImage,
+        normal: &Rgb32FImage,
+        depth: &Rgb32FImage,
+    ) -> Rgb32FImage {
+        let (width, height) = color.dimensions();
+        let variance = &Self::smooth(variance);
+        let radius = self.radius as i64;
+        let spatial_sigma = (self.radius as f64 / 2.0).max(0.5);
+
+        Rgb32FImage::from_fn(width, height, |x, y| {
+            let mut sum = [0.0; 3];
+            let mut weight_sum = 0.0;
+
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let (qx, qy) = (x as i64 + dx, y as i64 + dy);
+                    if qx < 0 || qy < 0 || qx >= width as i64 || qy >= height as i64 {
+                        continue;
+                    }
+                    let (qx, qy) = (qx as u32, qy as u32);
+
+                    let spatial = (dx * dx + dy * dy) as f64 / (2.0 * spatial_sigma * spatial_sigma);
+                    let distance = spatial
+                        + self.color_distance(color, variance, (x, y), (qx, qy))
+                        + Self::guide_distance(albedo, (x, y), (qx, qy), self.albedo_sigma)
+                        + Self::guide_distance(normal, (x, y), (qx, qy), self.normal_sigma)
+                        + self.depth_distance(depth, (x, y), (qx, qy));
+                    let weight = (-distance).exp();
+
+                    let c = color.get_pixel(qx, qy);
+                    for i in 0..3 {
+                        sum[i] += c[i] as f64 * weight;
+                    }
+                    weight_sum += weight;
+                }
+            }
+
+            // The pixel itself always has a weight of 1, so the sum is never zero
+            Rgb(sum.map(|s| (s / weight_sum) as f32))
+        })
+    }
+
+    /// Averages each pixel with its direct neighbors, since a variance estimated from few samples is noisy itself
+    fn smooth(image: &Rgb32FImage) -> Rgb32FImage {
+        let (width, height) = image.dimensions();
+        Rgb32FImage::from_fn(width, height, |x, y| {
+            let mut sum = [0.0; 3];
+            let mut count = 0.0;
+            for qy in y.saturating_sub(1)..(y + 2).min(height) {
+                for qx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let p = image.get_pixel(qx, qy);
+                    for i in 0..3 {
+                        sum[i] += p[i];
+                    }
+                    count += 1.0;
+                }
+            }
+            Rgb(sum.map(|s| s / count))
+        })
+    }
+
+    /// Squared color difference beyond what the noise of both pixels explains,
+    /// in units of their variance. Pixels without noise only match identical colors.
+    fn color_distance(&self, color: &Rgb32FImage, variance: &Rgb32FImage, p: (u32, u32), q: (u32, u32)) -> f64 {
+        let (cp, cq) = (color.get_pixel(p.0, p.1), color.get_pixel(q.0, q.1));
+        let (vp, vq) = (variance.get_pixel(p.0, p.1), variance.get_pixel(q.0, q.1));
+        let k2 = self.strength * self.strength;
+
+        (0..3)
+            .map(|i| {
+                let difference = (cp[i] - cq[i]) as f64;
+                let noise = (vp[i] + vq[i]) as f64;
+                (difference * difference - noise).max(0.0) / (1e-5 + k2 * noise)
+            })
+            .sum::<f64>()
+            / 3.0
+    }
+
+    /// Squared difference of a guide pass relative to the tolerated difference
+    fn guide_distance(guide: &Rgb32FImage, p: (u32, u32), q: (u32, u32), sigma: f64) -> f64 {
+        let (gp, gq) = (guide.get_pixel(p.0, p.1), guide.get_pixel(q.0, q.1));
+        let squared: f64 = (0..3).map(|i| ((gp[i] - gq[i]) as f64).powi(2)).sum();
+        squared / (2.0 * sigma * sigma)
+    }
+
+    /// Squared depth difference relative to the depth, pixels without a surface only match each other
+    fn depth_distance(&self, depth: &Rgb32FImage, p: (u32, u32), q: (u32, u32)) -> f64 {
+        let (dp, dq) = (depth.get_pixel(p.0, p.1)[0] as f64, depth.get_pixel(q.0, q.1)[0] as f64);
+        if dp == 0.0 || dq == 0.0 {
+            return if dp == dq { 0.0 } else { f64::INFINITY };
+        }
+        let relative = (dp - dq) / (self.depth_sigma * dp.max(dq));
+        relative * relative / 2.0
+    }
+}
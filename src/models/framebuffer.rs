@@ -0,0 +1,215 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use image::{Rgb, Rgb32FImage, RgbImage};
+use crate::models::color::Color;
+
+/// Identifies checkpoint files and their layout version
+const CHECKPOINT_MAGIC: &[u8; 8] = b"RTCKPT05";
+/// Magic, scene hash and the rectangle of the pixels
+const HEADER_BYTES: u64 = 8 + 8 + 4 * 4;
+/// Sum and squared sum of the colors and the number of samples
+const PIXEL_BYTES: u64 = 2 * 3 * 8 + 4;
+
+/// Rendered image kept in memory
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderBuffers {
+    /// Linear colors as computed by the renderer, not clamped to [0, 1]
+    pub color: Rgb32FImage,
+    /// The image as it would be saved, if requested
+    pub rgb: Option<RgbImage>,
+}
+
+/// Samples accumulated for every pixel of an image that is rendered in passes.
+/// The random numbers of a sample only depend on the pixel and the number of samples taken
+/// before, so adding the samples pass by pass gives the same result as taking them all at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    /// Position of the top left pixel in the image of the camera
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Sum of all sample colors of each pixel, row by row
+    pub sum: Vec<Color>,
+    /// Sum of the squared sample colors of each pixel, used to estimate the noise
+    pub sum_squared: Vec<Color>,
+    /// Number of samples taken for each pixel
+    pub samples: Vec<u32>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::for_region(0, 0, width, height)
+    }
+
+    /// Creates a framebuffer for a part of the image. The pixels are sampled
+    /// by their position in the full image, so they are rendered identically.
+    pub fn for_region(left: u32, top: u32, width: u32, height: u32) -> Self {
+        let size = (width * height) as usize;
+
+        Self {
+            left,
+            top,
+            width,
+            height,
+            sum: vec![Color::BLACK; size],
+            sum_squared: vec![Color::BLACK; size],
+            samples: vec![0; size],
+        }
+    }
+
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    pub fn add_sample(&mut self, x: u32, y: u32, color: Color) {
+        let i = self.index(x, y);
+        self.sum[i] += color;
+        self.sum_squared[i] += color * color;
+        self.samples[i] += 1;
+    }
+
+    /// Returns the current estimate of the pixel color, black if no sample was taken yet
+    pub fn color(&self, x: u32, y: u32) -> Color {
+        let i = self.index(x, y);
+        if self.samples[i] == 0 {
+            Color::BLACK
+        } else {
+            self.sum[i] * (1.0 / self.samples[i] as f64)
+        }
+    }
+
+    /// Returns the variance of the current estimate of the pixel color, which shrinks with
+    /// every sample taken. Zero while there are less than two samples to estimate it from.
+    pub fn variance(&self, x: u32, y: u32) -> Color {
+        let i = self.index(x, y);
+        let n = self.samples[i] as f64;
+        if n < 2.0 {
+            return Color::BLACK;
+        }
+        let mean = self.sum[i] * (1.0 / n);
+        let squared_mean = self.sum_squared[i] * (1.0 / n);
+        let variance = |mean: f64, squared_mean: f64| (squared_mean - mean * mean).max(0.0) / (n - 1.0);
+        Color::new(
+            variance(mean.r, squared_mean.r),
+            variance(mean.g, squared_mean.g),
+            variance(mean.b, squared_mean.b),
+        )
+    }
+
+    /// Returns the variance of every pixel as floating point image, see `variance`
+    pub fn to_variance_image(&self) -> Rgb32FImage {
+        Rgb32FImage::from_fn(self.width, self.height, |x, y| {
+            let variance = self.variance(x, y);
+            Rgb([variance.r as f32, variance.g as f32, variance.b as f32])
+        })
+    }
+
+    /// Converts the current estimate into a floating point image, keeping values outside of [0, 1]
+    pub fn to_float_image(&self) -> Rgb32FImage {
+        Rgb32FImage::from_fn(self.width, self.height, |x, y| {
+            let color = self.color(x, y);
+            Rgb([color.r as f32, color.g as f32, color.b as f32])
+        })
+    }
+
+    /// Returns the number of passes rendered so far.
+    /// Pixels that stopped early with adaptive sampling have fewer samples.
+    pub fn passes(&self) -> u32 {
+        self.samples.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Writes the framebuffer to a checkpoint file, so rendering can continue later.
+    /// The file is written next to the target and renamed, so an interruption while
+    /// saving keeps the previous checkpoint intact. The scene hash identifies the scene
+    /// the samples belong to, see `Scene::content_hash`.
+    pub fn save(&self, path: &Path, scene_hash: u64) -> io::Result<()> {
+        let temp_path = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&temp_path)?);
+            writer.write_all(CHECKPOINT_MAGIC)?;
+            writer.write_all(&scene_hash.to_le_bytes())?;
+            writer.write_all(&self.left.to_le_bytes())?;
+            writer.write_all(&self.top.to_le_bytes())?;
+            writer.write_all(&self.width.to_le_bytes())?;
+            writer.write_all(&self.height.to_le_bytes())?;
+
+            for i in 0..self.sum.len() {
+                for color in [self.sum[i], self.sum_squared[i]] {
+                    writer.write_all(&color.r.to_le_bytes())?;
+                    writer.write_all(&color.g.to_le_bytes())?;
+                    writer.write_all(&color.b.to_le_bytes())?;
+                }
+                writer.write_all(&self.samples[i].to_le_bytes())?;
+            }
+            writer.flush()?;
+        }
+        fs::rename(temp_path, path)
+    }
+
+    /// Reads a framebuffer from a checkpoint file written by `save`.
+    /// Fails if the checkpoint was saved for a scene with another hash.
+    pub fn load(path: &Path, scene_hash: u64) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let file_length = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a checkpoint file"));
+        }
+        if read_u64(&mut reader)? != scene_hash {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Checkpoint belongs to another scene"));
+        }
+
+        let left = read_u32(&mut reader)?;
+        let top = read_u32(&mut reader)?;
+        let width = read_u32(&mut reader)?;
+        let height = read_u32(&mut reader)?;
+        let size = width as usize * height as usize;
+
+        // The header is not trusted with the memory to allocate before the file is known to hold all pixels
+        let expected_length = (size as u64).checked_mul(PIXEL_BYTES).and_then(|pixels| pixels.checked_add(HEADER_BYTES));
+        if expected_length != Some(file_length) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Checkpoint file does not match its image size"));
+        }
+
+        let mut framebuffer = Self {
+            left,
+            top,
+            width,
+            height,
+            sum: Vec::with_capacity(size),
+            sum_squared: Vec::with_capacity(size),
+            samples: Vec::with_capacity(size),
+        };
+        for _ in 0..size {
+            framebuffer.sum.push(read_color(&mut reader)?);
+            framebuffer.sum_squared.push(read_color(&mut reader)?);
+            framebuffer.samples.push(read_u32(&mut reader)?);
+        }
+
+        Ok(framebuffer)
+    }
+}
+
+fn read_color(reader: &mut impl Read) -> io::Result<Color> {
+    let r = f64::from_bits(read_u64(reader)?);
+    let g = f64::from_bits(read_u64(reader)?);
+    let b = f64::from_bits(read_u64(reader)?);
+    Ok(Color::new(r, g, b))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
@@ -0,0 +1,145 @@
+use serde::Deserialize;
+use crate::models::intersection::Intersection;
+use crate::models::material::{Material, MaterialConductor, MaterialDielectric, MaterialSolid, MaterialTextured};
+use crate::models::principled::MaterialPrincipled;
+use crate::models::matrix::Matrix4;
+use crate::models::motion::Motion;
+use crate::models::ray::Ray;
+use crate::models::surface::{Surface, SurfaceType, Surfaces};
+use crate::models::transform::{Transform, TransformMatrices};
+
+/// A node of the scene graph that moves its children as one unit.
+/// The transformation of a group applies to all of its children,
+/// nested groups compose their transformation with the one of their parent.
+/// Spheres and meshes without a material of their own use the default material of the group.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Group {
+    #[serde(default)]
+    pub material_solid: Option<MaterialSolid>,
+    #[serde(default)]
+    pub material_textured: Option<MaterialTextured>,
+    #[serde(default)]
+    pub material_conductor: Option<MaterialConductor>,
+    #[serde(default)]
+    pub material_dielectric: Option<MaterialDielectric>,
+    #[serde(default)]
+    pub material_principled: Option<MaterialPrincipled>,
+    #[serde(default)]
+    pub transform: Transform,
+    #[serde(default)]
+    pub motion: Option<Motion>,
+    pub surfaces: Surfaces,
+    #[serde(skip)]
+    pub matrices: TransformMatrices,
+}
+
+impl Group {
+    pub fn new(transform: Transform, surfaces: Vec<SurfaceType>) -> Self {
+        let mut group = Self {
+            material_solid: None,
+            material_textured: None,
+            material_conductor: None,
+            material_dielectric: None,
+            material_principled: None,
+            transform,
+            motion: None,
+            surfaces: Surfaces { surfaces },
+            matrices: TransformMatrices::default(),
+        };
+        group.resolve();
+        group
+    }
+
+    /// Precomputes the transformation matrices of the group
+    pub fn resolve(&mut self) {
+        self.matrices = TransformMatrices::new(self.transform.matrix());
+    }
+
+    /// Moves the group by a transformation applied after its own, used for animations
+    pub fn place(&mut self, placement: Matrix4) {
+        self.matrices = TransformMatrices::new(placement * self.transform.matrix());
+    }
+
+    /// Returns the transformation matrices at the given time, including the motion of the group
+    pub fn matrices_at(&self, time: f64) -> TransformMatrices {
+        match &self.motion {
+            Some(motion) => motion.matrices_at(time, &self.matrices),
+            None => self.matrices,
+        }
+    }
+
+    /// Returns the default material of the group, if any
+    pub fn material(&self) -> Option<Material> {
+        Material::first_of(
+            &self.material_solid,
+            &self.material_textured,
+            &self.material_conductor,
+            &self.material_dielectric,
+            &self.material_principled,
+        )
+    }
+
+    /// Passes the default material down the hierarchy to all children that have none.
+    /// The default of a nested group takes precedence over the one inherited from its parents.
+    pub fn apply_default_material(&mut self, inherited: Option<&Material>) {
+        let own = self.material();
+        let Some(material) = own.as_ref().or(inherited) else {
+            return;
+        };
+
+        for surface in &mut self.surfaces.surfaces {
+            Self::assign_material(surface, material);
+        }
+    }
+
+    fn assign_material(surface: &mut SurfaceType, material: &Material) {
+        match surface {
+            SurfaceType::Sphere(sphere) => {
+                if !sphere.has_material() {
+                    sphere.set_material(material);
+                }
+            }
+            SurfaceType::Mesh(mesh) => {
+                if !mesh.has_material() {
+                    mesh.set_material(material);
+                }
+            }
+            // Instances keep the materials of their definition, whose surfaces always have one
+            SurfaceType::Instance(_) => {}
+            SurfaceType::Csg(csg) => {
+                for child in &mut csg.children {
+                    Self::assign_material(child, material);
+                }
+            }
+            SurfaceType::Group(group) => group.apply_default_material(Some(material)),
+        }
+    }
+}
+
+impl Surface for Group {
+    /// Intersects the children with the ray transformed into the space of the group
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let matrices = self.matrices_at(ray.time);
+        let local_ray = matrices.ray_to_object(ray);
+
+        self.surfaces.surfaces
+            .iter()
+            .filter_map(|surface| surface.intersect(&local_ray))
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+            .map(|intersection| matrices.intersection_to_world(intersection))
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let matrices = self.matrices_at(ray.time);
+        let local_ray = matrices.ray_to_object(ray);
+
+        let mut hits: Vec<Intersection> = self.surfaces.surfaces
+            .iter()
+            .flat_map(|surface| surface.intersect_all(&local_ray))
+            .map(|intersection| matrices.intersection_to_world(intersection))
+            .collect();
+
+        hits.sort_by(|a, b| a.t.total_cmp(&b.t));
+        hits
+    }
+}
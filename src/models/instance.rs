@@ -0,0 +1,178 @@
+use std::sync::Arc;
+use serde::Deserialize;
+use crate::models::intersection::Intersection;
+use crate::models::material::{Material, MaterialConductor, MaterialDielectric, MaterialSolid, MaterialTextured};
+use crate::models::principled::MaterialPrincipled;
+use crate::models::matrix::Matrix4;
+use crate::models::motion::Motion;
+use crate::models::ray::Ray;
+use crate::models::surface::{Surface, SurfaceType};
+use crate::models::transform::{Transform, TransformMatrices};
+
+/// Named geometry definitions that can be placed in the scene multiple times
+#[derive(Debug, Deserialize, PartialEq, Default)]
+pub struct Definitions {
+    #[serde(default)]
+    pub geometry: Vec<Arc<Geometry>>,
+}
+
+/// Reusable geometry, loaded once and shared by all of its instances
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Geometry {
+    pub name: String,
+    #[serde(rename = "$value", default)]
+    pub surfaces: Vec<SurfaceType>,
+}
+
+/// Places a geometry definition in the scene with its own transformation
+/// and optionally a material that replaces the one of the definition
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Instance {
+    /// Name of the referenced geometry definition
+    pub geometry: String,
+    #[serde(default)]
+    pub material_solid: Option<MaterialSolid>,
+    #[serde(default)]
+    pub material_textured: Option<MaterialTextured>,
+    #[serde(default)]
+    pub material_conductor: Option<MaterialConductor>,
+    #[serde(default)]
+    pub material_dielectric: Option<MaterialDielectric>,
+    #[serde(default)]
+    pub material_principled: Option<MaterialPrincipled>,
+    #[serde(default)]
+    pub transform: Transform,
+    #[serde(default)]
+    pub motion: Option<Motion>,
+    #[serde(skip)]
+    pub shared: Option<Arc<Geometry>>,
+    #[serde(skip)]
+    pub matrices: TransformMatrices,
+}
+
+impl Definitions {
+    pub fn find(&self, name: &str) -> Option<&Arc<Geometry>> {
+        self.geometry.iter().find(|g| g.name == name)
+    }
+}
+
+impl Geometry {
+    /// Names of the definitions that are instanced inside this geometry
+    pub fn dependencies(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for surface in &self.surfaces {
+            collect_instanced(surface, &mut names);
+        }
+        names
+    }
+}
+
+fn collect_instanced<'a>(surface: &'a SurfaceType, names: &mut Vec<&'a str>) {
+    match surface {
+        SurfaceType::Instance(instance) => names.push(&instance.geometry),
+        SurfaceType::Csg(csg) => {
+            for child in &csg.children {
+                collect_instanced(child, names);
+            }
+        }
+        SurfaceType::Group(group) => {
+            for child in &group.surfaces.surfaces {
+                collect_instanced(child, names);
+            }
+        }
+        SurfaceType::Sphere(_) | SurfaceType::Mesh(_) => {}
+    }
+}
+
+impl Instance {
+    pub fn new(geometry: Arc<Geometry>, transform: Transform) -> Self {
+        let mut instance = Self {
+            geometry: geometry.name.clone(),
+            material_solid: None,
+            material_textured: None,
+            material_conductor: None,
+            material_dielectric: None,
+            material_principled: None,
+            transform,
+            motion: None,
+            shared: None,
+            matrices: TransformMatrices::default(),
+        };
+        instance.resolve(geometry);
+        instance
+    }
+
+    /// Links the instance to its geometry and precomputes the transformation matrices
+    pub fn resolve(&mut self, geometry: Arc<Geometry>) {
+        self.shared = Some(geometry);
+        self.matrices = TransformMatrices::new(self.transform.matrix());
+    }
+
+    /// Moves the instance by a transformation applied after its own, used for animations
+    pub fn place(&mut self, placement: Matrix4) {
+        self.matrices = TransformMatrices::new(placement * self.transform.matrix());
+    }
+
+    /// Returns the transformation matrices at the given time, including the motion of the instance
+    pub fn matrices_at(&self, time: f64) -> TransformMatrices {
+        match &self.motion {
+            Some(motion) => motion.matrices_at(time, &self.matrices),
+            None => self.matrices,
+        }
+    }
+
+    /// Returns the material override of the instance, if any
+    pub fn material(&self) -> Option<Material> {
+        Material::first_of(
+            &self.material_solid,
+            &self.material_textured,
+            &self.material_conductor,
+            &self.material_dielectric,
+            &self.material_principled,
+        )
+    }
+
+    /// Transforms an object space intersection back into world space
+    /// and applies the material override
+    fn to_world_space(matrices: &TransformMatrices, intersection: Intersection, material: Option<Material>) -> Intersection {
+        let mut intersection = matrices.intersection_to_world(intersection);
+        if let Some(m) = material {
+            intersection.material = m;
+        }
+        intersection
+    }
+}
+
+impl Surface for Instance {
+    /// Intersects the shared geometry with the ray transformed into object space
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let geometry = self.shared.as_ref()?;
+        let matrices = self.matrices_at(ray.time);
+        let local_ray = matrices.ray_to_object(ray);
+
+        geometry.surfaces
+            .iter()
+            .filter_map(|surface| surface.intersect(&local_ray))
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+            // The override is only resolved for the closest hit
+            .map(|intersection| Self::to_world_space(&matrices, intersection, self.material()))
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let Some(geometry) = self.shared.as_ref() else {
+            return Vec::new();
+        };
+        let matrices = self.matrices_at(ray.time);
+        let local_ray = matrices.ray_to_object(ray);
+        let material = self.material();
+
+        let mut hits: Vec<Intersection> = geometry.surfaces
+            .iter()
+            .flat_map(|surface| surface.intersect_all(&local_ray))
+            .map(|intersection| Self::to_world_space(&matrices, intersection, material.clone()))
+            .collect();
+
+        hits.sort_by(|a, b| a.t.total_cmp(&b.t));
+        hits
+    }
+}
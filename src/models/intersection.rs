@@ -1,3 +1,4 @@
+use crate::models::bump::Tangents;
 use crate::models::point::Point;
 use crate::models::vector::Vector;
 use crate::models::material::Material;
@@ -9,6 +10,8 @@ pub struct Intersection {
     pub point: Point,       // Intersection point
     pub normal: Vector,     // Surface normal at intersection
     pub material: Material, // Material at the point
+    pub uv: (f64, f64),     // Texture coordinates at the point
+    pub tangents: Option<Tangents>, // Derivatives of the point by u and v, for normal and bump maps
 }
 
 
@@ -25,6 +28,27 @@ impl Intersection {
             point,
             normal,
             material,
+            uv: (0.0, 0.0),
+            tangents: None,
+        }
+    }
+
+    /// Sets the texture coordinates of the intersection
+    pub fn with_uv(self, uv: (f64, f64)) -> Self {
+        Self { uv, ..self }
+    }
+
+    /// Tilts the shading normal by the normal and bump map of the material.
+    /// Surfaces without tangents, like triangles without texture coordinates, keep their normal.
+    pub fn apply_normal_maps(&mut self) {
+        let Some(tangents) = self.tangents else {
+            return;
+        };
+        if let Some(map) = self.material.normal_map() {
+            self.normal = map.perturb(self.normal, tangents, self.uv);
+        }
+        if let Some(map) = self.material.bump_map() {
+            self.normal = map.perturb(self.normal, tangents, self.uv);
         }
     }
 }
\ No newline at end of file
@@ -44,4 +44,22 @@ pub struct SpotLight {
 pub struct Falloff {
     pub alpha1: f64,
     pub alpha2: f64,
-}
\ No newline at end of file
+}
+impl SpotLight {
+    /// Returns the part of the light sent in the direction: all of it within the angle `alpha1`
+    /// around the direction of the spot, fading out until `alpha2` and none beyond. Angles are in degrees.
+    pub fn intensity_towards(&self, direction: Vector) -> f64 {
+        let cos_angle = direction.normalize().dot(self.direction.normalize()).clamp(-1.0, 1.0);
+        let angle = cos_angle.acos().to_degrees();
+        let Falloff { alpha1, alpha2 } = self.falloff;
+
+        if angle <= alpha1 {
+            1.0
+        } else if angle >= alpha2 {
+            0.0
+        } else {
+            let t = (angle - alpha1) / (alpha2 - alpha1);
+            1.0 - t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
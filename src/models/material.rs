@@ -1,6 +1,12 @@
 use image::RgbImage;
 use serde::Deserialize;
+use crate::models::bsdf::{self, Bsdf, BsdfSample, Frame, Ggx};
+use crate::models::bump::{BumpMap, NormalMap};
 use crate::models::color::Color;
+use crate::models::principled::MaterialPrincipled;
+use crate::models::shading::Shading;
+use crate::models::spectrum::{self, Cauchy, Sellmeier};
+use crate::models::vector::Vector;
 
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct MaterialSolid {
@@ -9,6 +15,12 @@ pub struct MaterialSolid {
     pub reflectance: Reflectance,
     pub transmittance: Transmittance,
     pub refraction: Refraction,
+    #[serde(default)]
+    pub shading: Shading,
+    #[serde(default)]
+    pub normal_map: Option<NormalMap>,
+    #[serde(default)]
+    pub bump_map: Option<BumpMap>,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Clone)]
@@ -18,14 +30,58 @@ pub struct MaterialTextured {
     pub reflectance: Reflectance,
     pub transmittance: Transmittance,
     pub refraction: Refraction,
+    #[serde(default)]
+    pub shading: Shading,
+    #[serde(default)]
+    pub normal_map: Option<NormalMap>,
+    #[serde(default)]
+    pub bump_map: Option<BumpMap>,
+}
+
+/// Metal with a complex index of refraction per color channel, reflecting with a GGX microfacet distribution
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct MaterialConductor {
+    /// Real part of the index of refraction
+    pub eta: Color,
+    /// Imaginary part of the index of refraction, the absorption of the metal
+    pub k: Color,
+    /// 0 for a perfect mirror up to 1 for a dull surface
+    #[serde(default)]
+    pub roughness: f64,
+}
+
+/// Glass or other transparent material that reflects and refracts, with a GGX microfacet distribution
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct MaterialDielectric {
+    /// Index of refraction inside the material
+    pub ior: f64,
+    /// 0 for clear glass up to 1 for a strongly frosted surface
+    #[serde(default)]
+    pub roughness: f64,
+    #[serde(default)]
+    pub absorption: Option<Absorption>,
+    /// Index of refraction of spectral renders by wavelength, instead of the ior
+    #[serde(default)]
+    pub cauchy: Option<Cauchy>,
+    #[serde(default)]
+    pub sellmeier: Option<Sellmeier>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Material {
     Solid(MaterialSolid),
     Textured(MaterialTextured),
+    Conductor(MaterialConductor),
+    Dielectric(MaterialDielectric),
+    Principled(Box<MaterialPrincipled>),
 }
 
+/// Coefficients of the physically based materials, which are not shaded with Phong
+const NO_PHONG: Phong = Phong { ka: 0.0, kd: 0.0, ks: 0.0, exponent: 1.0 };
+const NO_REFLECTANCE: Reflectance = Reflectance { r: 0.0, roughness: 0.0, samples: 1 };
+const NO_TRANSMITTANCE: Transmittance = Transmittance { t: 0.0, roughness: 0.0, samples: 1, absorption: None };
+const NO_REFRACTION: Refraction = Refraction { iof: 1.0, cauchy: None, sellmeier: None };
+
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct Phong {
     pub ka: f64,         // Ambient coefficient
@@ -37,39 +93,121 @@ pub struct Phong {
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct Reflectance {
     pub r: f64,
+    /// Spread of the reflected rays, 0 for a perfect mirror
+    #[serde(default)]
+    pub roughness: f64,
+    /// Number of reflected rays of a rough surface
+    #[serde(default = "default_gloss_samples")]
+    pub samples: u32,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct Transmittance {
     pub t: f64,
+    /// Spread of the refracted rays, 0 for clear glass
+    #[serde(default)]
+    pub roughness: f64,
+    /// Number of refracted rays of a rough surface
+    #[serde(default = "default_gloss_samples")]
+    pub samples: u32,
+    #[serde(default)]
+    pub absorption: Option<Absorption>,
+}
+
+fn default_gloss_samples() -> u32 {
+    4
+}
+
+/// Absorption of the light traveling inside of a transmissive material, by the Beer-Lambert law
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct Absorption {
+    /// Color of white light after traveling a distance of 1 / density inside the material
+    pub color: Color,
+    #[serde(default = "default_density")]
+    pub density: f64,
+}
+
+fn default_density() -> f64 {
+    1.0
+}
+
+/// Spread of the secondary rays of a glossy reflection or refraction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gloss {
+    pub roughness: f64,
+    pub samples: u32,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct Refraction {
     pub iof: f64,
+    /// Index of refraction of spectral renders by wavelength, instead of the iof
+    #[serde(default)]
+    pub cauchy: Option<Cauchy>,
+    #[serde(default)]
+    pub sellmeier: Option<Sellmeier>,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct Texture {
     pub name: String,
+    /// Shared by the copies of the material that every intersection gets
     #[serde(skip)]
-    pub data: Option<RgbImage>,
+    pub data: Option<Arc<RgbImage>>,
 }
 
 impl Material {
+    /// Returns the first of the materials that is set, as surfaces only use one
+    pub fn first_of(
+        solid: &Option<MaterialSolid>,
+        textured: &Option<MaterialTextured>,
+        conductor: &Option<MaterialConductor>,
+        dielectric: &Option<MaterialDielectric>,
+        principled: &Option<MaterialPrincipled>,
+    ) -> Option<Material> {
+        solid.clone().map(Material::Solid)
+            .or_else(|| textured.clone().map(Material::Textured))
+            .or_else(|| conductor.clone().map(Material::Conductor))
+            .or_else(|| dielectric.clone().map(Material::Dielectric))
+            .or_else(|| principled.clone().map(|p| Material::Principled(Box::new(p))))
+    }
+
     pub fn color(&self) -> Color {
         let black = Color::new(0.0, 0.0, 0.0);
 
         match self {
             Material::Solid(s) => s.color,
             Material::Textured(_) => black,
+            Material::Conductor(c) => c.reflectance(1.0),
+            Material::Dielectric(_) => Color::WHITE,
+            Material::Principled(p) => p.base_color,
+        }
+    }
+
+    /// Returns the color of the material at the texture coordinates of a point.
+    /// Textured materials are black as long as the texture is not loaded.
+    pub fn color_at(&self, uv: (f64, f64)) -> Color {
+        match self {
+            Material::Solid(s) => s.color,
+            Material::Textured(t) => t.texture.sample(uv).unwrap_or(Color::new(0.0, 0.0, 0.0)),
+            Material::Principled(p) => p.at(uv).base_color,
+            _ => self.color(),
+        }
+    }
+
+    /// Returns the part of the ambient light that the material reflects at the texture coordinates.
+    /// Physically based materials reflect it with their diffuse albedo.
+    pub fn ambient_at(&self, uv: (f64, f64)) -> Color {
+        match self {
+            Material::Principled(p) => p.at(uv).diffuse_albedo(),
+            _ => self.color() * self.phong().ka,
         }
     }
 
     pub fn texture(&self) -> &str {
         match self {
-            Material::Solid(_) => "No Texture!",
-            Material::Textured(t) => &t.texture.name
+            Material::Textured(t) => &t.texture.name,
+            _ => "No Texture!",
         }
     }
 
@@ -77,6 +215,16 @@ impl Material {
         match self {
             Material::Solid(s) => &s.phong,
             Material::Textured(t) => &t.phong,
+            Material::Conductor(_) | Material::Dielectric(_) | Material::Principled(_) => &NO_PHONG,
+        }
+    }
+
+    /// Returns the local shading model, the default one for physically based materials
+    pub fn shading(&self) -> &Shading {
+        match self {
+            Material::Solid(s) => &s.shading,
+            Material::Textured(t) => &t.shading,
+            Material::Conductor(_) | Material::Dielectric(_) | Material::Principled(_) => &Shading::DEFAULT,
         }
     }
 
@@ -84,6 +232,7 @@ impl Material {
         match self {
             Material::Solid(s) => &s.reflectance,
             Material::Textured(t) => &t.reflectance,
+            Material::Conductor(_) | Material::Dielectric(_) | Material::Principled(_) => &NO_REFLECTANCE,
         }
     }
 
@@ -91,6 +240,60 @@ impl Material {
         match self {
             Material::Solid(s) => &s.transmittance,
             Material::Textured(t) => &t.transmittance,
+            Material::Conductor(_) | Material::Dielectric(_) | Material::Principled(_) => &NO_TRANSMITTANCE,
+        }
+    }
+
+    /// Returns how the light inside of a transmissive material is absorbed, if at all
+    pub fn absorption(&self) -> Option<&Absorption> {
+        match self {
+            Material::Solid(s) => s.transmittance.absorption.as_ref(),
+            Material::Textured(t) => t.transmittance.absorption.as_ref(),
+            Material::Conductor(_) => None,
+            Material::Dielectric(d) => d.absorption.as_ref(),
+            Material::Principled(p) => p.absorption.as_ref(),
+        }
+    }
+
+    /// Returns the tangent-space normal map of the material, if it has one
+    pub fn normal_map(&self) -> Option<&NormalMap> {
+        match self {
+            Material::Solid(s) => s.normal_map.as_ref(),
+            Material::Textured(t) => t.normal_map.as_ref(),
+            Material::Principled(p) => p.normal_map.as_ref(),
+            Material::Conductor(_) | Material::Dielectric(_) => None,
+        }
+    }
+
+    /// Returns the grayscale bump map of the material, if it has one
+    pub fn bump_map(&self) -> Option<&BumpMap> {
+        match self {
+            Material::Solid(s) => s.bump_map.as_ref(),
+            Material::Textured(t) => t.bump_map.as_ref(),
+            Material::Principled(p) => p.bump_map.as_ref(),
+            Material::Conductor(_) | Material::Dielectric(_) => None,
+        }
+    }
+
+    /// Returns the part of the light that passes straight through the surface at the texture coordinates,
+    /// tinted by the color of the material. Opaque materials let no light through.
+    pub fn straight_transmission(&self, uv: (f64, f64)) -> Color {
+        match self {
+            Material::Solid(_) | Material::Textured(_) => self.color_at(uv) * self.transmittance().t,
+            Material::Conductor(_) => Color::BLACK,
+            Material::Dielectric(_) => Color::WHITE,
+            Material::Principled(p) => p.at(uv).straight_transmission(),
+        }
+    }
+
+    /// Returns the scattering function of physically based materials at the texture coordinates,
+    /// None for the Phong materials
+    pub fn bsdf(&self, uv: (f64, f64)) -> Option<Box<dyn Bsdf>> {
+        match self {
+            Material::Conductor(c) => Some(Box::new(c.clone())),
+            Material::Dielectric(d) => Some(Box::new(d.clone())),
+            Material::Principled(p) => Some(Box::new(p.at(uv))),
+            _ => None,
         }
     }
 
@@ -98,11 +301,28 @@ impl Material {
         match self {
             Material::Solid(s) => &s.refraction,
             Material::Textured(t) => &t.refraction,
+            Material::Conductor(_) | Material::Dielectric(_) | Material::Principled(_) => &NO_REFRACTION,
+        }
+    }
+
+    /// Sets the index of refraction to the one at a wavelength in nanometers,
+    /// for the materials with a dispersion model
+    pub fn disperse(&mut self, wavelength: f64) {
+        match self {
+            Material::Solid(s) => s.refraction.disperse(wavelength),
+            Material::Textured(t) => t.refraction.disperse(wavelength),
+            Material::Dielectric(d) => {
+                if let Some(ior) = spectrum::ior_at(&d.cauchy, &d.sellmeier, wavelength) {
+                    d.ior = ior;
+                }
+            }
+            Material::Conductor(_) | Material::Principled(_) => {}
         }
     }
 }
 
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use image::ImageError;
 
 impl Texture {
@@ -111,7 +331,298 @@ impl Texture {
         let texture_path: PathBuf = base_path.join("assets/textures").join(&self.name);
         // Open the image file
         let img = image::open(&texture_path)?.to_rgb8();
-        self.data = Some(img);
+        self.data = Some(Arc::new(img));
         Ok(())
     }
+
+    /// Returns the color of the nearest texel, repeating the texture outside of [0, 1].
+    /// The v coordinate points up, so v = 0 is the bottom row of the image.
+    pub fn sample(&self, (u, v): (f64, f64)) -> Option<Color> {
+        let data = self.data.as_ref().filter(|data| data.width() > 0 && data.height() > 0)?;
+        let (width, height) = data.dimensions();
+        let x = ((u.rem_euclid(1.0) * width as f64) as u32).min(width - 1);
+        let y = (((1.0 - v.rem_euclid(1.0)) * height as f64) as u32).min(height - 1);
+        let [r, g, b] = data.get_pixel(x, y).0;
+        Some(Color::new(r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0))
+    }
+}
+
+impl Reflectance {
+    pub fn gloss(&self) -> Gloss {
+        Gloss { roughness: self.roughness, samples: self.samples }
+    }
+}
+
+impl Transmittance {
+    pub fn gloss(&self) -> Gloss {
+        Gloss { roughness: self.roughness, samples: self.samples }
+    }
+}
+
+impl Refraction {
+    fn disperse(&mut self, wavelength: f64) {
+        if let Some(iof) = spectrum::ior_at(&self.cauchy, &self.sellmeier, wavelength) {
+            self.iof = iof;
+        }
+    }
+}
+
+impl Absorption {
+    /// Returns the part of the light that is left after traveling the distance inside the material
+    pub fn transmittance(&self, distance: f64) -> Color {
+        let exponent = self.density * distance.max(0.0);
+        let remaining = |channel: f64| channel.clamp(0.0, 1.0).powf(exponent);
+        Color::new(remaining(self.color.r), remaining(self.color.g), remaining(self.color.b))
+    }
+}
+
+impl Gloss {
+    /// Returns the number of rays to trace. Only surfaces hit by camera rays split into several rays,
+    /// deeper bounces take one ray each, which the samples of the pixel average.
+    pub fn rays(&self, camera_ray: bool) -> u32 {
+        if self.roughness > 0.0 && camera_ray {
+            self.samples.max(1)
+        } else {
+            1
+        }
+    }
+
+    /// Turns the ideal direction randomly by up to the angle whose tangent is the roughness.
+    /// Directions that end up behind the surface are mirrored to the `side` the ray has to leave on.
+    pub fn perturb(&self, direction: Vector, side: Vector, (u1, u2): (f64, f64)) -> Vector {
+        if self.roughness <= 0.0 {
+            return direction;
+        }
+
+        let frame = Frame::from_normal(direction);
+        let r = self.roughness * u1.sqrt();
+        let phi = 2.0 * std::f64::consts::PI * u2;
+        let perturbed = frame.to_world(Vector::new(r * phi.cos(), r * phi.sin(), 1.0)).normalize();
+        let behind = perturbed.dot(side);
+        if behind < 0.0 {
+            perturbed - side * (2.0 * behind)
+        } else {
+            perturbed
+        }
+    }
+}
+
+impl MaterialConductor {
+    /// Fresnel reflectance for light arriving at the cosine `cos_i` to the normal
+    pub fn reflectance(&self, cos_i: f64) -> Color {
+        Color::new(
+            bsdf::fresnel_conductor(cos_i, self.eta.r, self.k.r),
+            bsdf::fresnel_conductor(cos_i, self.eta.g, self.k.g),
+            bsdf::fresnel_conductor(cos_i, self.eta.b, self.k.b),
+        )
+    }
+
+    /// Metals reflect on both sides, so the normal is turned towards the outgoing direction
+    fn frame(outgoing: Vector, normal: Vector) -> Frame {
+        if outgoing.dot(normal) < 0.0 {
+            Frame::from_normal(-normal)
+        } else {
+            Frame::from_normal(normal)
+        }
+    }
+}
+
+impl Bsdf for MaterialConductor {
+    fn eval(&self, outgoing: Vector, incoming: Vector, normal: Vector) -> Color {
+        let ggx = Ggx::from_roughness(self.roughness);
+        let frame = Self::frame(outgoing, normal);
+        let (wo, wi) = (frame.to_local(outgoing), frame.to_local(incoming));
+        if ggx.is_smooth() || wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color::BLACK;
+        }
+
+        let h = (wo + wi).normalize();
+        self.reflectance(wo.dot(h)) * (ggx.d(h) * ggx.g(wo, wi, h) / (4.0 * wo.z * wi.z))
+    }
+
+    fn sample(&self, outgoing: Vector, normal: Vector, u: (f64, f64), _u_lobe: f64) -> Option<BsdfSample> {
+        let ggx = Ggx::from_roughness(self.roughness);
+        let frame = Self::frame(outgoing, normal);
+        let wo = frame.to_local(outgoing);
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        if ggx.is_smooth() {
+            return Some(BsdfSample {
+                direction: frame.to_world(Vector::new(-wo.x, -wo.y, wo.z)),
+                weight: self.reflectance(wo.z),
+                pdf: 1.0,
+                specular: true,
+            });
+        }
+
+        let h = ggx.sample_visible_normal(wo, u);
+        let wi = bsdf::reflect(wo, h);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction: frame.to_world(wi),
+            weight: self.reflectance(wo.dot(h)) * (ggx.g(wo, wi, h) / ggx.g1(wo, h)),
+            pdf: ggx.visible_pdf(wo, h) / (4.0 * wo.dot(h)),
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, outgoing: Vector, incoming: Vector, normal: Vector) -> f64 {
+        let ggx = Ggx::from_roughness(self.roughness);
+        let frame = Self::frame(outgoing, normal);
+        let (wo, wi) = (frame.to_local(outgoing), frame.to_local(incoming));
+        if ggx.is_smooth() || wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+
+        let h = (wo + wi).normalize();
+        ggx.visible_pdf(wo, h) / (4.0 * wo.dot(h))
+    }
+}
+
+/// Reflected and refracted direction for one microfacet normal, before choosing between them
+struct DielectricLobes {
+    /// Fresnel reflectance, the chance to reflect
+    fresnel: f64,
+    reflection: Option<BsdfSample>,
+    refraction: Option<BsdfSample>,
+}
+
+impl MaterialDielectric {
+    /// Turns the normal towards the outgoing direction and returns the index of refraction
+    /// on the other side of the surface divided by the one on its side
+    fn frame(&self, outgoing: Vector, normal: Vector) -> (Frame, f64) {
+        if outgoing.dot(normal) < 0.0 {
+            (Frame::from_normal(-normal), 1.0 / self.ior)
+        } else {
+            (Frame::from_normal(normal), self.ior)
+        }
+    }
+
+    /// Reflects and refracts the outgoing direction at a sampled microfacet. The weights and
+    /// densities do not include the chance of choosing the lobe.
+    fn lobes(&self, outgoing: Vector, normal: Vector, u: (f64, f64)) -> Option<DielectricLobes> {
+        let ggx = Ggx::from_roughness(self.roughness);
+        let (frame, eta) = self.frame(outgoing, normal);
+        let wo = frame.to_local(outgoing);
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        // Radiance is compressed into a smaller solid angle when it enters a denser material
+        let transmission = Color::WHITE * (1.0 / (eta * eta));
+        if ggx.is_smooth() {
+            let h = Vector::new(0.0, 0.0, 1.0);
+            let sample = |direction: Vector, weight: Color| BsdfSample {
+                direction: frame.to_world(direction),
+                weight,
+                pdf: 1.0,
+                specular: true,
+            };
+            return Some(DielectricLobes {
+                fresnel: bsdf::fresnel_dielectric(wo.z, eta),
+                reflection: Some(sample(bsdf::reflect(wo, h), Color::WHITE)),
+                refraction: bsdf::refract(wo, h, eta).map(|wi| sample(wi, transmission)),
+            });
+        }
+
+        let h = ggx.sample_visible_normal(wo, u);
+        let cos_o = wo.dot(h);
+        let visible_pdf = ggx.visible_pdf(wo, h);
+        let sample = |wi: Vector, weight: Color, pdf: f64| BsdfSample {
+            direction: frame.to_world(wi),
+            weight: weight * (ggx.g(wo, wi, h) / ggx.g1(wo, h)),
+            pdf,
+            specular: false,
+        };
+
+        let reflected = bsdf::reflect(wo, h);
+        let reflection = (reflected.z > 0.0).then(|| sample(reflected, Color::WHITE, visible_pdf / (4.0 * cos_o)));
+        let refraction = bsdf::refract(wo, h, eta).filter(|wi| wi.z < 0.0).map(|wi| {
+            let denominator = cos_o + eta * wi.dot(h);
+            sample(wi, transmission, visible_pdf * eta * eta * wi.dot(h).abs() / (denominator * denominator))
+        });
+        Some(DielectricLobes { fresnel: bsdf::fresnel_dielectric(cos_o, eta), reflection, refraction })
+    }
+}
+
+impl Bsdf for MaterialDielectric {
+    fn eval(&self, outgoing: Vector, incoming: Vector, normal: Vector) -> Color {
+        let ggx = Ggx::from_roughness(self.roughness);
+        let (frame, eta) = self.frame(outgoing, normal);
+        let (wo, wi) = (frame.to_local(outgoing), frame.to_local(incoming));
+        if ggx.is_smooth() || wo.z <= 0.0 || wi.z == 0.0 {
+            return Color::BLACK;
+        }
+
+        if wi.z > 0.0 {
+            let h = (wo + wi).normalize();
+            let fresnel = bsdf::fresnel_dielectric(wo.dot(h), eta);
+            return Color::WHITE * (fresnel * ggx.d(h) * ggx.g(wo, wi, h) / (4.0 * wo.z * wi.z));
+        }
+
+        // The microfacet that refracts between the directions lies between them, weighted by the indices
+        let mut h = (wo + wi * eta).normalize();
+        if h.z < 0.0 {
+            h = -h;
+        }
+        let (cos_o, cos_i) = (wo.dot(h), wi.dot(h));
+        if cos_o <= 0.0 || cos_i >= 0.0 {
+            return Color::BLACK;
+        }
+        let fresnel = bsdf::fresnel_dielectric(cos_o, eta);
+        let denominator = cos_o + eta * cos_i;
+        let value = (1.0 - fresnel) * ggx.d(h) * ggx.g(wo, wi, h) * (cos_o * cos_i).abs()
+            / ((wo.z * wi.z).abs() * denominator * denominator);
+        Color::WHITE * value
+    }
+
+    fn sample(&self, outgoing: Vector, normal: Vector, u: (f64, f64), u_lobe: f64) -> Option<BsdfSample> {
+        let lobes = self.lobes(outgoing, normal, u)?;
+        let (lobe, chance) = if u_lobe < lobes.fresnel {
+            (lobes.reflection, lobes.fresnel)
+        } else {
+            (lobes.refraction, 1.0 - lobes.fresnel)
+        };
+        lobe.map(|sample| BsdfSample { pdf: sample.pdf * chance, ..sample })
+    }
+
+    fn pdf(&self, outgoing: Vector, incoming: Vector, normal: Vector) -> f64 {
+        let ggx = Ggx::from_roughness(self.roughness);
+        let (frame, eta) = self.frame(outgoing, normal);
+        let (wo, wi) = (frame.to_local(outgoing), frame.to_local(incoming));
+        if ggx.is_smooth() || wo.z <= 0.0 || wi.z == 0.0 {
+            return 0.0;
+        }
+
+        if wi.z > 0.0 {
+            let h = (wo + wi).normalize();
+            let fresnel = bsdf::fresnel_dielectric(wo.dot(h), eta);
+            return fresnel * ggx.visible_pdf(wo, h) / (4.0 * wo.dot(h));
+        }
+
+        let mut h = (wo + wi * eta).normalize();
+        if h.z < 0.0 {
+            h = -h;
+        }
+        let (cos_o, cos_i) = (wo.dot(h), wi.dot(h));
+        if cos_o <= 0.0 || cos_i >= 0.0 {
+            return 0.0;
+        }
+        let fresnel = bsdf::fresnel_dielectric(cos_o, eta);
+        let denominator = cos_o + eta * cos_i;
+        (1.0 - fresnel) * ggx.visible_pdf(wo, h) * eta * eta * cos_i.abs() / (denominator * denominator)
+    }
+
+    fn sample_lobes(&self, outgoing: Vector, normal: Vector, u: (f64, f64)) -> Vec<BsdfSample> {
+        let Some(lobes) = self.lobes(outgoing, normal, u) else {
+            return Vec::new();
+        };
+        let reflection = lobes.reflection.map(|sample| BsdfSample { weight: sample.weight * lobes.fresnel, ..sample });
+        let refraction = lobes.refraction.map(|sample| BsdfSample { weight: sample.weight * (1.0 - lobes.fresnel), ..sample });
+        reflection.into_iter().chain(refraction).collect()
+    }
 }
@@ -2,10 +2,14 @@ use std::io;
 use std::path::Path;
 use image::ImageError;
 use serde::Deserialize;
+use crate::models::bvh::Bvh;
 use crate::models::intersection::Intersection;
-use crate::models::material::{Material, MaterialSolid, MaterialTextured};
+use crate::models::material::{Material, MaterialConductor, MaterialDielectric, MaterialSolid, MaterialTextured};
+use crate::models::principled::MaterialPrincipled;
+use crate::models::motion::Motion;
 use crate::models::ray::Ray;
 use crate::models::surface::Surface;
+use crate::models::transform::TransformMatrices;
 use crate::models::triangle::Triangle;
 use crate::services::obj_parser_service::read_obj_file;
 
@@ -16,8 +20,18 @@ pub struct Mesh {
     pub material_solid: Option<MaterialSolid>,
     #[serde(default)]
     pub material_textured: Option<MaterialTextured>,
+    #[serde(default)]
+    pub material_conductor: Option<MaterialConductor>,
+    #[serde(default)]
+    pub material_dielectric: Option<MaterialDielectric>,
+    #[serde(default)]
+    pub material_principled: Option<MaterialPrincipled>,
+    #[serde(default)]
+    pub motion: Option<Motion>,
     #[serde(skip)]
     pub triangles: Vec<Triangle>,
+    #[serde(skip)]
+    pub bvh: Bvh,
 }
 
 impl Mesh {
@@ -27,17 +41,28 @@ impl Mesh {
             name,
             material_solid,
             material_textured,
+            material_conductor: None,
+            material_dielectric: None,
+            material_principled: None,
+            motion: None,
             triangles: Vec::new(),
+            bvh: Bvh::default(),
         }
     }
 
-    /// Loads triangles from an OBJ file
+    /// Loads triangles from an OBJ file and builds the acceleration structure
     pub fn load_obj<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
         let obj_model = read_obj_file(path)?;
-        self.triangles = obj_model.to_triangles();
+        self.set_triangles(obj_model.to_triangles()?);
         Ok(())
     }
 
+    /// Replaces the triangles of the mesh and rebuilds the BVH over them
+    pub fn set_triangles(&mut self, triangles: Vec<Triangle>) {
+        self.bvh = Bvh::build(&triangles);
+        self.triangles = triangles;
+    }
+
     /// Loads the texture for this mesh if it has a textured material.
     pub fn load_texture(&mut self, base_path: &Path) -> Result<(), ImageError> {
         if let Some(ref mut textured) = self.material_textured {
@@ -47,14 +72,38 @@ impl Mesh {
         Ok(())
     }
 
-    //noinspection ALL
     pub fn material(&self) -> Material {
-        if let Some(m) = &self.material_solid {
-            Material::Solid(m.clone())
-        } else if let Some(m) = &self.material_textured {
-            Material::Textured(m.clone())
-        } else {
-            unreachable!("There must always be either a solid or textured material.");
+        Material::first_of(
+            &self.material_solid,
+            &self.material_textured,
+            &self.material_conductor,
+            &self.material_dielectric,
+            &self.material_principled,
+        )
+        .unwrap_or_else(|| unreachable!("There must always be a material."))
+    }
+
+    pub fn has_material(&self) -> bool {
+        self.material_solid.is_some()
+            || self.material_textured.is_some()
+            || self.material_conductor.is_some()
+            || self.material_dielectric.is_some()
+            || self.material_principled.is_some()
+    }
+
+    /// Replaces the material of the surface
+    pub fn set_material(&mut self, material: &Material) {
+        self.material_solid = None;
+        self.material_textured = None;
+        self.material_conductor = None;
+        self.material_dielectric = None;
+        self.material_principled = None;
+        match material {
+            Material::Solid(m) => self.material_solid = Some(m.clone()),
+            Material::Textured(m) => self.material_textured = Some(m.clone()),
+            Material::Conductor(m) => self.material_conductor = Some(m.clone()),
+            Material::Dielectric(m) => self.material_dielectric = Some(m.clone()),
+            Material::Principled(m) => self.material_principled = Some(m.as_ref().clone()),
         }
     }
 }
@@ -63,21 +112,37 @@ impl Surface for Mesh {
     /// Computes if there is an intersection between the mesh and a ray.
     /// If an intersection exists, returns an `Intersection` object with the intersection data.
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
-        let mut closest: Option<Intersection> = None;
-
-        // Iterate through all triangles in the mesh
-        for triangle in &self.triangles {
-            // Check for intersection with the triangle
-            if let Some(intersection) = triangle.intersect(ray, &self.material()) {
-                // Keep the closest intersection
-                closest = match closest {
-                    Some(current) if intersection.t < current.t => Some(intersection),
-                    None => Some(intersection),
-                    _ => closest,
-                };
-            }
+        if let Some(motion) = &self.motion {
+            // A moving mesh is intersected in its own space at the time of the ray
+            let matrices = motion.matrices_at(ray.time, &TransformMatrices::default());
+            let local_ray = matrices.ray_to_object(ray);
+            return self.bvh.intersect(&self.triangles, &local_ray)
+                .map(|(i, hit)| self.triangles[i].intersection(&local_ray, hit, self.material()))
+                .map(|intersection| matrices.intersection_to_world(intersection));
         }
 
-        closest
+        // Traverse the BVH to only test triangles whose bounds are hit by the ray,
+        // the material is only cloned for the closest one
+        self.bvh.intersect(&self.triangles, ray)
+            .map(|(i, hit)| self.triangles[i].intersection(ray, hit, self.material()))
+    }
+
+    /// Returns the intersections with all triangles of the mesh sorted by distance.
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let material = self.material();
+        let matrices = match &self.motion {
+            Some(motion) => motion.matrices_at(ray.time, &TransformMatrices::default()),
+            None => TransformMatrices::default(),
+        };
+        let local_ray = matrices.ray_to_object(ray);
+
+        let mut hits: Vec<Intersection> = self.triangles
+            .iter()
+            .filter_map(|triangle| triangle.intersect(&local_ray, &material))
+            .map(|intersection| matrices.intersection_to_world(intersection))
+            .collect();
+
+        hits.sort_by(|a, b| a.t.total_cmp(&b.t));
+        hits
     }
 }
\ No newline at end of file
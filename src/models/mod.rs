@@ -11,6 +11,29 @@ pub mod ray;
 pub mod intersection;
 pub mod mesh;
 pub mod triangle;
+pub mod csg;
+pub mod matrix;
+pub mod transform;
+pub mod bvh;
+pub mod instance;
+pub mod group;
+pub mod random;
+pub mod motion;
+pub mod animation;
+pub mod turntable;
+pub mod framebuffer;
+pub mod progressive;
+pub mod checkpoint;
+pub mod progress;
+pub mod aov;
+pub mod denoiser;
+pub mod adaptive;
+pub mod sampler;
+pub mod bsdf;
+pub mod principled;
+pub mod shading;
+pub mod spectrum;
+pub mod bump;
 
 pub type Vertex = point::Point;
 pub type Normal = vector::Vector;
\ No newline at end of file
@@ -0,0 +1,82 @@
+use serde::Deserialize;
+use crate::models::matrix::Matrix4;
+use crate::models::transform::TransformMatrices;
+use crate::models::vector::Vector;
+
+/// Movement of an object over time, given as keyframes that are linearly interpolated.
+/// Before the first and after the last keyframe the object keeps still.
+#[derive(Debug, Deserialize, PartialEq, Clone, Default)]
+pub struct Motion {
+    #[serde(rename = "$value", default)]
+    pub keyframe: Vec<MotionKeyframe>,
+}
+
+/// Offset of an object at a point in time.
+/// The rotation (in degrees around x, then y, then z) is about the origin of the object
+/// and is applied before the translation.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct MotionKeyframe {
+    pub time: f64,
+    #[serde(default)]
+    pub translate: Option<Vector>,
+    #[serde(default)]
+    pub rotate: Option<Vector>,
+}
+
+impl MotionKeyframe {
+    fn translation(&self) -> Vector {
+        self.translate.unwrap_or(Vector::new(0.0, 0.0, 0.0))
+    }
+
+    fn rotation(&self) -> Vector {
+        self.rotate.unwrap_or(Vector::new(0.0, 0.0, 0.0))
+    }
+}
+
+impl Motion {
+    pub fn new(keyframe: Vec<MotionKeyframe>) -> Self {
+        Self { keyframe }
+    }
+
+    /// Returns the interpolated translation and rotation at the given time
+    pub fn offset_at(&self, time: f64) -> (Vector, Vector) {
+        // Closest keyframes before and after the time, in any order they are listed
+        let before = self.keyframe.iter()
+            .filter(|k| k.time <= time)
+            .max_by(|a, b| a.time.total_cmp(&b.time));
+        let after = self.keyframe.iter()
+            .filter(|k| k.time > time)
+            .min_by(|a, b| a.time.total_cmp(&b.time));
+
+        match (before, after) {
+            (Some(a), Some(b)) => {
+                let s = (time - a.time) / (b.time - a.time);
+                (
+                    a.translation() + (b.translation() - a.translation()) * s,
+                    a.rotation() + (b.rotation() - a.rotation()) * s,
+                )
+            }
+            (Some(k), None) | (None, Some(k)) => (k.translation(), k.rotation()),
+            (None, None) => (Vector::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 0.0)),
+        }
+    }
+
+    /// Returns the translation at the given time
+    pub fn translation_at(&self, time: f64) -> Vector {
+        self.offset_at(time).0
+    }
+
+    /// Returns the matrices that place the object at the given time, on top of its static transformation.
+    /// The rotation is applied in object space, so the object turns around its own origin,
+    /// while the translation is applied last and is given in world units.
+    /// The inverse is built directly, as rotations are inverted by their transpose.
+    pub fn matrices_at(&self, time: f64, base: &TransformMatrices) -> TransformMatrices {
+        let (t, r) = self.offset_at(time);
+        let rotation = Matrix4::rotation_z(r.z) * Matrix4::rotation_y(r.y) * Matrix4::rotation_x(r.x);
+
+        TransformMatrices {
+            object_to_world: Matrix4::translation(t.x, t.y, t.z) * base.object_to_world * rotation,
+            world_to_object: rotation.transpose() * base.world_to_object * Matrix4::translation(-t.x, -t.y, -t.z),
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use std::f64::consts::PI;
+use std::path::Path;
+use image::ImageError;
+use serde::Deserialize;
+use crate::models::bsdf::{self, Bsdf, BsdfSample, Frame, Ggx};
+use crate::models::bump::{self, BumpMap, NormalMap};
+use crate::models::color::Color;
+use crate::models::material::{Absorption, MaterialDielectric, Texture};
+use crate::models::vector::Vector;
+
+/// Material with the parameters of the Disney principled BSDF, which go from 0 to 1 except the index of refraction.
+/// Each parameter can be multiplied by a texture, the scalar ones by the brightness of their texture.
+/// source: Burley, Physically Based Shading at Disney
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct MaterialPrincipled {
+    pub base_color: Color,
+    /// 0 for a dielectric up to 1 for a metal colored by the base color
+    #[serde(default)]
+    pub metallic: f64,
+    #[serde(default = "default_roughness")]
+    pub roughness: f64,
+    /// Strength of the reflection of dielectrics, 0.5 for a reflectance of 4% at normal incidence
+    #[serde(default = "default_specular")]
+    pub specular: f64,
+    /// Strength of a second, colorless coat on top of the material
+    #[serde(default)]
+    pub clearcoat: f64,
+    #[serde(default = "default_clearcoat_roughness")]
+    pub clearcoat_roughness: f64,
+    /// Brightens the diffuse reflection at grazing angles, as on cloth
+    #[serde(default)]
+    pub sheen: f64,
+    /// 0 for an opaque material up to 1 for glass tinted by the base color
+    #[serde(default)]
+    pub transmission: f64,
+    /// Index of refraction of the transmitted light
+    #[serde(default = "default_ior")]
+    pub ior: f64,
+    /// Absorption inside of transmissive materials, in addition to the tint by the base color
+    #[serde(default)]
+    pub absorption: Option<Absorption>,
+    #[serde(default)]
+    pub base_color_map: Option<Texture>,
+    #[serde(default)]
+    pub metallic_map: Option<Texture>,
+    #[serde(default)]
+    pub roughness_map: Option<Texture>,
+    #[serde(default)]
+    pub specular_map: Option<Texture>,
+    #[serde(default)]
+    pub clearcoat_map: Option<Texture>,
+    #[serde(default)]
+    pub sheen_map: Option<Texture>,
+    #[serde(default)]
+    pub transmission_map: Option<Texture>,
+    #[serde(default)]
+    pub normal_map: Option<NormalMap>,
+    #[serde(default)]
+    pub bump_map: Option<BumpMap>,
+}
+
+fn default_roughness() -> f64 {
+    0.5
+}
+
+fn default_specular() -> f64 {
+    0.5
+}
+
+fn default_clearcoat_roughness() -> f64 {
+    0.1
+}
+
+fn default_ior() -> f64 {
+    1.5
+}
+
+impl MaterialPrincipled {
+    /// Loads the textures of all parameters that have one
+    pub fn load_textures(&mut self, base_path: &Path) -> Result<(), ImageError> {
+        let maps = [
+            &mut self.base_color_map,
+            &mut self.metallic_map,
+            &mut self.roughness_map,
+            &mut self.specular_map,
+            &mut self.clearcoat_map,
+            &mut self.sheen_map,
+            &mut self.transmission_map,
+        ];
+        for texture in maps.into_iter().flatten() {
+            texture.load(base_path)?;
+        }
+        bump::load_maps(&mut self.normal_map, &mut self.bump_map, base_path)
+    }
+
+    /// Returns the parameters at the texture coordinates of a point.
+    /// Textures that are not loaded leave their parameter unchanged.
+    pub fn at(&self, uv: (f64, f64)) -> Principled {
+        let color = |map: &Option<Texture>| map.as_ref().and_then(|texture| texture.sample(uv));
+        let scalar = |value: f64, map: &Option<Texture>| {
+            let factor = color(map).map_or(1.0, |c| (c.r + c.g + c.b) / 3.0);
+            (value * factor).clamp(0.0, 1.0)
+        };
+        let base_color = self.base_color * color(&self.base_color_map).unwrap_or(Color::WHITE);
+
+        Principled {
+            base_color: Color::new(base_color.r.clamp(0.0, 1.0), base_color.g.clamp(0.0, 1.0), base_color.b.clamp(0.0, 1.0)),
+            metallic: scalar(self.metallic, &self.metallic_map),
+            roughness: scalar(self.roughness, &self.roughness_map),
+            specular: scalar(self.specular, &self.specular_map),
+            clearcoat: scalar(self.clearcoat, &self.clearcoat_map),
+            clearcoat_roughness: self.clearcoat_roughness.clamp(0.0, 1.0),
+            sheen: scalar(self.sheen, &self.sheen_map),
+            transmission: scalar(self.transmission, &self.transmission_map),
+            ior: self.ior,
+        }
+    }
+}
+
+/// Parameters of a principled material at one point of its surface, with the textures applied
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Principled {
+    pub base_color: Color,
+    pub metallic: f64,
+    pub roughness: f64,
+    pub specular: f64,
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
+    pub sheen: f64,
+    pub transmission: f64,
+    pub ior: f64,
+}
+
+/// Parts of the principled BSDF. Each of them scatters at most all the light it gets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lobe {
+    Clearcoat,
+    Metal,
+    Specular,
+    Diffuse,
+    Transmission,
+}
+
+const LOBES: [Lobe; 5] = [Lobe::Clearcoat, Lobe::Metal, Lobe::Specular, Lobe::Diffuse, Lobe::Transmission];
+
+/// The lobes of the principled BSDF for one outgoing direction
+struct Lobes {
+    /// Frame turned towards the outgoing direction, for the lobes that reflect on both sides
+    frame: Frame,
+    /// Normal pointing to the outside of the object, which tells the transmission where the material is
+    normal: Vector,
+    /// Share of the light that each lobe gets
+    shares: [f64; 5],
+}
+
+impl Principled {
+    /// Part of the light that the material reflects diffusely
+    pub fn diffuse_albedo(&self) -> Color {
+        self.base_color * ((1.0 - self.metallic) * (1.0 - self.transmission))
+    }
+
+    /// Part of the light that passes straight through the surface, tinted like the refracted light
+    pub fn straight_transmission(&self) -> Color {
+        let tint = Color::new(self.base_color.r.sqrt(), self.base_color.g.sqrt(), self.base_color.b.sqrt());
+        tint * ((1.0 - self.metallic) * self.transmission)
+    }
+
+    /// Returns the share of the light that each lobe gets. The shares only depend on the outgoing
+    /// direction and add up to at most 1, which keeps the material from reflecting more light than arrives.
+    /// Below the surface of a transmissive material only the light of the transmission arrives.
+    fn lobes(&self, outgoing: Vector, normal: Vector) -> Lobes {
+        let transmission = (1.0 - self.metallic) * self.transmission;
+        let below = outgoing.dot(normal) < 0.0;
+        let frame = Frame::from_normal(if below { -normal } else { normal });
+        if below && transmission > 0.0 {
+            return Lobes { frame, normal, shares: [0.0, 0.0, 0.0, 0.0, 1.0] };
+        }
+
+        let cos_o = frame.to_local(outgoing).z;
+        let clearcoat = self.clearcoat * Self::schlick(0.04, cos_o);
+        let base = 1.0 - clearcoat;
+        let dielectric = base * (1.0 - self.metallic) * (1.0 - self.transmission);
+        let specular = Self::schlick(0.08 * self.specular, cos_o);
+        let shares = [
+            clearcoat,
+            base * self.metallic,
+            dielectric * specular,
+            dielectric * (1.0 - specular),
+            base * transmission,
+        ];
+        Lobes { frame, normal, shares }
+    }
+
+    fn schlick(f0: f64, cos_i: f64) -> f64 {
+        bsdf::fresnel_schlick(Color::new(f0, f0, f0), cos_i).r
+    }
+
+    fn ggx(&self, lobe: Lobe) -> Ggx {
+        match lobe {
+            Lobe::Clearcoat => Ggx::from_roughness(self.clearcoat_roughness),
+            _ => Ggx::from_roughness(self.roughness),
+        }
+    }
+
+    fn dielectric(&self) -> MaterialDielectric {
+        MaterialDielectric { ior: self.ior, roughness: self.roughness, absorption: None, cauchy: None, sellmeier: None }
+    }
+
+    /// Light passing through the surface is tinted by the square root of the base color,
+    /// so that it has the base color after entering and leaving the material
+    fn transmission_tint(&self, outgoing: Vector, incoming: Vector, normal: Vector) -> Color {
+        if outgoing.dot(normal) * incoming.dot(normal) < 0.0 {
+            Color::new(self.base_color.r.sqrt(), self.base_color.g.sqrt(), self.base_color.b.sqrt())
+        } else {
+            Color::WHITE
+        }
+    }
+
+    /// Value of a single lobe, without its share
+    fn eval_lobe(&self, lobe: Lobe, lobes: &Lobes, outgoing: Vector, incoming: Vector) -> Color {
+        let (wo, wi) = (lobes.frame.to_local(outgoing), lobes.frame.to_local(incoming));
+        match lobe {
+            Lobe::Clearcoat | Lobe::Specular => Color::WHITE * self.ggx(lobe).reflection(wo, wi),
+            Lobe::Metal => {
+                let fresnel = bsdf::fresnel_schlick(self.base_color, wo.dot((wo + wi).normalize()));
+                fresnel * self.ggx(lobe).reflection(wo, wi)
+            }
+            Lobe::Diffuse => {
+                if wo.z <= 0.0 || wi.z <= 0.0 {
+                    return Color::BLACK;
+                }
+                let sheen = self.sheen * (1.0 - wi.dot((wo + wi).normalize())).powi(5);
+                (self.base_color * (1.0 - sheen) + Color::WHITE * sheen) * (1.0 / PI)
+            }
+            Lobe::Transmission => {
+                let normal = lobes.normal;
+                self.dielectric().eval(outgoing, incoming, normal) * self.transmission_tint(outgoing, incoming, normal)
+            }
+        }
+    }
+
+    /// Probability density of sampling a single lobe
+    fn lobe_pdf(&self, lobe: Lobe, lobes: &Lobes, outgoing: Vector, incoming: Vector) -> f64 {
+        let (wo, wi) = (lobes.frame.to_local(outgoing), lobes.frame.to_local(incoming));
+        match lobe {
+            Lobe::Clearcoat | Lobe::Metal | Lobe::Specular => self.ggx(lobe).reflection_pdf(wo, wi),
+            Lobe::Diffuse => wi.z.max(0.0) / PI,
+            Lobe::Transmission => self.dielectric().pdf(outgoing, incoming, lobes.normal),
+        }
+    }
+
+    /// Samples a direction of a single lobe, with the weight and density of the lobe alone
+    fn sample_lobe(&self, lobe: Lobe, lobes: &Lobes, outgoing: Vector, u: (f64, f64), u_lobe: f64) -> Option<BsdfSample> {
+        let frame = &lobes.frame;
+        let wo = frame.to_local(outgoing);
+        if lobe == Lobe::Transmission {
+            let sample = self.dielectric().sample(outgoing, lobes.normal, u, u_lobe)?;
+            let tint = self.transmission_tint(outgoing, sample.direction, lobes.normal);
+            return Some(BsdfSample { weight: sample.weight * tint, ..sample });
+        }
+        if wo.z <= 0.0 {
+            return None;
+        }
+        if lobe == Lobe::Diffuse {
+            let wi = bsdf::sample_cosine_hemisphere(u);
+            let incoming = frame.to_world(wi);
+            let pdf = wi.z / PI;
+            let weight = self.eval_lobe(lobe, lobes, outgoing, incoming) * (wi.z / pdf);
+            return (pdf > 0.0).then_some(BsdfSample { direction: incoming, weight, pdf, specular: false });
+        }
+
+        let ggx = self.ggx(lobe);
+        let fresnel = |cos: f64| match lobe {
+            Lobe::Metal => bsdf::fresnel_schlick(self.base_color, cos),
+            _ => Color::WHITE,
+        };
+        if ggx.is_smooth() {
+            return Some(BsdfSample {
+                direction: frame.to_world(Vector::new(-wo.x, -wo.y, wo.z)),
+                weight: fresnel(wo.z),
+                pdf: 1.0,
+                specular: true,
+            });
+        }
+
+        let h = ggx.sample_visible_normal(wo, u);
+        let wi = bsdf::reflect(wo, h);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction: frame.to_world(wi),
+            weight: fresnel(wo.dot(h)) * (ggx.g(wo, wi, h) / ggx.g1(wo, h)),
+            pdf: ggx.reflection_pdf(wo, wi),
+            specular: false,
+        })
+    }
+}
+
+impl Bsdf for Principled {
+    fn eval(&self, outgoing: Vector, incoming: Vector, normal: Vector) -> Color {
+        let lobes = self.lobes(outgoing, normal);
+        let mut value = Color::BLACK;
+        for (lobe, share) in LOBES.into_iter().zip(lobes.shares) {
+            if share > 0.0 {
+                value += self.eval_lobe(lobe, &lobes, outgoing, incoming) * share;
+            }
+        }
+        value
+    }
+
+    /// Chooses a lobe in proportion to its share, then a direction of the lobe
+    fn sample(&self, outgoing: Vector, normal: Vector, u: (f64, f64), u_lobe: f64) -> Option<BsdfSample> {
+        let lobes = self.lobes(outgoing, normal);
+        let shares = lobes.shares;
+        let total: f64 = shares.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut start = 0.0;
+        let mut chosen = LOBES.len() - 1;
+        for (index, share) in shares.iter().enumerate() {
+            if *share > 0.0 && u_lobe * total < start + share {
+                chosen = index;
+                break;
+            }
+            start += share;
+        }
+        let chance = shares[chosen] / total;
+        // The rest of the number chooses between reflection and refraction of the transmission
+        let u_rest = ((u_lobe * total - start) / shares[chosen]).clamp(0.0, 1.0 - f64::EPSILON);
+        let sample = self.sample_lobe(LOBES[chosen], &lobes, outgoing, u, u_rest)?;
+
+        if sample.specular {
+            return Some(BsdfSample {
+                weight: sample.weight * (shares[chosen] / chance),
+                pdf: sample.pdf * chance,
+                ..sample
+            });
+        }
+        // Other lobes could have chosen the direction as well
+        let pdf = self.pdf(outgoing, sample.direction, normal);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let value = self.eval(outgoing, sample.direction, normal);
+        Some(BsdfSample {
+            weight: value * (sample.direction.dot(normal).abs() / pdf),
+            pdf,
+            ..sample
+        })
+    }
+
+    fn pdf(&self, outgoing: Vector, incoming: Vector, normal: Vector) -> f64 {
+        let lobes = self.lobes(outgoing, normal);
+        let total: f64 = lobes.shares.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        LOBES.into_iter()
+            .zip(lobes.shares)
+            .filter(|(_, share)| *share > 0.0)
+            .map(|(lobe, share)| share / total * self.lobe_pdf(lobe, &lobes, outgoing, incoming))
+            .sum()
+    }
+
+    /// Follows every lobe but the diffuse one, whose light the ray tracer takes from the lights
+    /// and the ambient light instead of tracing rays in all directions
+    fn sample_lobes(&self, outgoing: Vector, normal: Vector, u: (f64, f64)) -> Vec<BsdfSample> {
+        let lobes = self.lobes(outgoing, normal);
+        let mut samples = Vec::new();
+        for (lobe, share) in LOBES.into_iter().zip(lobes.shares) {
+            if share <= 0.0 {
+                continue;
+            }
+            match lobe {
+                Lobe::Diffuse => {}
+                Lobe::Transmission => {
+                    for sample in self.dielectric().sample_lobes(outgoing, normal, u) {
+                        let tint = self.transmission_tint(outgoing, sample.direction, normal);
+                        samples.push(BsdfSample { weight: sample.weight * tint * share, ..sample });
+                    }
+                }
+                _ => {
+                    if let Some(sample) = self.sample_lobe(lobe, &lobes, outgoing, u, 0.5) {
+                        samples.push(BsdfSample { weight: sample.weight * share, ..sample });
+                    }
+                }
+            }
+        }
+        samples
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// State of a running render, passed to the progress callback after every row.
+/// Pixels are counted once per pass, so `pixels_total` is the resolution times the number of passes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub pixels_done: u64,
+    pub pixels_total: u64,
+    /// Number of finished passes
+    pub passes_done: u32,
+    pub passes_total: u32,
+    pub elapsed: Duration,
+    /// Number of rays traced so far, including reflected, refracted and shadow rays
+    pub rays: u64,
+}
+
+impl Progress {
+    /// Returns the finished part of the render between 0 and 1
+    pub fn fraction(&self) -> f64 {
+        if self.pixels_total == 0 {
+            1.0
+        } else {
+            self.pixels_done as f64 / self.pixels_total as f64
+        }
+    }
+
+    /// Estimates the remaining time from the speed so far, None before the first pixel is done
+    pub fn eta(&self) -> Option<Duration> {
+        if self.pixels_done == 0 {
+            return None;
+        }
+        let remaining = self.pixels_total.saturating_sub(self.pixels_done) as f64;
+        Some(self.elapsed.mul_f64(remaining / self.pixels_done as f64))
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.rays as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Lets another thread stop a running render.
+/// Clones share the same state, so one clone can be handed to the renderer and one kept to cancel it.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Progress bar for the command line, drawn on stderr
+#[derive(Debug, Default)]
+pub struct ProgressBar {
+    last_permille: Option<u64>,
+    finished: bool,
+}
+
+impl ProgressBar {
+    const WIDTH: usize = 30;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redraws the bar, only when the progress changed visibly
+    pub fn update(&mut self, progress: &Progress) {
+        let permille = (progress.fraction() * 1000.0) as u64;
+        if self.last_permille == Some(permille) {
+            return;
+        }
+        self.last_permille = Some(permille);
+
+        let filled = (progress.fraction() * Self::WIDTH as f64) as usize;
+        let eta = progress.eta().map_or(String::from("--"), |eta| format!("{:.0} s", eta.as_secs_f64()));
+        let mut stderr = io::stderr();
+        let _ = write!(
+            stderr,
+            "\r[{}{}] {:5.1}% {:.0} s, ETA {}, {:.2} M rays/s ",
+            "#".repeat(filled),
+            "-".repeat(Self::WIDTH - filled),
+            progress.fraction() * 100.0,
+            progress.elapsed.as_secs_f64(),
+            eta,
+            progress.rays_per_second() / 1e6,
+        );
+        let _ = stderr.flush();
+
+        if progress.pixels_done >= progress.pixels_total {
+            self.finish();
+        }
+    }
+
+    /// Ends the line of the bar, for renders that stop before they are complete
+    pub fn finish(&mut self) {
+        if self.last_permille.is_some() && !self.finished {
+            eprintln!();
+            self.finished = true;
+        }
+    }
+}
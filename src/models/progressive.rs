@@ -0,0 +1,30 @@
+use serde::Deserialize;
+
+/// Renders the image in passes of one sample per pixel and writes the current estimate
+/// to the output file while rendering. Rendering ends once all samples of the camera
+/// are taken or, if given, the time limit is reached. Time is checked after every pass.
+#[derive(Debug, Deserialize, PartialEq, Clone, Default)]
+pub struct Progressive {
+    /// Writes the image whenever this many seconds passed since the last write
+    #[serde(default)]
+    pub seconds: Option<f64>,
+    /// Writes the image after every this many passes
+    #[serde(default)]
+    pub passes: Option<u32>,
+    /// Stops rendering after this many seconds and keeps the image rendered so far
+    #[serde(default)]
+    pub time_limit: Option<f64>,
+}
+
+impl Progressive {
+    /// Returns if the image should be written after the given pass
+    pub fn should_write(&self, pass: u32, seconds_since_write: f64) -> bool {
+        self.passes.is_some_and(|n| n > 0 && pass.is_multiple_of(n))
+            || self.seconds.is_some_and(|s| seconds_since_write >= s)
+    }
+
+    /// Returns if the time limit is reached
+    pub fn out_of_time(&self, elapsed_seconds: f64) -> bool {
+        self.time_limit.is_some_and(|limit| elapsed_seconds >= limit)
+    }
+}
@@ -0,0 +1,48 @@
+/// Small pseudo random number generator (PCG32).
+/// Seeded explicitly so that renders are reproducible.
+/// source: https://www.pcg-random.org/download.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Random {
+    state: u64,
+    increment: u64,
+}
+
+impl Random {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut random = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        random.next_u32();
+        random.state = random.state.wrapping_add(seed);
+        random.next_u32();
+        random
+    }
+
+    /// Creates a generator for a pixel, so that every pixel gets its own sequence
+    pub fn for_pixel(x: u32, y: u32, seed: u64) -> Self {
+        Self::new(seed, ((y as u64) << 32) | x as u64)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xor_shifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xor_shifted.rotate_right(rot)
+    }
+
+    /// Returns a uniformly distributed number in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        self.next_u32() as f64 / 4294967296.0
+    }
+
+    /// Returns a pair of uniformly distributed numbers in [0, 1)
+    pub fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_f64(), self.next_f64())
+    }
+}
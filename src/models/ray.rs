@@ -7,6 +7,8 @@ pub struct Ray {
     pub direction: Vector,
     pub t_min: f64, // Minimum distance
     pub t_max: f64, // Maximum distance
+    pub time: f64,  // Point in time the ray is sent, for motion blur
+    pub wavelength: Option<f64>, // Wavelength in nanometers of the light the ray carries in spectral renders
 }
 
 impl Ray {
@@ -16,9 +18,21 @@ impl Ray {
             direction: direction.normalize(),
             t_min,
             t_max,
+            time: 0.0,
+            wavelength: None,
         }
     }
 
+    /// Returns the same ray sent at another point in time
+    pub fn with_time(self, time: f64) -> Ray {
+        Ray { time, ..self }
+    }
+
+    /// Returns the same ray carrying light of another wavelength
+    pub fn with_wavelength(self, wavelength: Option<f64>) -> Ray {
+        Ray { wavelength, ..self }
+    }
+
     /// Calculates a point along the ray at distance t
     pub fn at(&self, t: f64) -> Point {
         self.origin + self.direction * t
@@ -35,6 +49,6 @@ impl Ray {
             reflected_direction.normalize(),
             1e-6,
             f64::INFINITY,
-        )
+        ).with_time(self.time).with_wavelength(self.wavelength)
     }
 }
@@ -0,0 +1,415 @@
+use serde::Deserialize;
+use crate::models::random::Random;
+
+/// Produces the random numbers of a sample: the position in the pixel, on the lens, the time,
+/// and after that any light and BSDF samples, each taken from the next dimensions.
+/// The numbers only depend on the pixel, the index of the sample and the seed,
+/// so the image is the same no matter in which order or on how many threads pixels are rendered.
+pub trait Sampler {
+    /// Starts the sample with the given index of a pixel, restarting at the first dimension
+    fn start_sample(&mut self, x: u32, y: u32, index: u32);
+    /// Returns the next dimension of the sample, uniformly distributed in [0, 1)
+    fn next_1d(&mut self) -> f64;
+    /// Returns the next two dimensions of the sample, which are distributed well together
+    fn next_2d(&mut self) -> (f64, f64);
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplerType {
+    /// Uniform random numbers without any structure
+    #[default]
+    Independent,
+    /// Jittered samples with one sample in every cell of a grid over the pixel
+    Stratified,
+    /// Halton sequence, rotated per pixel
+    Halton,
+    /// Two dimensional Sobol sequence, scrambled per pixel and dimension
+    Sobol,
+    /// Sobol sequence shared by neighboring pixels, so their errors form blue noise
+    #[serde(rename = "bluenoise")]
+    BlueNoise,
+}
+
+/// Selects the sampler of the camera. Defaults to independent random numbers.
+#[derive(Debug, Deserialize, PartialEq, Clone, Default)]
+pub struct SamplerSettings {
+    #[serde(rename = "type", default)]
+    pub kind: SamplerType,
+    /// Changes all random numbers of the image, for a different noise pattern
+    #[serde(default)]
+    pub seed: u32,
+}
+
+impl SamplerSettings {
+    /// Creates the sampler for an image with the given number of samples per pixel
+    pub fn create(&self, samples: u32) -> Box<dyn Sampler> {
+        match self.kind {
+            SamplerType::Independent => Box::new(IndependentSampler::new(self.seed)),
+            SamplerType::Stratified => Box::new(StratifiedSampler::new(samples, self.seed)),
+            SamplerType::Halton => Box::new(HaltonSampler::new(self.seed)),
+            SamplerType::Sobol => Box::new(SobolSampler::new(self.seed)),
+            SamplerType::BlueNoise => Box::new(BlueNoiseSampler::new(samples, self.seed)),
+        }
+    }
+}
+
+/// Uniform random numbers from a generator seeded with the pixel and the sample index
+pub struct IndependentSampler {
+    seed: u32,
+    random: Random,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u32) -> Self {
+        Self { seed, random: Random::new(seed as u64, 0) }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.random = Random::for_pixel(x, y, ((self.seed as u64) << 32) | index as u64);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        self.random.next_f64()
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        self.random.next_2d()
+    }
+}
+
+/// Correlated multi-jittered samples: in two dimensions every sample lies in its own cell of a grid
+/// and also in its own row and column of the finer grid inside the cells, in one dimension every
+/// sample lies in its own interval. Each dimension gets its own shuffling of the cells.
+/// source: Kensler, Correlated Multi-Jittered Sampling, 2013
+pub struct StratifiedSampler {
+    samples: u32,
+    seed: u32,
+    pixel_seed: u32,
+    index: u32,
+    dimension: u32,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples: u32, seed: u32) -> Self {
+        Self { samples: samples.max(1), seed, pixel_seed: 0, index: 0, dimension: 0 }
+    }
+
+    fn next_seed(&mut self) -> u32 {
+        self.dimension += 1;
+        hash_combine(self.pixel_seed, self.dimension)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel_seed = pixel_seed(x, y, self.seed);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let p = self.next_seed();
+        let stratum = permute(self.index % self.samples, self.samples, p);
+        (stratum as f64 + jitter(self.index, p.wrapping_mul(0x967a889b))) / self.samples as f64
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let p = self.next_seed();
+        let n = self.samples;
+        let columns = (n as f64).sqrt().ceil() as u32;
+        let rows = n.div_ceil(columns);
+
+        let s = permute(self.index % n, n, p.wrapping_mul(0x51633e2d));
+        let (column, row) = (s % columns, s / columns);
+        let sx = permute(column, columns, p.wrapping_mul(0x68bc21eb));
+        let sy = permute(row, rows, p.wrapping_mul(0x02e5be93));
+        let jx = jitter(self.index, p.wrapping_mul(0x967a889b));
+        let jy = jitter(self.index, p.wrapping_mul(0x368cc8b7));
+
+        let x = (column as f64 + (sy as f64 + jx) / rows as f64) / columns as f64;
+        let y = (row as f64 + (sx as f64 + jy) / columns as f64) / rows as f64;
+        (x.min(ONE_MINUS_EPSILON), y.min(ONE_MINUS_EPSILON))
+    }
+}
+
+/// Halton sequence with a prime base per dimension, shifted by a random offset per pixel and dimension.
+/// Dimensions beyond the table of primes fall back to independent random numbers.
+pub struct HaltonSampler {
+    seed: u32,
+    pixel_seed: u32,
+    index: u32,
+    dimension: usize,
+}
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+];
+
+impl HaltonSampler {
+    pub fn new(seed: u32) -> Self {
+        Self { seed, pixel_seed: 0, index: 0, dimension: 0 }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel_seed = pixel_seed(x, y, self.seed);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        let offset_seed = hash_combine(self.pixel_seed, dimension as u32);
+
+        match PRIMES.get(dimension) {
+            Some(&base) => (radical_inverse(base, self.index) + to_unit(hash(offset_seed))).fract(),
+            None => to_unit(hash_combine(offset_seed, self.index)),
+        }
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+/// The first two dimensions of the Sobol sequence, used for every pair of dimensions with
+/// its own nested uniform scrambling and shuffled order, so the pairs are not correlated.
+/// source: Burley, Practical Hash-based Owen Scrambling, 2020
+pub struct SobolSampler {
+    seed: u32,
+    pixel_seed: u32,
+    index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u32) -> Self {
+        Self { seed, pixel_seed: 0, index: 0, dimension: 0 }
+    }
+
+    fn next_seed(&mut self) -> u32 {
+        self.dimension += 1;
+        hash_combine(self.pixel_seed, self.dimension)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel_seed = pixel_seed(x, y, self.seed);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let seed = self.next_seed();
+        sobol_1d(self.index, seed)
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let seed = self.next_seed();
+        sobol_2d(self.index, seed)
+    }
+}
+
+/// One scrambled Sobol sequence shared by all pixels of a tile, of which every pixel takes its own
+/// consecutive part. The pixels are ordered along a randomly flipped Z curve, so every block of 2 x 2,
+/// 4 x 4, ... pixels takes a consecutive part as well, whose samples are spread as evenly as those of
+/// a single pixel. The errors of neighboring pixels cancel out, so the remaining noise has
+/// no low frequencies and looks finer.
+/// source: Ahmed and Wonka, Screen-Space Blue-Noise Diffusion of Monte Carlo Sampling Error via
+/// Hierarchical Ordering of Pixels, 2020
+pub struct BlueNoiseSampler {
+    seed: u32,
+    /// Number of sequence elements per pixel, the samples per pixel rounded up to a power of two
+    stride: u32,
+    tile_seed: u32,
+    index: u32,
+    dimension: u32,
+}
+
+impl BlueNoiseSampler {
+    pub fn new(samples: u32, seed: u32) -> Self {
+        let stride = samples.max(1).next_power_of_two().min(1 << (32 - 2 * TILE_BITS));
+        Self { seed, stride, tile_seed: 0, index: 0, dimension: 0 }
+    }
+
+    fn next_seed(&mut self) -> u32 {
+        self.dimension += 1;
+        hash_combine(self.tile_seed, self.dimension)
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        let tile_size = 1 << TILE_BITS;
+        self.tile_seed = pixel_seed(x / tile_size, y / tile_size, self.seed);
+        // Scrambling flips the quadrants on every level of the curve, keeping the blocks together
+        let shift = 32 - 2 * TILE_BITS;
+        let pixel = nested_uniform_scramble(morton(x % tile_size, y % tile_size) << shift, self.tile_seed) >> shift;
+        self.index = pixel.wrapping_mul(self.stride).wrapping_add(index % self.stride);
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let seed = self.next_seed();
+        sobol_1d(self.index, seed)
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let seed = self.next_seed();
+        sobol_2d(self.index, seed)
+    }
+}
+
+/// Tiles of 2^6 x 2^6 pixels share a sequence
+const TILE_BITS: u32 = 6;
+
+/// Largest f64 below 1, keeps samples inside [0, 1)
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+fn to_unit(value: u32) -> f64 {
+    value as f64 / 4294967296.0
+}
+
+/// Mixes the bits of a value, source: https://nullprogram.com/blog/2018/07/31/
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^= x >> 16;
+    x
+}
+
+fn hash_combine(seed: u32, value: u32) -> u32 {
+    hash(seed ^ value.wrapping_add(0x9e3779b9).wrapping_add(seed << 6).wrapping_add(seed >> 2))
+}
+
+fn pixel_seed(x: u32, y: u32, seed: u32) -> u32 {
+    hash_combine(hash_combine(hash(seed), x), y)
+}
+
+/// Returns the position of `i` in a random permutation of [0, length) chosen by `p`
+fn permute(mut i: u32, length: u32, p: u32) -> u32 {
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        // Values outside of the length are permuted again until they fall inside
+        if i < length {
+            break;
+        }
+    }
+    (i + p) % length
+}
+
+/// Random offset in [0, 1) for sample `i`, chosen by `p`
+fn jitter(mut i: u32, p: u32) -> f64 {
+    i ^= p;
+    i ^= i >> 17;
+    i ^= i >> 10;
+    i = i.wrapping_mul(0xb36534e5);
+    i ^= i >> 12;
+    i ^= i >> 21;
+    i = i.wrapping_mul(0x93fc4795);
+    i ^= 0xdf6e307f;
+    i ^= i >> 17;
+    i = i.wrapping_mul(1 | p >> 18);
+    to_unit(i)
+}
+
+/// Mirrors the digits of the index in the given base at the decimal point
+fn radical_inverse(base: u32, mut index: u32) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut factor = inverse_base;
+    let mut result = 0.0;
+    while index > 0 {
+        result += (index % base) as f64 * factor;
+        index /= base;
+        factor *= inverse_base;
+    }
+    result.min(ONE_MINUS_EPSILON)
+}
+
+/// Returns the first (0) or second (1) dimension of the Sobol sequence as 32 bit fraction
+fn sobol(index: u32, dimension: u32) -> u32 {
+    if dimension == 0 {
+        return index.reverse_bits();
+    }
+    let mut result = 0;
+    let mut direction = 1u32 << 31;
+    let mut index = index;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+/// Scrambles the bits of a 32 bit fraction so that the result is still stratified like the input
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x ^= x.wrapping_mul(0x3d20adea);
+    x = x.wrapping_add(seed);
+    x = x.wrapping_mul((seed >> 16) | 1);
+    x ^= x.wrapping_mul(0x05526c56);
+    x ^= x.wrapping_mul(0x53a22864);
+    x.reverse_bits()
+}
+
+fn sobol_1d(index: u32, seed: u32) -> f64 {
+    let index = nested_uniform_scramble(index, hash(seed));
+    to_unit(nested_uniform_scramble(sobol(index, 0), hash_combine(seed, 0)))
+}
+
+fn sobol_2d(index: u32, seed: u32) -> (f64, f64) {
+    let index = nested_uniform_scramble(index, hash(seed));
+    (
+        to_unit(nested_uniform_scramble(sobol(index, 0), hash_combine(seed, 0))),
+        to_unit(nested_uniform_scramble(sobol(index, 1), hash_combine(seed, 1))),
+    )
+}
+
+/// Interleaves the bits of x and y into the position on the Z curve
+fn morton(x: u32, y: u32) -> u32 {
+    let spread = |mut v: u32| {
+        v &= 0x0000ffff;
+        v = (v | (v << 8)) & 0x00ff00ff;
+        v = (v | (v << 4)) & 0x0f0f0f0f;
+        v = (v | (v << 2)) & 0x33333333;
+        v = (v | (v << 1)) & 0x55555555;
+        v
+    };
+    spread(x) | (spread(y) << 1)
+}
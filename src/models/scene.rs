@@ -1,10 +1,21 @@
 use std::io;
 use std::path::Path;
+use std::sync::Arc;
 use serde::Deserialize;
+use crate::models::animation::Animation;
+use crate::models::bump;
+use crate::models::aov::Aovs;
 use crate::models::camera::Camera;
+use crate::models::checkpoint::Checkpoint;
 use crate::models::color::Color;
+use crate::models::denoiser::Denoiser;
+use crate::models::instance::Definitions;
 use crate::models::lights::Lights;
-use crate::models::surface::{Surfaces, SurfaceType}; // Import from surface.rs
+use crate::models::material::{MaterialSolid, MaterialTextured};
+use crate::models::principled::MaterialPrincipled;
+use crate::models::progressive::Progressive;
+use crate::models::surface::{Surfaces, SurfaceType};
+use crate::models::turntable::Turntable;
 
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct Scene {
@@ -12,18 +23,149 @@ pub struct Scene {
     pub background_color: Color,
     pub camera: Camera,
     pub lights: Lights,
+    #[serde(default)]
+    pub definitions: Definitions,
     pub surfaces: Surfaces,
+    #[serde(default)]
+    pub animation: Option<Animation>,
+    #[serde(default)]
+    pub turntable: Option<Turntable>,
+    #[serde(default)]
+    pub progressive: Option<Progressive>,
+    #[serde(default)]
+    pub checkpoint: Option<Checkpoint>,
+    #[serde(default)]
+    pub aovs: Option<Aovs>,
+    #[serde(default)]
+    pub denoiser: Option<Denoiser>,
+    /// FNV-1a hash of the XML the scene was parsed from, set by `Scene::from_xml`
+    #[serde(skip)]
+    pub source_hash: u64,
 }
 
 impl Scene {
     /// Loads OBJ models for all meshes in the scene
     pub fn load_meshes(&mut self) -> io::Result<()> {
+        self.load_definitions()?;
+
+        for surface in &mut self.surfaces.surfaces {
+            Self::load_surface(surface, &self.definitions)?;
+        }
+
+        if let Some(animation) = &self.animation {
+            animation.prepare(&self.lights, &mut self.surfaces)?;
+        }
+
+        if let Some(turntable) = &self.turntable {
+            turntable.validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses a scene from its XML and remembers the hash of the text for `content_hash`
+    pub fn from_xml(content: &str) -> Result<Scene, serde_xml_rs::Error> {
+        let mut scene: Scene = serde_xml_rs::from_str(content)?;
+        scene.source_hash = fnv1a(FNV_OFFSET_BASIS, content.as_bytes());
+        Ok(scene)
+    }
+
+    /// Hash of the scene file and the render settings, used to tell if a checkpoint belongs to the scene.
+    /// The hash is the same across builds and platforms. OBJ models and textures are not part of it.
+    pub fn content_hash(&self) -> u64 {
+        let camera = &self.camera;
+        let settings = [
+            camera.resolution.horizontal,
+            camera.resolution.vertical,
+            camera.samples.n,
+            camera.max_bounces.n,
+        ];
+        settings
+            .iter()
+            .fold(self.source_hash, |hash, setting| fnv1a(hash, &setting.to_le_bytes()))
+    }
+
+    /// Loads the geometry definitions once, before they get shared by instances.
+    /// A definition that instances other definitions is loaded after them.
+    fn load_definitions(&mut self) -> io::Result<()> {
+        let order = Self::definition_order(&self.definitions)?;
+        let mut loaded = Definitions::default();
+
+        for index in order {
+            let geometry = &mut self.definitions.geometry[index];
+            let name = geometry.name.clone();
+            let geometry = Arc::get_mut(geometry).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Geometry definition {} is already in use, the scene was loaded before", name),
+                )
+            })?;
+            for surface in &mut geometry.surfaces {
+                Self::load_surface(surface, &loaded)?;
+            }
+            loaded.geometry.push(Arc::clone(&self.definitions.geometry[index]));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the indices of the definitions in an order where every definition
+    /// comes after the ones it instances
+    fn definition_order(definitions: &Definitions) -> io::Result<Vec<usize>> {
+        let mut order: Vec<usize> = Vec::new();
+
+        while order.len() < definitions.geometry.len() {
+            let is_loaded = |name: &str| order.iter().any(|&i| definitions.geometry[i].name == name);
+            let next = (0..definitions.geometry.len()).find(|i| {
+                !order.contains(i) && definitions.geometry[*i].dependencies().into_iter().all(is_loaded)
+            });
+
+            match next {
+                Some(index) => order.push(index),
+                None => {
+                    let remaining = (0..definitions.geometry.len()).filter(|i| !order.contains(i));
+                    let dependencies: Vec<&str> = remaining
+                        .flat_map(|i| definitions.geometry[i].dependencies())
+                        .collect();
+                    return Err(match dependencies.iter().find(|name| definitions.find(name).is_none()) {
+                        Some(name) => io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!("Unknown geometry definition: {}", name),
+                        ),
+                        None => io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Geometry definitions instance each other: {}", dependencies.join(", ")),
+                        ),
+                    });
+                }
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Sets all animated properties to their value at the given frame, starting at 1.
+    /// Has no effect on scenes without animation.
+    pub fn set_frame(&mut self, frame: u32) {
+        if let Some(animation) = &self.animation {
+            animation.apply(frame as f64, &mut self.camera, &mut self.lights, &mut self.surfaces);
+        }
+    }
+
+    /// Loads the OBJ model and texture of a surface, descending into CSG children
+    /// and groups, and links instances to their geometry definition.
+    /// Fails for spheres and meshes left without a material by their groups.
+    fn load_surface(surface: &mut SurfaceType, definitions: &Definitions) -> io::Result<()> {
         let obj_base_path = "assets/obj_models/";
         // Base path used for loading textures; adjust as needed.
         let base_path = Path::new(".");
 
-        for surface in &mut self.surfaces.surfaces {
-            if let SurfaceType::Mesh(mesh) = surface {
+        match surface {
+            SurfaceType::Mesh(mesh) => {
+                if !mesh.has_material() {
+                    return Err(Self::missing_material(&format!("Mesh {}", mesh.name)));
+                }
+
                 // Load the geometry from the OBJ file.
                 let obj_path = format!("{}{}", obj_base_path, mesh.name);
                 mesh.load_obj(&obj_path)?;
@@ -35,9 +177,84 @@ impl Scene {
                     mesh.load_texture(base_path)
                         .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
                 }
+                Self::load_principled(&mut mesh.material_principled, base_path)?;
+                Self::load_normal_maps(&mut mesh.material_solid, &mut mesh.material_textured, base_path)?;
+            }
+            SurfaceType::Csg(csg) => {
+                for child in &mut csg.children {
+                    Self::load_surface(child, definitions)?;
+                }
+            }
+            SurfaceType::Instance(instance) => {
+                let geometry = definitions.find(&instance.geometry).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("Unknown geometry definition: {}", instance.geometry),
+                    )
+                })?;
+                instance.resolve(Arc::clone(geometry));
+
+                if let Some(textured) = &mut instance.material_textured {
+                    textured.texture.load(base_path).map_err(io::Error::other)?;
+                }
+                Self::load_principled(&mut instance.material_principled, base_path)?;
+                Self::load_normal_maps(&mut instance.material_solid, &mut instance.material_textured, base_path)?;
+            }
+            SurfaceType::Group(group) => {
+                group.apply_default_material(None);
+                group.resolve();
+                for child in &mut group.surfaces.surfaces {
+                    Self::load_surface(child, definitions)?;
+                }
+            }
+            SurfaceType::Sphere(sphere) => {
+                if !sphere.has_material() {
+                    return Err(Self::missing_material("Sphere"));
+                }
+                Self::load_principled(&mut sphere.material_principled, base_path)?;
+                Self::load_normal_maps(&mut sphere.material_solid, &mut sphere.material_textured, base_path)?;
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Loads the textures of a principled material, if the surface has one
+    fn load_principled(material: &mut Option<MaterialPrincipled>, base_path: &Path) -> io::Result<()> {
+        match material {
+            Some(principled) => principled.load_textures(base_path).map_err(io::Error::other),
+            None => Ok(()),
+        }
+    }
+
+    /// Loads the normal and bump maps of a Phong material, if the surface has one
+    fn load_normal_maps(
+        solid: &mut Option<MaterialSolid>,
+        textured: &mut Option<MaterialTextured>,
+        base_path: &Path,
+    ) -> io::Result<()> {
+        if let Some(solid) = solid {
+            bump::load_maps(&mut solid.normal_map, &mut solid.bump_map, base_path).map_err(io::Error::other)?;
+        }
+        if let Some(textured) = textured {
+            bump::load_maps(&mut textured.normal_map, &mut textured.bump_map, base_path).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    /// Error for a surface that has no material, neither its own nor the default of a group
+    fn missing_material(surface: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} has no material, set one on it or on an enclosing group", surface),
+        )
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Continues the 64 bit FNV-1a hash with the bytes
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
@@ -0,0 +1,157 @@
+use std::f64::consts::PI;
+use serde::Deserialize;
+use crate::models::vector::Vector;
+
+/// Model of the diffuse reflection of the Phong materials
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffuseModel {
+    #[default]
+    Lambert,
+    /// Rough surfaces like clay or the moon, which stay bright towards their silhouette
+    OrenNayar,
+}
+
+/// Model of the highlight of the Phong materials
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecularModel {
+    #[default]
+    Phong,
+    /// Highlight around the halfway vector, which needs about four times the Phong exponent for the same size
+    BlinnPhong,
+    /// Microfacet highlight with a Beckmann distribution and the Fresnel reflectance of the index of refraction
+    CookTorrance,
+    /// Anisotropic highlight, stretched along the tangent or the bitangent
+    Ward,
+}
+
+/// Local shading model of a Phong material, Lambert and Phong by default.
+/// The terms are scaled like the Lambert term, which leaves out the 1 / pi of its BRDF.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct Shading {
+    #[serde(default)]
+    pub diffuse: DiffuseModel,
+    #[serde(default)]
+    pub specular: SpecularModel,
+    /// Standard deviation of the facet slopes, in radians for Oren-Nayar
+    #[serde(default = "default_roughness")]
+    pub roughness: f64,
+    /// Roughness of the Ward model along the tangent, the roughness if not set
+    #[serde(default)]
+    pub roughness_x: Option<f64>,
+    /// Roughness of the Ward model along the bitangent, the roughness if not set
+    #[serde(default)]
+    pub roughness_y: Option<f64>,
+}
+
+fn default_roughness() -> f64 {
+    0.3
+}
+
+impl Default for Shading {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Shading {
+    pub const DEFAULT: Shading = Shading {
+        diffuse: DiffuseModel::Lambert,
+        specular: SpecularModel::Phong,
+        roughness: 0.3,
+        roughness_x: None,
+        roughness_y: None,
+    };
+
+    /// Returns the diffuse reflection of the light from `light_dir` towards `view_dir`,
+    /// before the diffuse coefficient and the colors
+    pub fn diffuse(&self, normal: Vector, light_dir: Vector, view_dir: Vector) -> f64 {
+        let cos_i = normal.dot(light_dir);
+        if cos_i <= 0.0 {
+            return 0.0;
+        }
+
+        match self.diffuse {
+            DiffuseModel::Lambert => cos_i,
+            DiffuseModel::OrenNayar => {
+                // source: Oren and Nayar, Generalization of Lambert's Reflectance Model
+                let cos_o = normal.dot(view_dir).clamp(0.0, 1.0);
+                let sigma2 = self.roughness * self.roughness;
+                let a = 1.0 - 0.5 * sigma2 / (sigma2 + 0.33);
+                let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+                // Cosine of the angle between the directions around the normal
+                let light_tangent = light_dir - normal * cos_i;
+                let view_tangent = view_dir - normal * cos_o;
+                let lengths = light_tangent.length() * view_tangent.length();
+                let cos_phi = if lengths > 0.0 { light_tangent.dot(view_tangent) / lengths } else { 0.0 };
+
+                let (sin_i, sin_o) = ((1.0 - cos_i * cos_i).sqrt(), (1.0 - cos_o * cos_o).sqrt());
+                // sin(alpha) tan(beta) with alpha the larger and beta the smaller angle to the normal
+                let (sin_alpha, tan_beta) = if cos_i < cos_o {
+                    (sin_i, sin_o / cos_o.max(1e-6))
+                } else {
+                    (sin_o, sin_i / cos_i)
+                };
+                cos_i * (a + b * cos_phi.max(0.0) * sin_alpha * tan_beta)
+            }
+        }
+    }
+
+    /// Returns the highlight of the light from `light_dir` towards `view_dir`, before the specular
+    /// coefficient and the light color. `ior` is the index of refraction for the Fresnel reflectance.
+    pub fn specular(&self, normal: Vector, light_dir: Vector, view_dir: Vector, exponent: f64, ior: f64) -> f64 {
+        let (cos_i, cos_o) = (normal.dot(light_dir), normal.dot(view_dir));
+        let half = (light_dir + view_dir).normalize();
+
+        match self.specular {
+            SpecularModel::Phong => {
+                let reflection_dir = (normal * (2.0 * cos_i) - light_dir).normalize();
+                view_dir.dot(reflection_dir).max(0.0).powf(exponent)
+            }
+            SpecularModel::BlinnPhong => {
+                if cos_i <= 0.0 {
+                    return 0.0;
+                }
+                normal.dot(half).max(0.0).powf(exponent)
+            }
+            SpecularModel::CookTorrance => {
+                // source: Cook and Torrance, A Reflectance Model for Computer Graphics
+                if cos_i <= 0.0 || cos_o <= 0.0 {
+                    return 0.0;
+                }
+                let cos_h = normal.dot(half).max(1e-6);
+                let m2 = (self.roughness * self.roughness).max(1e-6);
+                let tan2 = (1.0 - cos_h * cos_h) / (cos_h * cos_h);
+                let d = (-tan2 / m2).exp() / (PI * m2 * cos_h.powi(4));
+
+                let cos_vh = view_dir.dot(half).max(1e-6);
+                let g = (2.0 * cos_h * cos_o / cos_vh).min(2.0 * cos_h * cos_i / cos_vh).min(1.0);
+                let f0 = ((ior - 1.0) / (ior + 1.0)).powi(2);
+                let f = f0 + (1.0 - f0) * (1.0 - cos_vh).powi(5);
+                PI * d * f * g / (4.0 * cos_o)
+            }
+            SpecularModel::Ward => {
+                // source: Ward, Measuring and Modeling Anisotropic Reflection
+                if cos_i <= 0.0 || cos_o <= 0.0 {
+                    return 0.0;
+                }
+                let alpha_x = self.roughness_x.unwrap_or(self.roughness).max(1e-3);
+                let alpha_y = self.roughness_y.unwrap_or(self.roughness).max(1e-3);
+                let (tangent, bitangent) = Self::tangents(normal);
+                let cos_h = normal.dot(half).max(1e-6);
+                let exponent = -((half.dot(tangent) / alpha_x).powi(2) + (half.dot(bitangent) / alpha_y).powi(2))
+                    / (cos_h * cos_h);
+                cos_i * exponent.exp() / (4.0 * alpha_x * alpha_y * (cos_i * cos_o).sqrt())
+            }
+        }
+    }
+
+    /// Tangent running around the y axis, like the lines of latitude of a sphere, and the bitangent
+    fn tangents(normal: Vector) -> (Vector, Vector) {
+        let axis = if normal.y.abs() < 0.999 { Vector::new(0.0, 1.0, 0.0) } else { Vector::new(1.0, 0.0, 0.0) };
+        let tangent = axis.cross(normal).normalize();
+        (tangent, normal.cross(tangent))
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::OnceLock;
+use serde::Deserialize;
+use crate::models::color::Color;
+
+/// Shortest wavelength of visible light in nanometers
+pub const MIN_WAVELENGTH: f64 = 380.0;
+/// Longest wavelength of visible light in nanometers
+pub const MAX_WAVELENGTH: f64 = 780.0;
+
+/// Traces every camera sample with a single wavelength of light, so that materials with a
+/// dispersion model split white light into its colors. The rays still carry RGB colors,
+/// which are weighted by the color of the wavelength and average out to the RGB render.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct Spectral {}
+
+impl Spectral {
+    /// Maps a uniform sample in [0, 1) to a wavelength of visible light in nanometers
+    pub fn sample_wavelength(sample: f64) -> f64 {
+        MIN_WAVELENGTH + (MAX_WAVELENGTH - MIN_WAVELENGTH) * sample
+    }
+
+    /// Returns the weight of a sample traced with the wavelength, its color scaled so that
+    /// the weights of uniformly sampled wavelengths average to white
+    pub fn weight(wavelength: f64) -> Color {
+        static MEAN: OnceLock<Color> = OnceLock::new();
+        let mean = MEAN.get_or_init(|| {
+            let steps = (MAX_WAVELENGTH - MIN_WAVELENGTH) as usize;
+            let mut sum = Color::BLACK;
+            for i in 0..steps {
+                sum += wavelength_to_rgb(Self::sample_wavelength((i as f64 + 0.5) / steps as f64));
+            }
+            sum * (1.0 / steps as f64)
+        });
+        let rgb = wavelength_to_rgb(wavelength);
+        Color::new(rgb.r / mean.r, rgb.g / mean.g, rgb.b / mean.b)
+    }
+}
+
+/// Returns the CIE 1931 color matching functions x, y and z at a wavelength in nanometers
+/// source: Wyman, Sloan and Shirley, Simple Analytic Approximations to the CIE XYZ Color Matching Functions
+pub fn cie_xyz(wavelength: f64) -> (f64, f64, f64) {
+    // Gaussian with different widths left and right of its peak
+    let lobe = |peak: f64, left: f64, right: f64| {
+        let t = (wavelength - peak) / if wavelength < peak { left } else { right };
+        (-0.5 * t * t).exp()
+    };
+
+    let x = 1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7) - 0.065 * lobe(501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8);
+    (x, y, z)
+}
+
+/// Returns the linear sRGB color of light with a single wavelength in nanometers.
+/// Most of these colors are outside of the sRGB gamut, the negative channels are clipped.
+pub fn wavelength_to_rgb(wavelength: f64) -> Color {
+    let (x, y, z) = cie_xyz(wavelength);
+    Color::new(
+        (3.2404542 * x - 1.5371385 * y - 0.4985314 * z).max(0.0),
+        (-0.9692660 * x + 1.8760108 * y + 0.0415560 * z).max(0.0),
+        (0.0556434 * x - 0.2040259 * y + 1.0572252 * z).max(0.0),
+    )
+}
+
+/// Index of refraction by Cauchy's equation n = a + b / λ² + c / λ⁴, with λ in micrometers
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct Cauchy {
+    pub a: f64,
+    #[serde(default)]
+    pub b: f64,
+    #[serde(default)]
+    pub c: f64,
+}
+
+impl Cauchy {
+    /// Returns the index of refraction at a wavelength in nanometers
+    pub fn ior(&self, wavelength: f64) -> f64 {
+        let l2 = (wavelength / 1000.0).powi(2);
+        self.a + self.b / l2 + self.c / (l2 * l2)
+    }
+}
+
+/// Index of refraction by the Sellmeier equation n² = 1 + Σ bᵢ λ² / (λ² - cᵢ), with λ in micrometers
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct Sellmeier {
+    pub b1: f64,
+    #[serde(default)]
+    pub b2: f64,
+    #[serde(default)]
+    pub b3: f64,
+    pub c1: f64,
+    #[serde(default)]
+    pub c2: f64,
+    #[serde(default)]
+    pub c3: f64,
+}
+
+impl Sellmeier {
+    /// Returns the index of refraction at a wavelength in nanometers
+    pub fn ior(&self, wavelength: f64) -> f64 {
+        let l2 = (wavelength / 1000.0).powi(2);
+        let term = |b: f64, c: f64| b * l2 / (l2 - c);
+        (1.0 + term(self.b1, self.c1) + term(self.b2, self.c2) + term(self.b3, self.c3)).max(1.0).sqrt()
+    }
+}
+
+/// Returns the index of refraction at a wavelength in nanometers by the first of the dispersion models that is set
+pub fn ior_at(cauchy: &Option<Cauchy>, sellmeier: &Option<Sellmeier>, wavelength: f64) -> Option<f64> {
+    cauchy.as_ref().map(|cauchy| cauchy.ior(wavelength))
+        .or_else(|| sellmeier.as_ref().map(|sellmeier| sellmeier.ior(wavelength)))
+}
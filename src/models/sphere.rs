@@ -1,9 +1,14 @@
+use std::f64::consts::PI;
 use serde::Deserialize;
+use crate::models::bump::Tangents;
 use crate::models::intersection::Intersection;
 use crate::models::point::Point;
-use crate::models::material::{Material, MaterialSolid, MaterialTextured};
+use crate::models::material::{Material, MaterialConductor, MaterialDielectric, MaterialSolid, MaterialTextured};
+use crate::models::principled::MaterialPrincipled;
+use crate::models::motion::Motion;
 use crate::models::ray::Ray;
 use crate::models::surface::Surface;
+use crate::models::vector::Vector;
 
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct Sphere {
@@ -13,19 +18,77 @@ pub struct Sphere {
     pub material_solid: Option<MaterialSolid>,
     #[serde(default)]
     pub material_textured: Option<MaterialTextured>,
+    #[serde(default)]
+    pub material_conductor: Option<MaterialConductor>,
+    #[serde(default)]
+    pub material_dielectric: Option<MaterialDielectric>,
+    #[serde(default)]
+    pub material_principled: Option<MaterialPrincipled>,
+    #[serde(default)]
+    pub motion: Option<Motion>,
 }
 
 impl Sphere {
-    //noinspection ALL
+    /// Returns the center of the sphere at the given time.
+    /// A sphere looks the same after any rotation, so only the translation of the motion is used.
+    pub fn center_at(&self, time: f64) -> Point {
+        match &self.motion {
+            Some(motion) => self.position + motion.translation_at(time),
+            None => self.position,
+        }
+    }
+
     pub fn material(&self) -> Material {
-        if let Some(m) = &self.material_solid {
-            Material::Solid(m.clone())
-        } else if let Some(m) = &self.material_textured {
-            Material::Textured(m.clone())
-        } else {
-            unreachable!("There must always be either a solid or textured material.");
+        Material::first_of(
+            &self.material_solid,
+            &self.material_textured,
+            &self.material_conductor,
+            &self.material_dielectric,
+            &self.material_principled,
+        )
+        .unwrap_or_else(|| unreachable!("There must always be a material."))
+    }
+
+    pub fn has_material(&self) -> bool {
+        self.material_solid.is_some()
+            || self.material_textured.is_some()
+            || self.material_conductor.is_some()
+            || self.material_dielectric.is_some()
+            || self.material_principled.is_some()
+    }
+
+    /// Replaces the material of the surface
+    pub fn set_material(&mut self, material: &Material) {
+        self.material_solid = None;
+        self.material_textured = None;
+        self.material_conductor = None;
+        self.material_dielectric = None;
+        self.material_principled = None;
+        match material {
+            Material::Solid(m) => self.material_solid = Some(m.clone()),
+            Material::Textured(m) => self.material_textured = Some(m.clone()),
+            Material::Conductor(m) => self.material_conductor = Some(m.clone()),
+            Material::Dielectric(m) => self.material_dielectric = Some(m.clone()),
+            Material::Principled(m) => self.material_principled = Some(m.as_ref().clone()),
         }
     }
+
+    /// Returns the texture coordinates for a point with the given normal.
+    /// u goes once around the y axis, v from the bottom to the top of the sphere.
+    fn uv(normal: Vector) -> (f64, f64) {
+        let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * PI);
+        let v = 0.5 + normal.y.clamp(-1.0, 1.0).asin() / PI;
+        (u, v)
+    }
+
+    /// Returns the derivatives of the point with the given normal by the texture coordinates u and v.
+    /// At the poles, where u is undefined, the derivatives of a point next to the pole are used.
+    fn tangents(&self, normal: Vector) -> Tangents {
+        let ring = (normal.x * normal.x + normal.z * normal.z).sqrt().max(1e-9);
+        let dpdu = Vector::new(-normal.z, 0.0, normal.x) * (2.0 * PI * self.radius);
+        let dpdv = Vector::new(-normal.y * normal.x / ring, ring, -normal.y * normal.z / ring) * (PI * self.radius);
+        (dpdu, dpdv)
+    }
 }
 
 impl Surface for Sphere {
@@ -34,7 +97,8 @@ impl Surface for Sphere {
     /// data is returned
     /// source tutorial page 16, 17
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
-        let oc = ray.origin - self.position;
+        let center = self.center_at(ray.time);
+        let oc = ray.origin - center;
         let a = ray.direction.dot(ray.direction);
         let b = 2.0 * ray.direction.dot(oc);
         let c = oc.dot(oc) - self.radius * self.radius;
@@ -58,14 +122,59 @@ impl Surface for Sphere {
         };
 
         let point = ray.at(t);
-        let normal = (point - self.position).normalize();
+        let normal = (point - center).normalize();
 
         Some(Intersection {
             t,
             point,
             normal,
             material: self.material(),
+            uv: Self::uv(normal),
+            tangents: Some(self.tangents(normal)),
         })
     }
+
+    /// Returns both points where the ray enters and leaves the sphere
+    /// that lie inside the valid range of the ray.
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let center = self.center_at(ray.time);
+        let oc = ray.origin - center;
+        let a = ray.direction.dot(ray.direction);
+        let b = 2.0 * ray.direction.dot(oc);
+        let c = oc.dot(oc) - self.radius * self.radius;
+
+        let discriminant = b * b - 4.0 * a * c;
+
+        if discriminant < 0.0 {
+            return Vec::new();
+        }
+
+        let sqrt_disc = discriminant.sqrt();
+        let t1 = (-b - sqrt_disc) / (2.0 * a);
+        let t2 = (-b + sqrt_disc) / (2.0 * a);
+
+        let hits: Vec<f64> = [t1, t2]
+            .into_iter()
+            .filter(|t| *t > ray.t_min && *t < ray.t_max)
+            .collect();
+
+        // The material is resolved once and only cloned for the hits after the first
+        let materials = std::iter::repeat_n(self.material(), hits.len());
+        hits.into_iter()
+            .zip(materials)
+            .map(|(t, material)| {
+                let point = ray.at(t);
+                let normal = (point - center).normalize();
+                Intersection {
+                    t,
+                    point,
+                    normal,
+                    material,
+                    uv: Self::uv(normal),
+                    tangents: Some(self.tangents(normal)),
+                }
+            })
+            .collect()
+    }
 }
 
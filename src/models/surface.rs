@@ -3,11 +3,18 @@ use crate::models::ray::Ray;
 use crate::models::intersection::Intersection;
 use crate::models::sphere::Sphere;
 use crate::models::mesh::Mesh;
+use crate::models::csg::Csg;
+use crate::models::instance::Instance;
+use crate::models::group::Group;
 
 /// Defines the behavior for surfaces
 pub trait Surface {
     /// Calculates the intersection with a ray and returns an optional result
     fn intersect(&self, ray: &Ray) -> Option<Intersection>;
+
+    /// Calculates every intersection with a ray, sorted by distance.
+    /// Used by CSG nodes to find the intervals where the ray is inside a solid.
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection>;
 }
 
 /// A collection of surfaces that can include spheres or meshes
@@ -24,6 +31,12 @@ pub enum SurfaceType {
     Sphere(Sphere),
     #[serde(rename = "mesh")]
     Mesh(Mesh),
+    #[serde(rename = "csg")]
+    Csg(Csg),
+    #[serde(rename = "instance")]
+    Instance(Box<Instance>),
+    #[serde(rename = "group")]
+    Group(Box<Group>),
 }
 
 impl Surface for SurfaceType {
@@ -31,6 +44,19 @@ impl Surface for SurfaceType {
         match self {
             SurfaceType::Sphere(sphere) => sphere.intersect(ray),
             SurfaceType::Mesh(mesh) => mesh.intersect(ray),
+            SurfaceType::Csg(csg) => csg.intersect(ray),
+            SurfaceType::Instance(instance) => instance.intersect(ray),
+            SurfaceType::Group(group) => group.intersect(ray),
+        }
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        match self {
+            SurfaceType::Sphere(sphere) => sphere.intersect_all(ray),
+            SurfaceType::Mesh(mesh) => mesh.intersect_all(ray),
+            SurfaceType::Csg(csg) => csg.intersect_all(ray),
+            SurfaceType::Instance(instance) => instance.intersect_all(ray),
+            SurfaceType::Group(group) => group.intersect_all(ray),
         }
     }
 }
\ No newline at end of file
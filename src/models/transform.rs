@@ -0,0 +1,86 @@
+use serde::Deserialize;
+use crate::models::intersection::Intersection;
+use crate::models::matrix::Matrix4;
+use crate::models::ray::Ray;
+
+/// A sequence of transformations as listed in the scene file.
+/// The operations are multiplied in the order they appear, so the
+/// last listed operation is the first one applied to the object.
+#[derive(Debug, Deserialize, PartialEq, Clone, Default)]
+pub struct Transform {
+    #[serde(rename = "$value", default)]
+    pub operations: Vec<TransformOperation>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub enum TransformOperation {
+    #[serde(rename = "translate")]
+    Translate { x: f64, y: f64, z: f64 },
+    #[serde(rename = "scale")]
+    Scale { x: f64, y: f64, z: f64 },
+    #[serde(rename = "rotateX")]
+    RotateX { theta: f64 },
+    #[serde(rename = "rotateY")]
+    RotateY { theta: f64 },
+    #[serde(rename = "rotateZ")]
+    RotateZ { theta: f64 },
+}
+
+impl TransformOperation {
+    pub fn matrix(&self) -> Matrix4 {
+        match *self {
+            TransformOperation::Translate { x, y, z } => Matrix4::translation(x, y, z),
+            TransformOperation::Scale { x, y, z } => Matrix4::scaling(x, y, z),
+            TransformOperation::RotateX { theta } => Matrix4::rotation_x(theta),
+            TransformOperation::RotateY { theta } => Matrix4::rotation_y(theta),
+            TransformOperation::RotateZ { theta } => Matrix4::rotation_z(theta),
+        }
+    }
+}
+
+impl Transform {
+    /// Returns the object to world matrix of the transformation
+    pub fn matrix(&self) -> Matrix4 {
+        self.operations
+            .iter()
+            .fold(Matrix4::identity(), |acc, op| acc * op.matrix())
+    }
+}
+
+/// Precomputed matrices of a transformation, used to move rays
+/// into object space and intersections back into world space
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TransformMatrices {
+    pub object_to_world: Matrix4,
+    pub world_to_object: Matrix4,
+}
+
+impl TransformMatrices {
+    pub fn new(object_to_world: Matrix4) -> Self {
+        Self {
+            object_to_world,
+            // A degenerate transformation (scale of zero) makes the object invisible
+            world_to_object: object_to_world.inverse().unwrap_or(Matrix4::new([[0.0; 4]; 4])),
+        }
+    }
+
+    /// Transforms a world space ray into object space.
+    /// The direction is not normalized so that distances stay the same in both spaces.
+    pub fn ray_to_object(&self, ray: &Ray) -> Ray {
+        Ray {
+            origin: self.world_to_object.transform_point(ray.origin),
+            direction: self.world_to_object.transform_vector(ray.direction),
+            ..*ray
+        }
+    }
+
+    /// Transforms an object space intersection back into world space
+    pub fn intersection_to_world(&self, mut intersection: Intersection) -> Intersection {
+        intersection.point = self.object_to_world.transform_point(intersection.point);
+        intersection.normal = Matrix4::transform_normal(&self.world_to_object, intersection.normal);
+        intersection.tangents = intersection.tangents.map(|(dpdu, dpdv)| {
+            (self.object_to_world.transform_vector(dpdu), self.object_to_world.transform_vector(dpdv))
+        });
+        intersection
+    }
+}
@@ -1,3 +1,4 @@
+use crate::models::bump::Tangents;
 use crate::models::intersection::Intersection;
 use crate::models::ray::Ray;
 use crate::models::vector::Vector;
@@ -10,6 +11,18 @@ pub struct Triangle {
     pub v1: Point,
     pub v2: Point,
     pub normal: Vector,
+    /// Texture coordinates of the three vertices, if the model has them
+    pub uvs: Option<[(f64, f64); 3]>,
+    /// Derivatives of the points of the triangle by the texture coordinates, if it has them
+    pub tangents: Option<Tangents>,
+}
+
+/// Distance and barycentric coordinates of a hit on a triangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriangleHit {
+    pub t: f64,
+    pub u: f64,
+    pub v: f64,
 }
 
 impl Triangle {
@@ -24,11 +37,30 @@ impl Triangle {
             v1: vertices[v_indices[1]],
             v2: vertices[v_indices[2]],
             normal: normals[n_index],
+            uvs: None,
+            tangents: None,
         }
     }
 
-    /// Möller–Trumbore intersection algorithm implementation
-    pub fn intersect(&self, ray: &Ray, material: &Material) -> Option<Intersection> {
+    /// Computes the derivatives of the points of a triangle by the texture coordinates of its vertices.
+    /// Returns None if the texture coordinates do not span an area.
+    pub fn tangents(vertices: [Point; 3], uvs: [(f64, f64); 3]) -> Option<Tangents> {
+        let (e1, e2) = (vertices[1] - vertices[0], vertices[2] - vertices[0]);
+        let (du1, dv1) = (uvs[1].0 - uvs[0].0, uvs[1].1 - uvs[0].1);
+        let (du2, dv2) = (uvs[2].0 - uvs[0].0, uvs[2].1 - uvs[0].1);
+        let determinant = du1 * dv2 - du2 * dv1;
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+
+        let dpdu = (e1 * dv2 - e2 * dv1) / determinant;
+        let dpdv = (e2 * du1 - e1 * du2) / determinant;
+        Some((dpdu, dpdv))
+    }
+
+    /// Möller–Trumbore intersection algorithm implementation.
+    /// Returns the distance and barycentric coordinates of the hit, without building an `Intersection`.
+    pub fn hit(&self, ray: &Ray) -> Option<TriangleHit> {
         let e1 = self.v1 - self.v0;
         let e2 = self.v2 - self.v0;
         let h = ray.direction.cross(e2);  // Intermediate vector
@@ -64,15 +96,38 @@ impl Triangle {
             return None;
         }
 
-        // Compute intersection point and normal
-        let point = ray.at(t);
-        let normal = self.normal;
+        Some(TriangleHit { t, u, v })
+    }
+
+    /// Builds the intersection data of a hit found by `hit`
+    pub fn intersection(&self, ray: &Ray, hit: TriangleHit, material: Material) -> Intersection {
+        Intersection {
+            t: hit.t,
+            point: ray.at(hit.t),
+            normal: self.normal,
+            material,  // Use the material from the mesh
+            uv: self.uv(hit.u, hit.v),
+            tangents: self.tangents,
+        }
+    }
 
-        Some(Intersection {
-            t,
-            point,
-            normal,
-            material: material.clone(),  // Use the material from the mesh
-        })
+    /// Computes the intersection between the triangle and a ray
+    pub fn intersect(&self, ray: &Ray, material: &Material) -> Option<Intersection> {
+        self.hit(ray).map(|hit| self.intersection(ray, hit, material.clone()))
+    }
+
+    /// Interpolates the texture coordinates at the barycentric coordinates u and v.
+    /// Without texture coordinates the barycentric coordinates themselves are used.
+    fn uv(&self, u: f64, v: f64) -> (f64, f64) {
+        match self.uvs {
+            Some([uv0, uv1, uv2]) => {
+                let w = 1.0 - u - v;
+                (
+                    uv0.0 * w + uv1.0 * u + uv2.0 * v,
+                    uv0.1 * w + uv1.1 * u + uv2.1 * v,
+                )
+            }
+            None => (u, v),
+        }
     }
 }
\ No newline at end of file
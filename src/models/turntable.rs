@@ -0,0 +1,65 @@
+use std::io;
+use serde::Deserialize;
+use crate::models::camera::Camera;
+use crate::models::point::Point;
+
+/// Renders the scene from all around by orbiting the camera around its `lookat` point.
+/// The camera turns once around the up vector over all frames, keeping its distance and height.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct Turntable {
+    pub frames: u32,
+    #[serde(default)]
+    pub format: TurntableFormat,
+    /// Time each frame is shown, in milliseconds
+    #[serde(default = "default_delay")]
+    pub delay: u32,
+    /// Also saves all frames side by side in a single image
+    #[serde(default)]
+    pub contact_sheet: bool,
+    /// Number of frames per row of the contact sheet, by default the sheet is about square
+    #[serde(default)]
+    pub columns: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TurntableFormat {
+    #[default]
+    Gif,
+    Apng,
+}
+
+fn default_delay() -> u32 {
+    100
+}
+
+impl Turntable {
+    /// Checks the settings before rendering, a turntable needs at least one frame
+    pub fn validate(&self) -> io::Result<()> {
+        if self.frames == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "A turntable needs at least one frame"));
+        }
+        Ok(())
+    }
+
+    /// Returns the camera position for the given frame, starting at 0 with the position of the camera
+    pub fn camera_position(&self, camera: &Camera, frame: u32) -> Point {
+        let angle = (360.0 * frame as f64 / self.frames.max(1) as f64).to_radians();
+        let axis = camera.up.normalize();
+        let offset = camera.position - camera.look_at;
+
+        // Rodrigues' rotation formula
+        let rotated = offset * angle.cos()
+            + axis.cross(offset) * angle.sin()
+            + axis * (axis.dot(offset) * (1.0 - angle.cos()));
+
+        camera.look_at + rotated
+    }
+
+    /// Returns the number of columns of the contact sheet
+    pub fn sheet_columns(&self) -> u32 {
+        self.columns
+            .unwrap_or_else(|| (self.frames as f64).sqrt().ceil() as u32)
+            .max(1)
+    }
+}
@@ -0,0 +1,95 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use image::codecs::gif::{GifEncoder, Repeat};
+use exr::prelude::{AnyChannel, AnyChannels, Encoding, FlatSamples, Image, ImageAttributes, IntegerBounds, Layer, LayerAttributes, SmallVec, WritableImage};
+use image::{imageops, Delay, DynamicImage, Frame, ImageResult, RgbImage};
+use crate::models::aov::AovPass;
+
+/// Service to combine rendered frames into animations and overview images
+pub struct ImageExportService;
+
+impl ImageExportService {
+    /// Saves the frames as an endlessly looping animated GIF.
+    /// GIF only supports 256 colors per frame, so gradients may show banding.
+    pub fn save_gif(frames: &[RgbImage], delay_ms: u32, path: &Path) -> ImageResult<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = GifEncoder::new_with_speed(file, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+        encoder.encode_frames(frames.iter().map(|img| {
+            let rgba = DynamicImage::ImageRgb8(img.clone()).into_rgba8();
+            Frame::from_parts(rgba, 0, 0, delay)
+        }))
+    }
+
+    /// Saves the frames as an endlessly looping animated PNG, which keeps the full colors.
+    /// All frames must have the same size. The image crate can only read animated PNGs,
+    /// so they are written with the png crate it uses internally.
+    pub fn save_apng(frames: &[RgbImage], delay_ms: u32, path: &Path) -> io::Result<()> {
+        let Some(first) = frames.first() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No frames to save"));
+        };
+
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, first.width(), first.height());
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frames.len() as u32, 0).map_err(io::Error::other)?;
+        encoder.set_frame_delay(delay_ms.min(u16::MAX as u32) as u16, 1000).map_err(io::Error::other)?;
+
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        for frame in frames {
+            writer.write_image_data(frame.as_raw()).map_err(io::Error::other)?;
+        }
+        writer.finish().map_err(io::Error::other)
+    }
+
+    /// Places the frames in a grid, row by row, with the given number of columns
+    pub fn contact_sheet(frames: &[RgbImage], columns: u32) -> RgbImage {
+        let Some(first) = frames.first() else {
+            return RgbImage::new(0, 0);
+        };
+
+        let (width, height) = first.dimensions();
+        let columns = columns.clamp(1, frames.len() as u32);
+        let rows = (frames.len() as u32).div_ceil(columns);
+
+        let mut sheet = RgbImage::new(width * columns, height * rows);
+        for (i, frame) in frames.iter().enumerate() {
+            let i = i as u32;
+            let x = (i % columns) * width;
+            let y = (i / columns) * height;
+            imageops::replace(&mut sheet, frame, x as i64, y as i64);
+        }
+        sheet
+    }
+
+    /// Saves the passes as layers of one EXR file, named after the passes.
+    /// All passes must have the same size.
+    pub fn save_exr(passes: &[AovPass], path: &Path) -> io::Result<()> {
+        let Some(first) = passes.first() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No passes to save"));
+        };
+        let size = (first.image.width() as usize, first.image.height() as usize);
+
+        let layers: SmallVec<[Layer<AnyChannels<FlatSamples>>; 2]> = passes
+            .iter()
+            .map(|pass| {
+                let channels = pass.kind.channels()
+                    .iter()
+                    .enumerate()
+                    .map(|(c, name)| {
+                        let samples = pass.image.pixels().map(|p| p[c]).collect();
+                        AnyChannel::new(*name, FlatSamples::F32(samples))
+                    })
+                    .collect();
+                Layer::new(size, LayerAttributes::named(pass.kind.name()), Encoding::SMALL_LOSSLESS, AnyChannels::sort(channels))
+            })
+            .collect();
+
+        let image = Image::from_layers(ImageAttributes::new(IntegerBounds::from_dimensions(size)), layers);
+        image.write().to_file(path).map_err(io::Error::other)
+    }
+}
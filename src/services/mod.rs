@@ -3,6 +3,7 @@ use crate::models::{point, vector};
 pub mod scene_import_service;
 pub mod render_service;
 pub mod obj_parser_service;
+pub mod image_export_service;
 
 pub type Vertex = point::Point;
 pub type Normal = vector::Vector;
\ No newline at end of file
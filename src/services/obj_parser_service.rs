@@ -7,7 +7,7 @@ use crate::models::triangle::Triangle;
 #[derive(Debug)]
 pub struct Face {
     pub vertex_indices: [usize; 3],  // Indices for 3 vertices
-    pub texture_indices: [usize; 3], // Indices for 3 texture coordinates
+    pub texture_indices: Option<[usize; 3]>, // Indices for 3 texture coordinates, if the face has them
     pub normal_indices: [usize; 3],  // Indices for 3 normals
 }
 
@@ -78,7 +78,7 @@ impl ObjModel {
         }
 
         let mut vertex_indices = [0; 3];
-        let mut texture_indices = [0; 3];
+        let mut texture_indices = [None; 3];
         let mut normal_indices = [0; 3];
 
         for (i, part) in parts.iter().skip(1).enumerate() {
@@ -87,7 +87,7 @@ impl ObjModel {
             vertex_indices[i] = indices[0].parse::<usize>().unwrap() - 1; // Convert to 0-based index
 
             if indices.len() > 1 && !indices[1].is_empty() {
-                texture_indices[i] = indices[1].parse::<usize>().unwrap() - 1;
+                texture_indices[i] = Some(indices[1].parse::<usize>().unwrap() - 1);
             }
 
             if indices.len() > 2 && !indices[2].is_empty() {
@@ -95,6 +95,12 @@ impl ObjModel {
             }
         }
 
+        // Faces where a vertex has no texture coordinates get none
+        let texture_indices = match texture_indices {
+            [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+            _ => None,
+        };
+
         self.faces.push(Face {
             vertex_indices,
             texture_indices,
@@ -102,8 +108,9 @@ impl ObjModel {
         });
     }
 
-    /// Converts the OBJ model into a list of triangles
-    pub fn to_triangles(&self) -> Vec<Triangle> {
+    /// Converts the OBJ model into a list of triangles.
+    /// Fails if a face refers to texture coordinates that the file does not have.
+    pub fn to_triangles(&self) -> io::Result<Vec<Triangle>> {
         let mut triangles = Vec::new();
 
         for face in &self.faces {
@@ -121,16 +128,40 @@ impl ObjModel {
                 (v1 - v0).cross(v2 - v0).normalize()
             };
 
+            // Get texture coordinates (if available)
+            let uvs = match face.texture_indices {
+                Some(indices) => Some(self.texture_coords_of(indices)?),
+                None => None,
+            };
+
+            // Tangents for normal and bump maps follow the texture coordinates
+            let tangents = uvs.and_then(|uvs| Triangle::tangents([v0, v1, v2], uvs));
+
             // Create triangle
             triangles.push(Triangle {
                 v0,
                 v1,
                 v2,
                 normal,
+                uvs,
+                tangents,
             });
         }
 
-        triangles
+        Ok(triangles)
+    }
+
+    fn texture_coords_of(&self, indices: [usize; 3]) -> io::Result<[(f64, f64); 3]> {
+        let mut uvs = [(0.0, 0.0); 3];
+        for (uv, index) in uvs.iter_mut().zip(indices) {
+            *uv = *self.texture_coords.get(index).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Face uses texture coordinate {} but only {} are defined", index + 1, self.texture_coords.len()),
+                )
+            })?;
+        }
+        Ok(uvs)
     }
 }
 
@@ -3,45 +3,546 @@ use crate::models::ray::Ray;
 use crate::models::intersection::Intersection;
 use crate::models::surface::{Surface, SurfaceType};
 use crate::models::color::Color;
-use image::{RgbImage, Rgb};
+use image::{imageops, ImageBuffer, Pixel, Rgb, Rgb32FImage, RgbImage};
+use std::cell::Cell;
+use std::fs;
 use std::path::Path;
-use crate::models::material::Material;
+use std::time::Instant;
+use crate::models::adaptive::Adaptive;
+use crate::models::bsdf::Bsdf;
+use crate::models::denoiser::Guides;
+use crate::models::aov::{self, AovFormat, AovImages, AovKind, AovPass, AovSample, Aovs};
+use crate::models::framebuffer::{Framebuffer, RenderBuffers};
+use crate::models::material::{Gloss, Material};
 use crate::models::point::Point;
+use crate::models::progress::{CancelToken, Progress, ProgressBar};
+use crate::models::sampler::Sampler;
+use crate::models::spectrum::Spectral;
+use crate::models::turntable::TurntableFormat;
+use crate::services::image_export_service::ImageExportService;
 use crate::models::vector::Vector;
 
+thread_local! {
+    /// Number of rays traced by the current thread, read for the progress report
+    static RAYS_TRACED: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Number of transmissive surfaces a shadow ray passes before the light counts as blocked
+const MAX_SHADOW_SURFACES: u32 = 16;
+
 /// Service to generate a ray traced image from a scene
 pub struct RenderService;
 
+/// Keeps track of a running render and passes its progress to the callback
+struct ProgressReporter<'a> {
+    start: Instant,
+    rays_at_start: u64,
+    pixels_per_pass: u64,
+    pixels_done: u64,
+    passes_total: u32,
+    on_progress: &'a mut dyn FnMut(&Progress),
+    cancel: &'a CancelToken,
+}
+
+impl<'a> ProgressReporter<'a> {
+    /// Starts reporting, counting the passes the framebuffer already has as done
+    fn new(
+        framebuffer: &Framebuffer,
+        passes_total: u32,
+        on_progress: &'a mut dyn FnMut(&Progress),
+        cancel: &'a CancelToken,
+    ) -> Self {
+        let pixels_per_pass = framebuffer.width as u64 * framebuffer.height as u64;
+        Self {
+            start: Instant::now(),
+            rays_at_start: RAYS_TRACED.with(Cell::get),
+            pixels_per_pass,
+            pixels_done: pixels_per_pass * framebuffer.passes() as u64,
+            passes_total,
+            on_progress,
+            cancel,
+        }
+    }
+
+    /// Reports a finished row, returns false if the render should stop
+    fn row_done(&mut self, pixels: u32) -> bool {
+        self.pixels_done += pixels as u64;
+        let progress = Progress {
+            pixels_done: self.pixels_done,
+            pixels_total: self.pixels_per_pass * self.passes_total as u64,
+            passes_done: (self.pixels_done / self.pixels_per_pass.max(1)) as u32,
+            passes_total: self.passes_total,
+            elapsed: self.start.elapsed(),
+            rays: RAYS_TRACED.with(Cell::get) - self.rays_at_start,
+        };
+        (self.on_progress)(&progress);
+        !self.cancel.is_cancelled()
+    }
+}
+
 impl RenderService {
     /// Generates and saves the ray traced image
     pub fn generate_image(scene: &Scene) {
+        let mut progress_bar = ProgressBar::new();
+        let cancel = CancelToken::new();
+        if scene.progressive.is_some() || scene.checkpoint.is_some() {
+            Self::generate_in_passes(scene, &mut |p| progress_bar.update(p), &cancel);
+            progress_bar.finish();
+            return;
+        }
+
+        let framebuffer = Self::render_with_progress(scene, &mut |p| progress_bar.update(p), &cancel);
+        // Adaptive sampling may finish before the bar is full
+        progress_bar.finish();
+        if let Some(framebuffer) = framebuffer {
+            let guides = Self::denoise_guides(scene);
+            Self::save_image(Self::output_image(scene, &framebuffer, guides.as_ref()), scene);
+            Self::save_heatmap(scene, &framebuffer);
+            Self::save_aovs(scene, &framebuffer, guides.as_ref());
+        }
+    }
+
+    /// Renders every frame of an animated scene and saves them as a numbered
+    /// image sequence in a folder named after the output file
+    pub fn generate_animation(scene: &mut Scene) {
+        let frames = scene.animation.as_ref().map_or(1, |animation| animation.frames);
+
+        for frame in 1..=frames {
+            scene.set_frame(frame);
+            let img = Self::render_image(scene);
+            Self::save_frame(img, scene, frame);
+        }
+    }
+
+    /// Renders the scene while the camera orbits around its look at point and saves
+    /// the frames as an animated GIF or PNG, optionally together with a contact sheet
+    pub fn generate_turntable(scene: &mut Scene) {
+        let Some(turntable) = scene.turntable.clone() else {
+            return;
+        };
+
+        let start = scene.camera.position;
+        let mut frames = Vec::new();
+        for frame in 0..turntable.frames {
+            scene.camera.position = turntable.camera_position(&scene.camera, frame);
+            frames.push(Self::render_image(scene));
+            println!("Rendered turntable frame {} of {}", frame + 1, turntable.frames);
+            scene.camera.position = start;
+        }
+
+        let name = Path::new(&scene.output_file).file_stem().unwrap_or_default().to_string_lossy();
+        let output_path = match turntable.format {
+            TurntableFormat::Gif => {
+                let path = Path::new("output").join(format!("{}_turntable.gif", name));
+                ImageExportService::save_gif(&frames, turntable.delay, &path).expect("Failed to save animation");
+                path
+            }
+            TurntableFormat::Apng => {
+                let path = Path::new("output").join(format!("{}_turntable.png", name));
+                ImageExportService::save_apng(&frames, turntable.delay, &path).expect("Failed to save animation");
+                path
+            }
+        };
+        println!("Animation saved to: {}", output_path.display());
+
+        if turntable.contact_sheet {
+            let sheet = ImageExportService::contact_sheet(&frames, turntable.sheet_columns());
+            let sheet_path = Path::new("output").join(format!("{}_contact_sheet.png", name));
+            sheet.save(&sheet_path).expect("Failed to save image");
+            println!("Contact sheet saved to: {}", sheet_path.display());
+        }
+    }
+
+    /// Renders the scene without writing to disk, calling `on_progress` after every finished row.
+    /// Returns None if the render was cancelled with the token.
+    pub fn render_with_progress(
+        scene: &Scene,
+        on_progress: &mut dyn FnMut(&Progress),
+        cancel: &CancelToken,
+    ) -> Option<Framebuffer> {
+        let mut framebuffer = Self::new_framebuffer(scene);
+        let passes = scene.camera.samples.n.max(1);
+        let mut reporter = ProgressReporter::new(&framebuffer, passes, on_progress, cancel);
+
+        for _ in 0..passes {
+            if Self::is_complete(scene, &framebuffer) {
+                break;
+            }
+            if !Self::render_pass_reporting(scene, &mut framebuffer, &mut reporter) {
+                return None;
+            }
+        }
+
+        Some(framebuffer)
+    }
+
+    /// Renders the scene as seen by its camera
+    pub fn render_image(scene: &Scene) -> RgbImage {
+        Self::output_image(scene, &Self::render_framebuffer(scene), Self::denoise_guides(scene).as_ref())
+    }
+
+    /// Renders the scene into memory without writing to disk.
+    /// The 8-bit image is only created if `with_rgb` is set.
+    pub fn render_to_buffer(scene: &Scene, with_rgb: bool) -> RenderBuffers {
+        let framebuffer = Self::render_framebuffer(scene);
+        let guides = Self::denoise_guides(scene);
+
+        RenderBuffers {
+            color: Self::output_float_image(scene, &framebuffer, guides.as_ref()),
+            rgb: with_rgb.then(|| Self::output_image(scene, &framebuffer, guides.as_ref())),
+        }
+    }
+
+    /// Creates an empty framebuffer for the image or the region of the camera
+    fn new_framebuffer(scene: &Scene) -> Framebuffer {
+        let resolution = &scene.camera.resolution;
+        let (left, top, width, height) = match &scene.camera.region {
+            Some(region) => region.pixels(resolution),
+            None => (0, 0, resolution.horizontal, resolution.vertical),
+        };
+        Framebuffer::for_region(left, top, width, height)
+    }
+
+    /// Takes all samples of the camera for every pixel
+    fn render_framebuffer(scene: &Scene) -> Framebuffer {
+        let mut framebuffer = Self::new_framebuffer(scene);
+
+        while !Self::is_complete(scene, &framebuffer) {
+            Self::render_pass(scene, &mut framebuffer);
+        }
+
+        framebuffer
+    }
+
+    /// Renders the image pass by pass. Writes the current estimate to the output file
+    /// and saves checkpoints as set by the progressive and checkpoint settings of the scene.
+    /// Calls `on_progress` after every finished row. Returns false if the render was cancelled
+    /// with the token, which leaves the last written image and checkpoint as they were.
+    pub fn generate_in_passes(scene: &Scene, on_progress: &mut dyn FnMut(&Progress), cancel: &CancelToken) -> bool {
+        let empty = Self::new_framebuffer(scene);
+        let passes = scene.camera.samples.n.max(1);
+        let progressive = scene.progressive.clone().unwrap_or_default();
+        let checkpoint_path = scene.checkpoint.as_ref().map(|c| c.path(&scene.output_file));
+        let scene_hash = checkpoint_path.as_ref().map_or(0, |_| scene.content_hash());
+
+        let mut framebuffer = match &checkpoint_path {
+            Some(path) if path.exists() => match Framebuffer::load(path, scene_hash) {
+                Ok(framebuffer) if Self::same_pixels(&framebuffer, &empty) => {
+                    println!("Resuming from {} after {} passes", path.display(), framebuffer.passes());
+                    framebuffer
+                }
+                Ok(_) => {
+                    eprintln!("Checkpoint {} does not match the image size, starting over", path.display());
+                    empty
+                }
+                Err(err) => {
+                    eprintln!("Failed to load checkpoint {}: {}, starting over", path.display(), err);
+                    empty
+                }
+            },
+            _ => empty,
+        };
+
+        let guides = Self::denoise_guides(scene);
+        let start = Instant::now();
+        let mut last_write = start;
+        let mut last_checkpoint = start;
+
+        let mut reporter = ProgressReporter::new(&framebuffer, passes, on_progress, cancel);
+
+        for pass in framebuffer.passes() + 1..=passes {
+            // A partly rendered pass is not saved, so a resumed render stays identical
+            if !Self::render_pass_reporting(scene, &mut framebuffer, &mut reporter) {
+                println!("Cancelled after {} of {} passes", pass - 1, passes);
+                return false;
+            }
+
+            if Self::is_complete(scene, &framebuffer) || progressive.out_of_time(start.elapsed().as_secs_f64()) {
+                break;
+            }
+
+            if progressive.should_write(pass, last_write.elapsed().as_secs_f64()) {
+                Self::save_image(Self::output_image(scene, &framebuffer, guides.as_ref()), scene);
+                last_write = Instant::now();
+            }
+
+            if let (Some(checkpoint), Some(path)) = (&scene.checkpoint, &checkpoint_path) {
+                if checkpoint.should_save(pass, last_checkpoint.elapsed().as_secs_f64()) {
+                    framebuffer.save(path, scene_hash).expect("Failed to save checkpoint");
+                    println!("Checkpoint saved to: {}", path.display());
+                    last_checkpoint = Instant::now();
+                }
+            }
+        }
+
+        println!("Finished after {} of {} passes in {:.1} s", framebuffer.passes(), passes, start.elapsed().as_secs_f64());
+
+        // A render stopped by the time limit can be continued, a finished one is done
+        if let Some(path) = &checkpoint_path {
+            if !Self::is_complete(scene, &framebuffer) {
+                framebuffer.save(path, scene_hash).expect("Failed to save checkpoint");
+                println!("Checkpoint saved to: {}", path.display());
+            } else if path.exists() {
+                fs::remove_file(path).expect("Failed to remove checkpoint");
+            }
+        }
+
+        Self::save_image(Self::output_image(scene, &framebuffer, guides.as_ref()), scene);
+        Self::save_heatmap(scene, &framebuffer);
+        Self::save_aovs(scene, &framebuffer, guides.as_ref());
+        true
+    }
+
+    /// Returns if the framebuffer has all samples of the camera,
+    /// or with adaptive sampling, if no pixel needs more samples
+    fn is_complete(scene: &Scene, framebuffer: &Framebuffer) -> bool {
         let camera = &scene.camera;
-        let width = camera.resolution.horizontal;
-        let height = camera.resolution.vertical;
+        framebuffer.passes() >= camera.samples.n.max(1)
+            || camera.adaptive.as_ref().is_some_and(|adaptive| !adaptive.needs_samples(framebuffer))
+    }
+
+    /// Takes one more sample for every pixel of the framebuffer
+    pub fn render_pass(scene: &Scene, framebuffer: &mut Framebuffer) {
+        for y in 0..framebuffer.height {
+            Self::render_row(scene, framebuffer, y);
+        }
+    }
+
+    /// Takes one more sample for every pixel of the framebuffer, reporting the progress after every row.
+    /// Returns false if the render was cancelled before the pass was finished.
+    fn render_pass_reporting(scene: &Scene, framebuffer: &mut Framebuffer, reporter: &mut ProgressReporter) -> bool {
+        for y in 0..framebuffer.height {
+            Self::render_row(scene, framebuffer, y);
+            if !reporter.row_done(framebuffer.width) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Takes one more sample for every pixel of a row
+    fn render_row(scene: &Scene, framebuffer: &mut Framebuffer, y: u32) {
         let max_bounces = scene.camera.max_bounces.n;
+        let adaptive = scene.camera.adaptive.as_ref();
+        let mut sampler = scene.camera.sampler.create(scene.camera.samples.n.max(1));
 
-        let mut img = RgbImage::new(width, height);
+        for x in 0..framebuffer.width {
+            // Pixels that are precise enough keep their color
+            if adaptive.is_some_and(|adaptive| adaptive.is_converged(framebuffer, x, y)) {
+                continue;
+            }
 
-        for y in 0..height {
-            for x in 0..width {
-                let ray = camera.generate_ray(x, y);
-                let color = Self::trace_ray(&ray, scene, max_bounces);
-                img.put_pixel(x, y, Self::color_to_rgb(color));
+            // The sample is numbered by the samples the pixel already has, so the result does not depend on render order
+            let i = framebuffer.index(x, y);
+            let (pixel_x, pixel_y) = (framebuffer.left + x, framebuffer.top + y);
+            sampler.start_sample(pixel_x, pixel_y, framebuffer.samples[i]);
+            let color = match Self::camera_ray(scene, sampler.as_mut(), pixel_x, pixel_y) {
+                Some(ray) => {
+                    let color = Self::trace_ray(&ray, scene, max_bounces, sampler.as_mut());
+                    ray.wavelength.map_or(color, |wavelength| color * Spectral::weight(wavelength))
+                }
+                None => scene.background_color,
+            };
+
+            framebuffer.add_sample(x, y, color);
+        }
+    }
+
+    /// Generates the camera ray for the sample the sampler was started on.
+    /// Returns None if the camera sees nothing through this part of the pixel.
+    fn camera_ray(scene: &Scene, sampler: &mut dyn Sampler, pixel_x: u32, pixel_y: u32) -> Option<Ray> {
+        let camera = &scene.camera;
+        // A single sample stays in the pixel center to keep the image sharp
+        let centered = camera.samples.n <= 1;
+
+        let pixel_sample = if centered { (0.5, 0.5) } else { sampler.next_2d() };
+        let lens_sample = sampler.next_2d();
+        let time = camera.shutter.sample_time(sampler.next_1d());
+        let wavelength = camera.spectral.as_ref().map(|_| Spectral::sample_wavelength(sampler.next_1d()));
+        camera
+            .generate_ray_sample(pixel_x, pixel_y, pixel_sample, lens_sample)
+            .map(|ray| ray.with_time(time).with_wavelength(wavelength))
+    }
+
+    /// Renders the passes selected in the AOV settings of the scene, with the same
+    /// camera rays as the image. Returns no passes if the scene has no AOV settings.
+    pub fn render_aovs(scene: &Scene) -> Vec<AovPass> {
+        let Some(settings) = &scene.aovs else {
+            return Vec::new();
+        };
+
+        let framebuffer = Self::new_framebuffer(scene);
+        Self::render_passes(scene, settings)
+            .into_iter()
+            .map(|pass| AovPass {
+                image: Self::place_in_image(scene, &framebuffer, pass.image, Rgb([0.0; 3])),
+                ..pass
+            })
+            .collect()
+    }
+
+    /// Renders the selected passes for the pixels of the framebuffer, without placing a region into the full image
+    fn render_passes(scene: &Scene, settings: &Aovs) -> Vec<AovPass> {
+        // Only used for the region
+        let framebuffer = Self::new_framebuffer(scene);
+        let mut images = AovImages::new(settings, &scene.lights, framebuffer.width, framebuffer.height);
+        let materials = aov::material_table(&scene.surfaces.surfaces);
+        let samples = scene.camera.samples.n.max(1);
+        let mut sampler = scene.camera.sampler.create(samples);
+
+        for y in 0..framebuffer.height {
+            for x in 0..framebuffer.width {
+                let (pixel_x, pixel_y) = (framebuffer.left + x, framebuffer.top + y);
+                let pixel_samples: Vec<AovSample> = (0..samples)
+                    .map(|index| {
+                        sampler.start_sample(pixel_x, pixel_y, index);
+                        match Self::camera_ray(scene, sampler.as_mut(), pixel_x, pixel_y) {
+                            Some(ray) => Self::aov_sample(&ray, scene, &materials, settings.lights),
+                            None => AovSample::miss(scene.background_color),
+                        }
+                    })
+                    .collect();
+                images.set_pixel(x, y, &pixel_samples);
             }
         }
 
-        Self::save_image(img, scene);
+        images.passes
+    }
+
+    /// Computes the values of the passes for a camera ray, the light passes only if `with_lights` is set
+    fn aov_sample(ray: &Ray, scene: &Scene, materials: &[Material], with_lights: bool) -> AovSample {
+        let closest = scene.surfaces.surfaces
+            .iter()
+            .enumerate()
+            .filter_map(|(index, surface)| surface.intersect(ray).map(|intersection| (index, intersection)))
+            .min_by(|a, b| a.1.t.total_cmp(&b.1.t));
+
+        let Some((index, mut intersection)) = closest else {
+            return AovSample::miss(scene.background_color);
+        };
+        intersection.apply_normal_maps();
+
+        // Lights only contribute with the part of the surface that is not reflective or transparent
+        let material = &intersection.material;
+        let local_weight = (1.0 - material.reflectance().r - material.transmittance().t).max(0.0);
+        let mut lights = Vec::new();
+        if with_lights {
+            lights = vec![Color::BLACK; aov::light_names(&scene.lights).len()];
+            Self::shade_lights(&intersection, ray, scene, &mut |light, color| lights[light] += color * local_weight);
+        }
+
+        AovSample {
+            depth: Some((intersection.point - ray.origin).length()),
+            normal: intersection.normal,
+            albedo: material.color_at(intersection.uv),
+            object_id: index as u32 + 1,
+            material_id: aov::material_id(materials, material),
+            uv: intersection.uv,
+            lights,
+        }
+    }
+
+    /// Returns if both framebuffers cover the same pixels of the image
+    fn same_pixels(a: &Framebuffer, b: &Framebuffer) -> bool {
+        (a.left, a.top, a.width, a.height) == (b.left, b.top, b.width, b.height)
+    }
+
+    /// Converts the framebuffer into the image that is output. A region that is not
+    /// cropped is placed into a full-size image filled with the background color.
+    /// Denoises the image first if the scene has a denoiser, using the guides from `denoise_guides`.
+    pub fn output_image(scene: &Scene, framebuffer: &Framebuffer, guides: Option<&Guides>) -> RgbImage {
+        let img = match Self::denoised_image(scene, framebuffer, guides) {
+            Some(denoised) => RgbImage::from_fn(framebuffer.width, framebuffer.height, |x, y| {
+                let [r, g, b] = denoised.get_pixel(x, y).0;
+                Self::color_to_rgb(Color::new(r as f64, g as f64, b as f64))
+            }),
+            None => Self::to_image(framebuffer),
+        };
+        Self::place_in_image(scene, framebuffer, img, Self::color_to_rgb(scene.background_color))
+    }
+
+    /// Converts the framebuffer into a floating point image that is output, see `output_image`
+    pub fn output_float_image(scene: &Scene, framebuffer: &Framebuffer, guides: Option<&Guides>) -> Rgb32FImage {
+        let c = scene.background_color;
+        let background = Rgb([c.r as f32, c.g as f32, c.b as f32]);
+        let img = Self::denoised_image(scene, framebuffer, guides).unwrap_or_else(|| framebuffer.to_float_image());
+        Self::place_in_image(scene, framebuffer, img, background)
+    }
+
+    /// Renders the albedo, normal and depth passes that guide the denoiser of the scene.
+    /// Returns None if the scene has no denoiser.
+    pub fn denoise_guides(scene: &Scene) -> Option<Guides> {
+        scene.denoiser.as_ref()?;
+        let settings = Aovs { albedo: true, normal: true, depth: true, ..Aovs::default() };
+        let passes = Self::render_passes(scene, &settings);
+        let guide = |kind: AovKind| passes.iter().find(|pass| pass.kind == kind).map(|pass| pass.image.clone());
+
+        Some(Guides {
+            albedo: guide(AovKind::Albedo)?,
+            normal: guide(AovKind::Normal)?,
+            depth: guide(AovKind::Depth)?,
+        })
+    }
+
+    /// Applies the denoiser of the scene to the framebuffer with the guide passes.
+    /// Returns None if the scene has no denoiser or no guides are given.
+    fn denoised_image(scene: &Scene, framebuffer: &Framebuffer, guides: Option<&Guides>) -> Option<Rgb32FImage> {
+        let denoiser = scene.denoiser.as_ref()?;
+        let guides = guides?;
+
+        Some(denoiser.denoise(
+            &framebuffer.to_float_image(),
+            &framebuffer.to_variance_image(),
+            &guides.albedo,
+            &guides.normal,
+            &guides.depth,
+        ))
+    }
+
+    /// Places an image of the framebuffer's pixels into a full-size image if the region is not cropped
+    fn place_in_image<P: Pixel>(
+        scene: &Scene,
+        framebuffer: &Framebuffer,
+        img: ImageBuffer<P, Vec<P::Subpixel>>,
+        background: P,
+    ) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        match &scene.camera.region {
+            Some(region) if !region.crop => {
+                let resolution = &scene.camera.resolution;
+                let mut full = ImageBuffer::from_pixel(resolution.horizontal, resolution.vertical, background);
+                imageops::replace(&mut full, &img, framebuffer.left as i64, framebuffer.top as i64);
+                full
+            }
+            _ => img,
+        }
+    }
+
+    /// Converts the current estimate of the framebuffer into an 8-bit image
+    pub fn to_image(framebuffer: &Framebuffer) -> RgbImage {
+        RgbImage::from_fn(framebuffer.width, framebuffer.height, |x, y| {
+            Self::color_to_rgb(framebuffer.color(x, y))
+        })
     }
 
     /// Casts a ray into the scene and returns the resulting color.
     /// This function is recursive and will combine local illumination,
-    /// reflection, and refraction.
-    fn trace_ray(ray: &Ray, scene: &Scene, depth: u32) -> Color {
+    /// reflection, and refraction. Rough surfaces scatter the rays with numbers of the sampler.
+    fn trace_ray(ray: &Ray, scene: &Scene, depth: u32, sampler: &mut dyn Sampler) -> Color {
         if depth == 0 {
             return scene.background_color;
         }
+        RAYS_TRACED.with(|rays| rays.set(rays.get() + 1));
+
+        if let Some(mut intersection) = Self::find_closest_intersection(ray, scene) {
+            if let Some(wavelength) = ray.wavelength {
+                intersection.material.disperse(wavelength);
+            }
+            let absorption = Self::absorption_along(&intersection, ray);
+            intersection.apply_normal_maps();
+            if let Some(bsdf) = intersection.material.bsdf(intersection.uv) {
+                return Self::trace_bsdf(&intersection, bsdf.as_ref(), ray, scene, depth, sampler) * absorption;
+            }
 
-        if let Some(intersection) = Self::find_closest_intersection(ray, scene) {
             // Compute the local illumination
             let local = Self::calculate_lighting(&intersection, ray, scene, depth);
 
@@ -50,13 +551,31 @@ impl RenderService {
             let trans = intersection.material.transmittance().t;
             let local_weight = (1.0 - reflect - trans).max(0.0);
 
+            // Averages the rays spread around the ideal direction of rough surfaces,
+            // starting slightly off the surface on the side they leave to avoid self-intersection.
+            let camera_ray = depth == scene.camera.max_bounces.n;
+            let trace_glossy = |direction: Vector, side: Vector, gloss: Gloss, sampler: &mut dyn Sampler| {
+                let origin = intersection.point + side * 1e-4;
+                let rays = gloss.rays(camera_ray);
+                let mut color = Color::BLACK;
+                for _ in 0..rays {
+                    let direction = if gloss.roughness > 0.0 {
+                        gloss.perturb(direction, side, sampler.next_2d())
+                    } else {
+                        direction
+                    };
+                    let secondary = Ray::new(origin, direction, 1e-4, f64::INFINITY)
+                        .with_time(ray.time)
+                        .with_wavelength(ray.wavelength);
+                    color += Self::trace_ray(&secondary, scene, depth - 1, sampler);
+                }
+                color * (1.0 / rays as f64)
+            };
+
             // Compute reflection contribution
             let reflection_color = if reflect > 0.0 {
                 let reflect_dir = Self::reflect(ray.direction, intersection.normal);
-                // Offset the origin slightly along the normal to avoid self-intersection.
-                let reflect_origin = intersection.point + intersection.normal * 1e-4;
-                let reflect_ray = Ray::new(reflect_origin, reflect_dir, 1e-4, f64::INFINITY);
-                Self::trace_ray(&reflect_ray, scene, depth - 1)
+                trace_glossy(reflect_dir, intersection.normal, intersection.material.reflectance().gloss(), sampler)
             } else {
                 Color::new(0.0, 0.0, 0.0)
             };
@@ -64,10 +583,8 @@ impl RenderService {
             // Compute refraction contribution
             let refraction_color = if trans > 0.0 {
                 if let Some(refract_dir) = Self::refract(ray.direction, intersection.normal, 1.0, intersection.material.refraction().iof) {
-                    // Offset in the opposite direction of the normal for the transmitted ray.
-                    let refract_origin = intersection.point - intersection.normal * 1e-4;
-                    let refract_ray = Ray::new(refract_origin, refract_dir, 1e-4, f64::INFINITY);
-                    Self::trace_ray(&refract_ray, scene, depth - 1)
+                    // The transmitted ray leaves on the opposite side of the normal.
+                    trace_glossy(refract_dir, -intersection.normal, intersection.material.transmittance().gloss(), sampler)
                 } else {
                     // Total internal reflection: treat as pure reflection.
                     Color::BLACK
@@ -77,12 +594,49 @@ impl RenderService {
             };
 
             // Combine the contributions.
-            local * local_weight + reflection_color * reflect + refraction_color * trans
+            (local * local_weight + reflection_color * reflect + refraction_color * trans) * absorption
         } else {
             scene.background_color
         }
     }
 
+    /// Returns the part of the light left after traveling from the intersection back to the origin of the ray.
+    /// A ray that hits the surface from behind travels inside of the material, which may absorb light.
+    fn absorption_along(intersection: &Intersection, ray: &Ray) -> Color {
+        match intersection.material.absorption() {
+            Some(absorption) if ray.direction.dot(intersection.normal) > 0.0 => {
+                absorption.transmittance(intersection.t * ray.direction.length())
+            }
+            _ => Color::WHITE,
+        }
+    }
+
+    /// Shades a surface with a physically based material: the lights through its BSDF,
+    /// and the light of the reflected and refracted rays, which are sampled on rough surfaces.
+    fn trace_bsdf(
+        intersection: &Intersection,
+        bsdf: &dyn Bsdf,
+        ray: &Ray,
+        scene: &Scene,
+        depth: u32,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut color = Self::calculate_lighting(intersection, ray, scene, depth);
+        let outgoing = -ray.direction.normalize();
+        let normal = intersection.normal;
+
+        for sample in bsdf.sample_lobes(outgoing, normal, sampler.next_2d()) {
+            // Refracted rays start below the surface
+            let side = if sample.direction.dot(normal) < 0.0 { -1.0 } else { 1.0 };
+            let origin = intersection.point + normal * (side * 1e-4);
+            let scattered = Ray::new(origin, sample.direction, 1e-4, f64::INFINITY)
+                .with_time(ray.time)
+                .with_wavelength(ray.wavelength);
+            color += Self::trace_ray(&scattered, scene, depth - 1, sampler) * sample.weight;
+        }
+        color
+    }
+
     /// Finds the closest intersection of a ray with any surface in the scene.
     fn find_closest_intersection(ray: &Ray, scene: &Scene) -> Option<Intersection> {
         let mut closest = None;
@@ -95,10 +649,23 @@ impl RenderService {
                     }
                 }
                 SurfaceType::Mesh(mesh) => {
-                    for triangle in &mesh.triangles {
-                        if let Some(intersection) = triangle.intersect(ray, &mesh.material()) {
-                            closest = Self::keep_closest(closest, intersection);
-                        }
+                    if let Some(intersection) = mesh.intersect(ray) {
+                        closest = Self::keep_closest(closest, intersection);
+                    }
+                }
+                SurfaceType::Csg(csg) => {
+                    if let Some(intersection) = csg.intersect(ray) {
+                        closest = Self::keep_closest(closest, intersection);
+                    }
+                }
+                SurfaceType::Instance(instance) => {
+                    if let Some(intersection) = instance.intersect(ray) {
+                        closest = Self::keep_closest(closest, intersection);
+                    }
+                }
+                SurfaceType::Group(group) => {
+                    if let Some(intersection) = group.intersect(ray) {
+                        closest = Self::keep_closest(closest, intersection);
                     }
                 }
             }
@@ -109,25 +676,36 @@ impl RenderService {
 
     /// Calculates local illumination (ambient, diffuse, and specular) at an intersection.
     fn calculate_lighting(intersection: &Intersection, ray: &Ray, scene: &Scene, _depth: u32) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        Self::shade_lights(intersection, ray, scene, &mut |_, light| color += light);
+        color
+    }
+
+    /// Passes the contributions of every light to `add`, together with the index of the light.
+    /// The lights are numbered in the order of `aov::light_names`.
+    fn shade_lights(intersection: &Intersection, ray: &Ray, scene: &Scene, add: &mut dyn FnMut(usize, Color)) {
         let material = &intersection.material;
+        let bsdf = material.bsdf(intersection.uv);
+        let bsdf = bsdf.as_deref();
         let normal = intersection.normal;
         let view_dir = -ray.direction.normalize();
         let point = intersection.point;
-
-        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut light = 0;
 
         // Ambient lighting
         for ambient in &scene.lights.ambient_light {
-            color += material.color() * ambient.color * material.phong().ka;
+            add(light, material.ambient_at(intersection.uv) * ambient.color);
+            light += 1;
         }
 
         // Parallel (directional) lights: no distance attenuation
         for parallel in &scene.lights.parallel_light {
             let light_dir = -parallel.direction.normalize();
-            if !Self::is_in_shadow(&point, normal, light_dir, f64::INFINITY, scene) {
-                color += Self::calc_diffuse(material, parallel.color, light_dir, normal, 1.0);
-                color += Self::calc_specular(material, parallel.color, light_dir, normal, view_dir, 1.0);
+            let shadow = Self::shadow_attenuation(&point, normal, light_dir, f64::INFINITY, ray.time, scene);
+            if shadow != Color::BLACK {
+                add(light, Self::reflected_light(material, bsdf, parallel.color, light_dir, normal, view_dir, 1.0) * shadow);
             }
+            light += 1;
         }
 
         // Point lights: include attenuation and intensity boost
@@ -137,41 +715,102 @@ impl RenderService {
             let distance = to_light.length();
             let light_dir = to_light.normalize();
 
-            if !Self::is_in_shadow(&point, normal, light_dir, distance, scene) {
+            let shadow = Self::shadow_attenuation(&point, normal, light_dir, distance, ray.time, scene);
+            if shadow != Color::BLACK {
                 let attenuation = 1.0 / (1.0 + 0.1 * distance + 0.01 * distance * distance);
                 let factor = attenuation * light_intensity;
-                color += Self::calc_diffuse(material, point_light.color, light_dir, normal, factor);
-                color += Self::calc_specular(material, point_light.color, light_dir, normal, view_dir, factor);
+                add(light, Self::reflected_light(material, bsdf, point_light.color, light_dir, normal, view_dir, factor) * shadow);
             }
+            light += 1;
         }
 
-        color
+        // Spot lights: like point lights, limited to their cone
+        for spot_light in &scene.lights.spot_light {
+            let to_light = spot_light.position - point;
+            let distance = to_light.length();
+            let light_dir = to_light.normalize();
+            let cone = spot_light.intensity_towards(-light_dir);
+
+            let shadow = if cone > 0.0 {
+                Self::shadow_attenuation(&point, normal, light_dir, distance, ray.time, scene)
+            } else {
+                Color::BLACK
+            };
+            if shadow != Color::BLACK {
+                let attenuation = 1.0 / (1.0 + 0.1 * distance + 0.01 * distance * distance);
+                let factor = attenuation * light_intensity * cone;
+                add(light, Self::reflected_light(material, bsdf, spot_light.color, light_dir, normal, view_dir, factor) * shadow);
+            }
+            light += 1;
+        }
     }
 
-    /// Returns true if an object is between the point and the light.
+    /// Returns the part of the light from `light_dir` that arrives at the point. Opaque objects in between
+    /// block the light, transmissive ones let it pass tinted by their color and absorption, without refracting it.
     /// For directional lights, pass max_distance = f64::INFINITY.
-    fn is_in_shadow(point: &Point, normal: Vector, light_dir: Vector, max_distance: f64, scene: &Scene) -> bool {
-        let shadow_ray = Ray::new(
+    /// Moving objects are tested where they are at the time of the shaded ray.
+    fn shadow_attenuation(point: &Point, normal: Vector, light_dir: Vector, max_distance: f64, time: f64, scene: &Scene) -> Color {
+        let mut shadow_ray = Ray::new(
             *point + normal * 1e-4,
             light_dir.normalize(),
             1e-4,
             max_distance - 1e-4,
-        );
+        ).with_time(time);
+        let mut attenuation = Color::WHITE;
 
-        scene.surfaces.surfaces.iter().any(|surface| match surface {
-            SurfaceType::Sphere(sphere) => sphere.intersect(&shadow_ray).is_some(),
-            SurfaceType::Mesh(mesh) => mesh.triangles.iter()
-                .any(|triangle| triangle.intersect(&shadow_ray, &mesh.material()).is_some()),
-        })
+        for _ in 0..MAX_SHADOW_SURFACES {
+            RAYS_TRACED.with(|rays| rays.set(rays.get() + 1));
+            let Some(intersection) = Self::find_closest_intersection(&shadow_ray, scene) else {
+                return attenuation;
+            };
+            attenuation = attenuation
+                * intersection.material.straight_transmission(intersection.uv)
+                * Self::absorption_along(&intersection, &shadow_ray);
+            if attenuation.r.max(attenuation.g).max(attenuation.b) <= 0.0 {
+                return Color::BLACK;
+            }
+
+            // Continues behind the surface, up to the light
+            shadow_ray.origin = intersection.point;
+            shadow_ray.t_max -= intersection.t;
+        }
+        Color::BLACK
+    }
+
+    /// Calculates the light reflected towards the viewer, with the BSDF of physically based
+    /// materials or else as the diffuse and specular Phong terms.
+    fn reflected_light(
+        material: &Material,
+        bsdf: Option<&dyn Bsdf>,
+        light_color: Color,
+        light_dir: Vector,
+        normal: Vector,
+        view_dir: Vector,
+        factor: f64,
+    ) -> Color {
+        match bsdf {
+            Some(bsdf) => bsdf.eval(view_dir, light_dir, normal) * light_color * (normal.dot(light_dir).abs() * factor),
+            None => {
+                Self::calc_diffuse(material, light_color, light_dir, normal, view_dir, factor)
+                    + Self::calc_specular(material, light_color, light_dir, normal, view_dir, factor)
+            }
+        }
     }
 
-    /// Calculates the diffuse contribution from a light.
-    fn calc_diffuse(material: &Material, light_color: Color, light_dir: Vector, normal: Vector, factor: f64) -> Color {
-        let diffuse_intensity = normal.dot(light_dir).max(0.0);
+    /// Calculates the diffuse contribution from a light with the diffuse model of the material.
+    fn calc_diffuse(
+        material: &Material,
+        light_color: Color,
+        light_dir: Vector,
+        normal: Vector,
+        view_dir: Vector,
+        factor: f64,
+    ) -> Color {
+        let diffuse_intensity = material.shading().diffuse(normal, light_dir, view_dir);
         material.color() * light_color * diffuse_intensity * material.phong().kd * factor
     }
 
-    /// Calculates the specular contribution from a light.
+    /// Calculates the specular contribution from a light with the specular model of the material.
     fn calc_specular(
         material: &Material,
         light_color: Color,
@@ -180,9 +819,10 @@ impl RenderService {
         view_dir: Vector,
         factor: f64,
     ) -> Color {
-        let reflection_dir = (normal * (2.0 * normal.dot(light_dir)) - light_dir).normalize();
-        let specular_intensity = view_dir.dot(reflection_dir).max(0.0).powf(material.phong().exponent);
-        light_color * specular_intensity * material.phong().ks * factor
+        let phong = material.phong();
+        let specular_intensity = material.shading()
+            .specular(normal, light_dir, view_dir, phong.exponent, material.refraction().iof);
+        light_color * specular_intensity * phong.ks * factor
     }
 
     /// Computes the reflection direction given an incident direction and a normal.
@@ -233,4 +873,64 @@ impl RenderService {
         img.save(&output_path).expect("Failed to save image");
         println!("Image saved to: {}", output_path.display());
     }
+
+    /// Renders and saves the passes selected in the AOV settings of the scene, named after the output file.
+    /// The rendered image is added as a layer to multi-layer files.
+    fn save_aovs(scene: &Scene, framebuffer: &Framebuffer, guides: Option<&Guides>) {
+        let Some(settings) = &scene.aovs else {
+            return;
+        };
+
+        let passes = Self::render_aovs(scene);
+        let name = Path::new(&scene.output_file).file_stem().unwrap_or_default().to_string_lossy();
+        let folder = Path::new("output");
+
+        match settings.format {
+            AovFormat::Png => {
+                for pass in &passes {
+                    let path = folder.join(format!("{}_{}.png", name, pass.kind.name()));
+                    pass.to_rgb().save(&path).expect("Failed to save image");
+                    println!("Pass saved to: {}", path.display());
+                }
+            }
+            AovFormat::Exr => {
+                for pass in passes {
+                    let path = folder.join(format!("{}_{}.exr", name, pass.kind.name()));
+                    ImageExportService::save_exr(&[pass], &path).expect("Failed to save image");
+                    println!("Pass saved to: {}", path.display());
+                }
+            }
+            AovFormat::Multilayer => {
+                let beauty = AovPass { kind: AovKind::Beauty, image: Self::output_float_image(scene, framebuffer, guides) };
+                let layers: Vec<AovPass> = std::iter::once(beauty).chain(passes).collect();
+                let path = folder.join(format!("{}_aovs.exr", name));
+                ImageExportService::save_exr(&layers, &path).expect("Failed to save image");
+                println!("Passes saved to: {}", path.display());
+            }
+        }
+    }
+
+    /// Saves the number of samples taken per pixel if adaptive sampling asks for it
+    fn save_heatmap(scene: &Scene, framebuffer: &Framebuffer) {
+        if !scene.camera.adaptive.as_ref().is_some_and(|adaptive| adaptive.heatmap) {
+            return;
+        }
+
+        let heatmap = Adaptive::heatmap(framebuffer, scene.camera.samples.n);
+        let img = Self::place_in_image(scene, framebuffer, heatmap, Rgb([0, 0, 0]));
+        let name = Path::new(&scene.output_file).file_stem().unwrap_or_default().to_string_lossy();
+        let output_path = Path::new("output").join(format!("{}_heatmap.png", name));
+        img.save(&output_path).expect("Failed to save image");
+        println!("Heatmap saved to: {}", output_path.display());
+    }
+
+    fn save_frame(img: RgbImage, scene: &Scene, frame: u32) {
+        let name = Path::new(&scene.output_file).file_stem().unwrap_or_default();
+        let folder = Path::new("output").join(name);
+        fs::create_dir_all(&folder).expect("Failed to create output folder");
+
+        let output_path = folder.join(format!("frame_{:04}.png", frame));
+        img.save(&output_path).expect("Failed to save image");
+        println!("Frame saved to: {}", output_path.display());
+    }
 }
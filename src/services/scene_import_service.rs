@@ -2,7 +2,6 @@ use rfd::FileDialog;
 use std::fs::File;
 use std::io::{Read};
 use std::path::Path;
-use serde_xml_rs::from_str;
 use crate::models::scene::Scene;
 
 #[derive(Debug)]
@@ -35,7 +34,7 @@ impl SceneImportService {
         let mut content = String::new();
         file.read_to_string(&mut content)?;
 
-        let mut scene: Scene = from_str(&content)?;
+        let mut scene = Scene::from_xml(&content)?;
         scene.load_meshes()?;
 
         // println!("scene loaded {:?}", scene);
@@ -0,0 +1,61 @@
+mod common;
+
+use ray_tracing::models::color::Color;
+use ray_tracing::models::material::{Absorption, Transmittance};
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+/// Glass sphere without refraction in front of the camera, absorbing red and blue light
+fn glass_sphere(radius: f64, absorption: &str) -> String {
+    format!(r#"
+        <sphere radius="{}">
+            <position x="0.0" y="0.0" z="-3.0"/>
+            <material_dielectric ior="1.0">{}</material_dielectric>
+        </sphere>
+    "#, radius, absorption)
+}
+
+/// Renders the sphere and returns the blue of the background seen through its center
+fn blue_through(sphere: &str) -> f32 {
+    let scene = common::create_scene((21, 11), "", "", sphere, "");
+    RenderService::render_to_buffer(&scene, false).color.get_pixel(10, 5).0[2]
+}
+
+#[test]
+fn test_parse_absorption() {
+    let xml_data = r#"
+        <transmittance t="0.8">
+            <absorption density="2.5">
+                <color r="0.5" g="1.0" b="0.5"/>
+            </absorption>
+        </transmittance>
+    "#;
+    let transmittance: Transmittance = from_str(xml_data).expect("Failed to parse Transmittance");
+    let absorption = transmittance.absorption.expect("Absorption should be parsed");
+    assert_eq!(absorption, Absorption { color: Color::new(0.5, 1.0, 0.5), density: 2.5 });
+
+    let clear: Transmittance = from_str(r#"<transmittance t="0.8"/>"#).expect("Failed to parse Transmittance");
+    assert!(clear.absorption.is_none());
+}
+
+#[test]
+fn test_beer_lambert_law() {
+    let absorption = Absorption { color: Color::new(0.5, 1.0, 0.0), density: 2.0 };
+
+    assert_eq!(absorption.transmittance(0.0), Color::WHITE);
+    assert_eq!(absorption.transmittance(0.5), Color::new(0.5, 1.0, 0.0), "The color is reached after 1 / density");
+    let twice = absorption.transmittance(1.0);
+    assert!((twice.r - 0.25).abs() < 1e-12, "Twice the distance squares the transmittance");
+}
+
+#[test]
+fn test_thick_glass_absorbs_more() {
+    let green = r#"<absorption><color r="0.5" g="1.0" b="0.5"/></absorption>"#;
+    let clear = blue_through(&glass_sphere(1.0, ""));
+    let thin = blue_through(&glass_sphere(0.5, green));
+    let thick = blue_through(&glass_sphere(1.0, green));
+
+    assert!((clear - 1.0).abs() < 1e-3, "Clear glass lets the background through, got {}", clear);
+    assert!((thin - 0.5).abs() < 1e-3, "One unit of glass halves the blue, got {}", thin);
+    assert!((thick - 0.25).abs() < 1e-3, "Two units of glass quarter the blue, got {}", thick);
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use ray_tracing::models::adaptive::Adaptive;
+use ray_tracing::models::color::Color;
+use ray_tracing::models::framebuffer::Framebuffer;
+use ray_tracing::models::scene::Scene;
+use ray_tracing::services::render_service::RenderService;
+
+fn create_scene(adaptive: &str) -> Scene {
+    let camera = format!(r#"<samples n="32"/>{}"#, adaptive);
+    common::create_scene((20, 16), &camera, common::AMBIENT_LIGHT, common::SPHERE, "")
+}
+
+#[test]
+fn test_parse_adaptive() {
+    let scene = create_scene(r#"<adaptive threshold="0.01" heatmap="true"/>"#);
+    let adaptive = scene.camera.adaptive.expect("Adaptive sampling is missing");
+
+    assert_eq!(adaptive.threshold, 0.01);
+    assert_eq!(adaptive.min_samples, Adaptive::default().min_samples);
+    assert!(adaptive.heatmap);
+}
+
+#[test]
+fn test_converged_pixels_stop() {
+    let adaptive = Adaptive { threshold: 0.01, min_samples: 4, heatmap: false };
+    let mut framebuffer = Framebuffer::new(2, 1);
+    for i in 0..4 {
+        framebuffer.add_sample(0, 0, Color::new(0.5, 0.5, 0.5));
+        framebuffer.add_sample(1, 0, Color::new(i as f64 % 2.0, 0.0, 0.0));
+    }
+
+    assert!(adaptive.is_converged(&framebuffer, 0, 0), "A pixel without noise needs no more samples");
+    assert!(!adaptive.is_converged(&framebuffer, 1, 0), "A noisy pixel needs more samples");
+    assert!(adaptive.needs_samples(&framebuffer));
+}
+
+#[test]
+fn test_adaptive_render_spends_samples_on_edges() {
+    let scene = create_scene(r#"<adaptive threshold="0.001" min_samples="4"/>"#);
+    let mut progress = Vec::new();
+    let framebuffer = RenderService::render_with_progress(&scene, &mut |p| progress.push(*p), &Default::default())
+        .expect("Render was not cancelled");
+
+    let samples = |x: u32, y: u32| framebuffer.samples[framebuffer.index(x, y)];
+    assert_eq!(samples(0, 0), 4, "Flat background stops after the minimum");
+    assert_eq!(samples(10, 8), 4, "Flat center of the sphere stops after the minimum");
+    assert!(framebuffer.samples.contains(&32), "Edges of the sphere take all samples");
+    assert!(framebuffer.samples.iter().all(|&n| n <= 32));
+    assert_eq!(framebuffer.passes(), 32);
+
+    // Pixels that stopped early keep the color they had
+    let uniform = RenderService::render_image(&create_scene(""));
+    let adaptive = RenderService::render_image(&scene);
+    assert_eq!(adaptive.get_pixel(0, 0), uniform.get_pixel(0, 0));
+}
+
+#[test]
+fn test_heatmap() {
+    let mut framebuffer = Framebuffer::new(2, 1);
+    framebuffer.add_sample(1, 0, Color::BLACK);
+    framebuffer.add_sample(1, 0, Color::BLACK);
+
+    let heatmap = Adaptive::heatmap(&framebuffer, 2);
+    assert_eq!(heatmap.get_pixel(0, 0).0, [0, 0, 255]);
+    assert_eq!(heatmap.get_pixel(1, 0).0, [255, 0, 0]);
+}
@@ -0,0 +1,181 @@
+use ray_tracing::models::animation::{interpolate, Interpolation, ScalarKey, Track, VectorKey};
+use ray_tracing::models::color::Color;
+use ray_tracing::models::point::Point;
+use ray_tracing::models::ray::Ray;
+use ray_tracing::models::scene::Scene;
+use ray_tracing::models::surface::{Surface, SurfaceType};
+use ray_tracing::models::vector::Vector;
+use serde_xml_rs::from_str;
+
+const ANIMATED_SCENE: &str = r#"
+    <scene output_file="animation.png">
+        <background_color r="0.0" g="0.0" b="0.0"/>
+        <camera>
+            <position x="0.0" y="0.0" z="1.0"/>
+            <lookat x="0.0" y="0.0" z="-2.5"/>
+            <up x="0.0" y="1.0" z="0.0"/>
+            <horizontal_fov angle="45"/>
+            <resolution horizontal="64" vertical="64"/>
+            <max_bounces n="8"/>
+        </camera>
+        <lights>
+            <ambient_light>
+                <color r="1.0" g="1.0" b="1.0"/>
+            </ambient_light>
+            <point_light>
+                <color r="1.0" g="1.0" b="1.0"/>
+                <position x="0.0" y="3.0" z="0.0"/>
+            </point_light>
+        </lights>
+        <surfaces>
+            <sphere radius="1.0">
+                <position x="0.0" y="0.0" z="-3.0"/>
+                <material_solid>
+                    <color r="0.95" g="0.63" b="0.01"/>
+                    <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+                    <reflectance r="0.0"/>
+                    <transmittance t="0.0"/>
+                    <refraction iof="2.3"/>
+                </material_solid>
+            </sphere>
+        </surfaces>
+        <animation frames="5">
+            <camera_position interpolation="ease">
+                <key frame="1" x="0.0" y="0.0" z="1.0"/>
+                <key frame="5" x="0.0" y="0.0" z="5.0"/>
+            </camera_position>
+            <light_color light="point" index="0">
+                <key frame="1" r="1.0" g="1.0" b="1.0"/>
+                <key frame="5" r="1.0" g="0.0" b="0.0"/>
+            </light_color>
+            <surface_translate surface="0">
+                <key frame="1" x="0.0" y="0.0" z="0.0"/>
+                <key frame="5" x="4.0" y="0.0" z="0.0"/>
+            </surface_translate>
+            <material_parameter surface="0" name="kd">
+                <key frame="1" value="0.9"/>
+                <key frame="5" value="0.1"/>
+            </material_parameter>
+        </animation>
+    </scene>
+"#;
+
+fn key(frame: f64, x: f64) -> VectorKey {
+    VectorKey { frame, x, y: 0.0, z: 0.0 }
+}
+
+#[test]
+fn test_parse_animation() {
+    let scene: Scene = from_str(ANIMATED_SCENE).expect("Failed to parse Scene");
+    let animation = scene.animation.expect("Animation should be parsed");
+
+    assert_eq!(animation.frames, 5);
+    assert_eq!(animation.tracks.len(), 4);
+
+    let Track::CameraPosition(track) = &animation.tracks[0] else {
+        panic!("Expected a camera position track");
+    };
+    assert_eq!(track.interpolation, Interpolation::Ease);
+    assert_eq!(track.keys.len(), 2);
+
+    let Track::MaterialParameter(track) = &animation.tracks[3] else {
+        panic!("Expected a material parameter track");
+    };
+    assert_eq!(track.interpolation, Interpolation::Linear);
+    assert_eq!(track.keys[1], ScalarKey { frame: 5.0, value: 0.1 });
+}
+
+#[test]
+fn test_interpolation_modes() {
+    let keys = vec![key(10.0, 2.0), key(0.0, 0.0), key(20.0, 0.0)];
+
+    // Outside of the keyframes the value is held
+    assert_eq!(interpolate(&keys, Interpolation::Linear, -5.0), Some([0.0, 0.0, 0.0]));
+    assert_eq!(interpolate(&keys, Interpolation::Linear, 25.0), Some([0.0, 0.0, 0.0]));
+
+    // Every mode passes through the keyframes
+    for mode in [Interpolation::Linear, Interpolation::Cubic, Interpolation::Ease] {
+        assert_eq!(interpolate(&keys, mode, 10.0), Some([2.0, 0.0, 0.0]));
+    }
+
+    assert_eq!(interpolate(&keys, Interpolation::Linear, 2.5), Some([0.5, 0.0, 0.0]));
+    // Ease starts slower than linear
+    let ease = interpolate(&keys, Interpolation::Ease, 2.5).unwrap()[0];
+    assert!((ease - 0.3125).abs() < 1e-9, "Incorrect eased value");
+    // The spline bends over the middle keyframe instead of turning sharply
+    let cubic = interpolate(&keys, Interpolation::Cubic, 7.5).unwrap()[0];
+    assert!(cubic > 1.5, "Cubic interpolation should be above the linear one");
+
+    let empty: Vec<VectorKey> = Vec::new();
+    assert_eq!(interpolate(&empty, Interpolation::Cubic, 1.0), None);
+}
+
+#[test]
+fn test_set_frame() {
+    let mut scene: Scene = from_str(ANIMATED_SCENE).expect("Failed to parse Scene");
+    scene.load_meshes().expect("Failed to load meshes");
+
+    // The animated sphere is wrapped into a group so it can be moved
+    assert!(matches!(scene.surfaces.surfaces[0], SurfaceType::Group(_)));
+
+    scene.set_frame(3);
+    assert_eq!(scene.camera.position, Point::new(0.0, 0.0, 3.0));
+    assert_eq!(scene.lights.point_light[0].color, Color::new(1.0, 0.5, 0.5));
+
+    let ray = Ray::new(Point::new(2.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0), 0.01, f64::INFINITY);
+    let hit = scene.surfaces.surfaces[0].intersect(&ray).expect("Sphere should have moved into the ray");
+    assert!((hit.t - 2.0).abs() < 1e-6, "Incorrect intersection distance");
+    assert!((hit.material.phong().kd - 0.5).abs() < 1e-9, "Material parameter not animated");
+
+    // Frames are absolute, going back restores the start
+    scene.set_frame(1);
+    assert!(scene.surfaces.surfaces[0].intersect(&ray).is_none(), "Sphere should be back at the start");
+}
+
+#[test]
+fn test_animation_with_unknown_surface() {
+    let xml_data = ANIMATED_SCENE.replace(r#"surface_translate surface="0""#, r#"surface_translate surface="3""#);
+    let mut scene: Scene = from_str(&xml_data).expect("Failed to parse Scene");
+
+    assert!(scene.load_meshes().is_err(), "Animating a missing surface should fail");
+}
+
+#[test]
+fn test_animation_with_unanimatable_material() {
+    let color_track = r#"
+        <material_color surface="0">
+            <key frame="1" r="1.0" g="0.0" b="0.0"/>
+        </material_color>
+    "#;
+    let conductor = r#"<material_conductor><eta r="0.2" g="0.4" b="1.4"/><k r="3.9" g="2.4" b="1.9"/></material_conductor>"#;
+    let start = ANIMATED_SCENE.find("<material_solid>").unwrap();
+    let end = ANIMATED_SCENE.find("</material_solid>").unwrap() + "</material_solid>".len();
+    let metal = format!("{}{}{}", &ANIMATED_SCENE[..start], conductor, &ANIMATED_SCENE[end..]);
+
+    // Conductors have neither a Phong parameter nor a color to animate
+    let mut scene: Scene = from_str(&metal).expect("Failed to parse Scene");
+    assert!(scene.load_meshes().is_err(), "Animating a parameter of a conductor should fail");
+
+    let xml_data = metal.replace(r#"<material_parameter surface="0" name="kd">
+                <key frame="1" value="0.9"/>
+                <key frame="5" value="0.1"/>
+            </material_parameter>"#, color_track);
+    let mut scene: Scene = from_str(&xml_data).expect("Failed to parse Scene");
+    assert!(scene.load_meshes().is_err(), "Animating the color of a conductor should fail");
+
+    // Without an override the material of an instance belongs to the shared geometry
+    let start = ANIMATED_SCENE.find("<surfaces>").unwrap();
+    let end = ANIMATED_SCENE.find("</surfaces>").unwrap() + "</surfaces>".len();
+    let sphere = &ANIMATED_SCENE[start + "<surfaces>".len()..end - "</surfaces>".len()];
+    let instanced = format!(
+        r#"{}<definitions><geometry name="ball">{}</geometry></definitions><surfaces><instance geometry="ball"/></surfaces>{}"#,
+        &ANIMATED_SCENE[..start], sphere, &ANIMATED_SCENE[end..],
+    );
+    let mut scene: Scene = from_str(&instanced).expect("Failed to parse Scene");
+    assert!(scene.load_meshes().is_err(), "Animating an instance without a material should fail");
+
+    // The color of a solid material can be animated
+    let xml_data = ANIMATED_SCENE.replace("</animation>", &format!("{}</animation>", color_track));
+    let mut scene: Scene = from_str(&xml_data).expect("Failed to parse Scene");
+    scene.load_meshes().expect("Animating the color of a solid material should work");
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use std::path::Path;
+use ray_tracing::models::aov::{AovFormat, AovKind, AovPass};
+use ray_tracing::models::scene::Scene;
+use ray_tracing::services::image_export_service::ImageExportService;
+use ray_tracing::services::render_service::RenderService;
+
+const LIGHTS: &str = r#"
+    <ambient_light>
+        <color r="1.0" g="1.0" b="1.0"/>
+    </ambient_light>
+    <point_light>
+        <color r="1.0" g="1.0" b="1.0"/>
+        <position x="0.0" y="3.0" z="0.0"/>
+    </point_light>
+"#;
+
+const AOVS: &str = r#"<aovs format="multilayer" depth="true" normal="true" albedo="true" object_id="true" material_id="true" uv="true" lights="true"/>"#;
+
+fn create_scene(sphere_positions: &[f64]) -> Scene {
+    let spheres: String = sphere_positions
+        .iter()
+        .map(|x| common::SPHERE.replace(r#"<position x="0.0""#, &format!(r#"<position x="{}""#, x)))
+        .collect();
+    common::create_scene((21, 11), "", LIGHTS, &spheres, AOVS)
+}
+
+fn find<'a>(passes: &'a [AovPass], name: &str) -> &'a AovPass {
+    passes.iter().find(|pass| pass.kind.name() == name).expect("Pass is missing")
+}
+
+#[test]
+fn test_parse_aovs() {
+    let scene = create_scene(&[0.0]);
+    let aovs = scene.aovs.as_ref().expect("AOV settings are missing");
+
+    assert_eq!(aovs.format, AovFormat::Multilayer);
+    assert!(aovs.depth && aovs.normal && aovs.albedo && aovs.object_id && aovs.material_id && aovs.uv && aovs.lights);
+
+    let passes = RenderService::render_aovs(&scene);
+    let names: Vec<&str> = passes.iter().map(|pass| pass.kind.name()).collect();
+    assert_eq!(names, ["depth", "normal", "albedo", "object_id", "material_id", "uv", "ambient_light_0", "point_light_0"]);
+}
+
+#[test]
+fn test_pass_values() {
+    let scene = create_scene(&[0.0]);
+    let passes = RenderService::render_aovs(&scene);
+
+    // The camera looks at the front of the sphere from a distance of 3
+    let depth = find(&passes, "depth").image.get_pixel(10, 5)[0];
+    assert!((depth - 3.0).abs() < 1e-4, "Unexpected depth {}", depth);
+    assert_eq!(find(&passes, "depth").image.get_pixel(0, 0)[0], 0.0);
+
+    let normal = find(&passes, "normal").image.get_pixel(10, 5).0;
+    assert!((normal[2] - 1.0).abs() < 1e-4, "Unexpected normal {:?}", normal);
+
+    let albedo = &find(&passes, "albedo").image;
+    assert_eq!(albedo.get_pixel(10, 5).0, [0.95, 0.63, 0.01]);
+    assert_eq!(albedo.get_pixel(0, 0).0, [0.0, 0.0, 1.0], "The background has its color as albedo");
+
+    assert_eq!(find(&passes, "object_id").image.get_pixel(10, 5)[0], 1.0);
+    assert_eq!(find(&passes, "object_id").image.get_pixel(0, 0)[0], 0.0);
+}
+
+#[test]
+fn test_light_passes_add_up_to_image() {
+    let scene = create_scene(&[0.0]);
+    let passes = RenderService::render_aovs(&scene);
+    let image = RenderService::render_to_buffer(&scene, false).color;
+
+    let ambient = find(&passes, "ambient_light_0").image.get_pixel(10, 5).0;
+    let point = find(&passes, "point_light_0").image.get_pixel(10, 5).0;
+    let beauty = image.get_pixel(10, 5).0;
+    for c in 0..3 {
+        assert!((ambient[c] + point[c] - beauty[c]).abs() < 1e-5, "Lights do not add up to the image");
+    }
+}
+
+#[test]
+fn test_ids() {
+    // Both spheres share one material but are separate objects
+    let scene = create_scene(&[-1.2, 1.2]);
+    let passes = RenderService::render_aovs(&scene);
+
+    let object_ids = &find(&passes, "object_id").image;
+    let material_ids = &find(&passes, "material_id").image;
+    assert_eq!(object_ids.get_pixel(4, 5)[0], 1.0);
+    assert_eq!(object_ids.get_pixel(16, 5)[0], 2.0);
+    assert_eq!(material_ids.get_pixel(4, 5)[0], 1.0);
+    assert_eq!(material_ids.get_pixel(16, 5)[0], 1.0);
+}
+
+#[test]
+fn test_save_multilayer_exr() {
+    let scene = create_scene(&[0.0]);
+    let mut layers = vec![AovPass { kind: AovKind::Beauty, image: RenderService::render_to_buffer(&scene, false).color }];
+    layers.extend(RenderService::render_aovs(&scene));
+
+    let path = std::env::temp_dir().join("ray_tracing_aov_test.exr");
+    ImageExportService::save_exr(&layers, &path).expect("Failed to save EXR");
+
+    let meta = exr::meta::MetaData::read_from_file(&path, false).expect("Failed to read EXR");
+    let names: Vec<String> = meta.headers.iter()
+        .map(|header| header.own_attributes.layer_name.as_ref().map(|name| name.to_string()).unwrap_or_default())
+        .collect();
+    assert_eq!(names[..3], ["beauty", "depth", "normal"]);
+    assert_eq!(meta.headers[1].channels.list[0].name.to_string(), "Z");
+    std::fs::remove_file(Path::new(&path)).ok();
+}
+
+#[test]
+fn test_spot_light_pass() {
+    let spot_light = r#"
+        <spot_light>
+            <color r="1.0" g="1.0" b="1.0"/>
+            <position x="0.0" y="0.0" z="1.0"/>
+            <direction x="0.0" y="0.0" z="-1.0"/>
+            <falloff alpha1="5" alpha2="10"/>
+        </spot_light>
+    "#;
+    let spheres = format!("{}{}", common::SPHERE, common::SPHERE.replace(r#"x="0.0""#, r#"x="1.5""#));
+    let scene = common::create_scene((21, 11), "", spot_light, &spheres, AOVS);
+    let passes = RenderService::render_aovs(&scene);
+
+    let spot = &find(&passes, "spot_light_0").image;
+    assert!(spot.get_pixel(10, 5)[0] > 0.0, "The spot light shines on the sphere in its cone");
+    assert_eq!(spot.get_pixel(17, 5)[0], 0.0, "The sphere outside of the cone stays dark");
+}
+
+#[test]
+fn test_textured_albedo() {
+    let plane = r#"
+        <mesh name="plane_small.obj">
+            <material_textured>
+                <texture name="rainbow.png"/>
+                <phong ka="0.3" kd="0.9" ks="1.0" exponent="20"/>
+                <reflectance r="0.0"/>
+                <transmittance t="0.0"/>
+                <refraction iof="0.0"/>
+            </material_textured>
+        </mesh>
+    "#;
+    let mut scene = common::create_scene((21, 11), "", LIGHTS, plane, AOVS);
+    scene.load_meshes().expect("Failed to load scene");
+    let passes = RenderService::render_aovs(&scene);
+
+    let albedo = &find(&passes, "albedo").image;
+    assert_ne!(albedo.get_pixel(10, 5).0, [0.0; 3], "The albedo is sampled from the texture");
+    assert_ne!(albedo.get_pixel(10, 5), albedo.get_pixel(12, 5));
+}
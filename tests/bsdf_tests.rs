@@ -0,0 +1,106 @@
+mod common;
+
+use ray_tracing::models::bsdf::{self, Bsdf, Frame};
+use ray_tracing::models::color::Color;
+use ray_tracing::models::material::{Material, MaterialConductor, MaterialDielectric};
+use ray_tracing::models::surface::SurfaceType;
+use ray_tracing::models::vector::Vector;
+use ray_tracing::services::render_service::RenderService;
+
+const GOLD: &str = r#"
+    <material_conductor roughness="0.3">
+        <eta r="0.143" g="0.374" b="1.442"/>
+        <k r="3.983" g="2.385" b="1.603"/>
+    </material_conductor>
+"#;
+
+fn gold(roughness: f64) -> MaterialConductor {
+    MaterialConductor {
+        eta: Color::new(0.143, 0.374, 1.442),
+        k: Color::new(3.983, 2.385, 1.603),
+        roughness,
+    }
+}
+
+fn sphere_with(material: &str) -> String {
+    format!(r#"<sphere radius="1.0"><position x="0.0" y="0.0" z="-3.0"/>{}</sphere>"#, material)
+}
+
+#[test]
+fn test_parse_microfacet_materials() {
+    let xml = format!("{}{}", sphere_with(GOLD), sphere_with(r#"<material_dielectric ior="1.5"/>"#));
+    let mut scene = common::create_scene((8, 6), "", common::AMBIENT_LIGHT, &xml, "");
+    scene.load_meshes().expect("Failed to load scene");
+
+    let SurfaceType::Sphere(metal) = &scene.surfaces.surfaces[0] else { panic!("Expected a sphere") };
+    assert_eq!(metal.material(), Material::Conductor(gold(0.3)));
+    let SurfaceType::Sphere(glass) = &scene.surfaces.surfaces[1] else { panic!("Expected a sphere") };
+    assert_eq!(glass.material(), Material::Dielectric(MaterialDielectric { ior: 1.5, roughness: 0.0, absorption: None, cauchy: None, sellmeier: None }));
+}
+
+#[test]
+fn test_fresnel() {
+    assert!((bsdf::fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-12);
+    assert_eq!(bsdf::fresnel_dielectric(0.1, 1.0 / 1.5), 1.0, "Total internal reflection");
+    // A conductor without absorption reflects like a dielectric
+    for cos in [0.1, 0.5, 0.9] {
+        assert!((bsdf::fresnel_conductor(cos, 1.5, 0.0) - bsdf::fresnel_dielectric(cos, 1.5)).abs() < 1e-9);
+    }
+    assert!(gold(0.0).reflectance(1.0).r > 0.9, "Gold reflects most red light");
+}
+
+#[test]
+fn test_frame_is_orthonormal() {
+    for normal in [Vector::new(0.0, 0.0, 1.0), Vector::new(0.0, 0.0, -1.0), Vector::new(0.3, -0.5, 0.2)] {
+        let frame = Frame::from_normal(normal);
+        let v = Vector::new(0.2, -0.7, 0.4);
+        let back = frame.to_world(frame.to_local(v));
+        assert!((back - v).length() < 1e-12);
+        assert!(frame.tangent.dot(frame.normal).abs() < 1e-12 && frame.bitangent.dot(frame.normal).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_rough_conductor_sampling() {
+    let normal = Vector::new(0.0, 1.0, 0.0);
+    let outgoing = Vector::new(0.6, 0.8, 0.0);
+    let albedo = common::check_sampling(&gold(0.5), outgoing, normal);
+
+    // A rough metal loses a little light to masking but never creates any
+    let fresnel = gold(0.5).reflectance(0.8);
+    assert!(albedo.r <= fresnel.r * 1.05 && albedo.r > fresnel.r * 0.8, "Albedo {:?} for Fresnel {:?}", albedo, fresnel);
+}
+
+#[test]
+fn test_rough_dielectric_sampling() {
+    let normal = Vector::new(0.0, 0.0, 1.0);
+    let glass = MaterialDielectric { ior: 1.5, roughness: 0.4, absorption: None, cauchy: None, sellmeier: None };
+    common::check_sampling(&glass, Vector::new(0.0, 0.6, 0.8), normal);
+    // From inside of the glass
+    common::check_sampling(&glass, Vector::new(0.0, 0.3, -0.95).normalize(), normal);
+}
+
+#[test]
+fn test_smooth_dielectric_splits_light() {
+    let glass = MaterialDielectric { ior: 1.5, roughness: 0.0, absorption: None, cauchy: None, sellmeier: None };
+    let lobes = glass.sample_lobes(Vector::new(0.0, 0.0, 1.0), Vector::new(0.0, 0.0, 1.0), (0.5, 0.5));
+
+    assert_eq!(lobes.len(), 2);
+    assert!(lobes.iter().all(|lobe| lobe.specular));
+    assert!((lobes[0].weight.r - 0.04).abs() < 1e-12, "Reflection at normal incidence");
+    assert!((lobes[1].direction.z + 1.0).abs() < 1e-12, "Straight through the glass");
+    assert!((lobes[1].weight.r - 0.96 / 2.25).abs() < 1e-12);
+}
+
+#[test]
+fn test_render_smooth_metal_reflects_background() {
+    let metal = r#"<material_conductor><eta r="0.2" g="0.2" b="0.2"/><k r="3.0" g="3.0" b="3.0"/></material_conductor>"#;
+    let scene = common::create_scene((21, 11), "", "", &sphere_with(metal), "");
+    let image = RenderService::render_to_buffer(&scene, false).color;
+
+    // Facing the camera the sphere reflects the blue background behind the camera
+    let reflectance = bsdf::fresnel_conductor(1.0, 0.2, 3.0) as f32;
+    let center = image.get_pixel(10, 5).0;
+    assert_eq!(center[0], 0.0);
+    assert!((center[2] - reflectance).abs() < 1e-3, "Reflected {:?}, expected {}", center, reflectance);
+}
@@ -1,7 +1,7 @@
 use ray_tracing::models::camera::Camera;
 use ray_tracing::models::point::Point;
 use ray_tracing::models::vector::Vector;
-use ray_tracing::models::camera::{Fov, Resolution, MaxBounces};
+use ray_tracing::models::camera::{Fov, Resolution, MaxBounces, Samples, Bokeh, ProjectionType, FisheyeMapping};
 use serde_xml_rs::from_str;
 
 #[test]
@@ -21,11 +21,164 @@ fn test_parse_camera() {
 
     // Assertions
     assert_eq!(camera.position, Point { x: 1.0, y: -2.0E-10, z: -3.0 });
-    assert_eq!(camera.look_at, Vector { x: 1.0, y: 2.0, z: 3.0 });
+    assert_eq!(camera.look_at, Point { x: 1.0, y: 2.0, z: 3.0 });
     assert_eq!(camera.up, Vector { x: 0.0, y: 1.0, z: 0.0 });
     assert_eq!(camera.horizontal_fov, Fov { angle: 90.0 });
     assert_eq!(camera.resolution, Resolution { horizontal: 1920, vertical: 1080 });
     assert_eq!(camera.max_bounces, MaxBounces { n: 100 });
+    assert_eq!(camera.samples, Samples { n: 1 });
+    assert!(camera.lens.is_none());
+    assert_eq!(camera.projection.kind, ProjectionType::Perspective);
+}
+
+fn parse_lens_camera(lens: &str) -> Camera {
+    let xml_data = format!(r#"
+        <camera>
+            <position x="0.0" y="0.0" z="0.0"/>
+            <lookat x="0.0" y="0.0" z="-1.0"/>
+            <up x="0.0" y="1.0" z="0.0"/>
+            <horizontal_fov angle="45"/>
+            <resolution horizontal="64" vertical="64"/>
+            <max_bounces n="8"/>
+            <samples n="16"/>
+            {}
+        </camera>
+    "#, lens);
+
+    from_str(&xml_data).expect("Failed to parse Camera")
+}
+
+#[test]
+fn test_parse_lens() {
+    let camera = parse_lens_camera(r#"<lens aperture="0.2" focus_distance="5.0" bokeh="polygon" blades="5"/>"#);
+    let lens = camera.lens.as_ref().expect("Missing lens");
+
+    assert_eq!(camera.samples, Samples { n: 16 });
+    assert_eq!(lens.aperture, 0.2);
+    assert_eq!(lens.focus_distance, Some(5.0));
+    assert_eq!(lens.bokeh, Bokeh::Polygon);
+    assert_eq!(lens.blades, 5);
+
+    let camera = parse_lens_camera(r#"<lens aperture="0.1"><focus_point x="1.0" y="0.0" z="-4.0"/></lens>"#);
+    let lens = camera.lens.as_ref().expect("Missing lens");
+    assert_eq!(lens.focus_point, Some(Point::new(1.0, 0.0, -4.0)));
+    assert_eq!(lens.bokeh, Bokeh::Disk);
+}
+
+#[test]
+fn test_thin_lens_rays_meet_on_focus_plane() {
+    let camera = parse_lens_camera(r#"<lens aperture="0.5" focus_distance="4.0"/>"#);
+    let pinhole = camera.generate_ray(10, 20).expect("Perspective camera always generates a ray");
+
+    // Focus plane is at z = -4, the pinhole ray crosses it at t = 4 / cos(angle)
+    let t_focus = 4.0 / -pinhole.direction.z;
+    let expected = pinhole.at(t_focus);
+
+    for lens_sample in [(0.0, 0.0), (0.9, 0.1), (0.3, 0.7), (0.5, 0.5)] {
+        let ray = camera.generate_ray_sample(10, 20, (0.5, 0.5), lens_sample).expect("Missing ray");
+        let t = (-4.0 - ray.origin.z) / ray.direction.z;
+        assert!((ray.at(t) - expected).length() < 1e-9, "Lens ray misses the point in focus");
+        assert!(ray.origin.z.abs() < 1e-12, "Lens ray must start on the lens plane");
+        assert!((ray.origin - Point::new(0.0, 0.0, 0.0)).length() <= 0.5 + 1e-9, "Lens ray starts outside the aperture");
+    }
+}
+
+#[test]
+fn test_polygon_bokeh_stays_inside_aperture() {
+    let camera = parse_lens_camera(r#"<lens aperture="1.0" bokeh="polygon" blades="6"/>"#);
+    let lens = camera.lens.as_ref().expect("Missing lens");
+
+    // Inner radius of a hexagon inscribed in the unit circle
+    let inner_radius = (std::f64::consts::PI / 6.0).cos();
+
+    for i in 0..32 {
+        for j in 0..32 {
+            let (x, y) = lens.sample_aperture((i as f64 / 32.0, j as f64 / 32.0));
+            let r = (x * x + y * y).sqrt();
+            assert!(r <= 1.0 + 1e-9, "Sample outside the circumscribed circle");
+
+            // Project onto the normal of the edge of the sector the sample lies in
+            let angle = y.atan2(x).rem_euclid(std::f64::consts::PI / 3.0) - std::f64::consts::PI / 6.0;
+            assert!(r * angle.cos() <= inner_radius + 1e-9, "Sample outside the hexagon");
+        }
+    }
+}
+
+fn parse_projection_camera(projection: &str, fov: f64) -> Camera {
+    let xml_data = format!(r#"
+        <camera>
+            <position x="0.0" y="0.0" z="0.0"/>
+            <lookat x="0.0" y="0.0" z="-1.0"/>
+            <up x="0.0" y="1.0" z="0.0"/>
+            <horizontal_fov angle="{}"/>
+            <resolution horizontal="200" vertical="100"/>
+            <max_bounces n="8"/>
+            {}
+        </camera>
+    "#, fov, projection);
+
+    from_str(&xml_data).expect("Failed to parse Camera")
+}
+
+fn assert_direction(actual: Vector, expected: Vector) {
+    let diff = actual - expected.normalize();
+    assert!(diff.length() < 1e-6, "Expected direction {:?}, got {:?}", expected, actual);
+}
+
+#[test]
+fn test_orthographic_rays_are_parallel() {
+    let camera = parse_projection_camera(r#"<projection type="orthographic" view_width="4.0"/>"#, 45.0);
+    assert_eq!(camera.projection.kind, ProjectionType::Orthographic);
+    assert_eq!(camera.projection.view_width, Some(4.0));
+
+    // Pixel corners of the image span 4 x 2 world units
+    let top_left = camera.generate_ray_sample(0, 0, (0.0, 0.0), (0.5, 0.5)).expect("Missing ray");
+    let bottom_right = camera.generate_ray_sample(199, 99, (1.0, 1.0), (0.5, 0.5)).expect("Missing ray");
+
+    assert_direction(top_left.direction, Vector::new(0.0, 0.0, -1.0));
+    assert_direction(bottom_right.direction, Vector::new(0.0, 0.0, -1.0));
+    assert!((top_left.origin - Point::new(-2.0, 1.0, 0.0)).length() < 1e-9, "Incorrect origin {:?}", top_left.origin);
+    assert!((bottom_right.origin - Point::new(2.0, -1.0, 0.0)).length() < 1e-9, "Incorrect origin {:?}", bottom_right.origin);
+}
+
+#[test]
+fn test_equirectangular_covers_full_sphere() {
+    let camera = parse_projection_camera(r#"<projection type="equirectangular"/>"#, 45.0);
+
+    let forward = camera.generate_ray_sample(100, 50, (0.0, 0.0), (0.5, 0.5)).expect("Missing ray");
+    let right = camera.generate_ray_sample(150, 50, (0.0, 0.0), (0.5, 0.5)).expect("Missing ray");
+    let backward = camera.generate_ray_sample(0, 50, (0.0, 0.0), (0.5, 0.5)).expect("Missing ray");
+    let up = camera.generate_ray_sample(100, 0, (0.0, 0.0), (0.5, 0.5)).expect("Missing ray");
+
+    assert_direction(forward.direction, Vector::new(0.0, 0.0, -1.0));
+    assert_direction(right.direction, Vector::new(1.0, 0.0, 0.0));
+    assert_direction(backward.direction, Vector::new(0.0, 0.0, 1.0));
+    assert_direction(up.direction, Vector::new(0.0, 1.0, 0.0));
+}
+
+#[test]
+fn test_fisheye_mappings() {
+    // 90 degrees at the border gives a 180 degree fisheye
+    let camera = parse_projection_camera(r#"<projection type="fisheye"/>"#, 90.0);
+    assert_eq!(camera.projection.mapping, FisheyeMapping::Equidistant);
+
+    let center = camera.generate_ray_sample(100, 50, (0.0, 0.0), (0.5, 0.5)).expect("Missing ray");
+    let edge = camera.generate_ray_sample(199, 50, (1.0, 0.0), (0.5, 0.5)).expect("Missing ray");
+    let halfway = camera.generate_ray_sample(150, 50, (0.0, 0.0), (0.5, 0.5)).expect("Missing ray");
+
+    assert_direction(center.direction, Vector::new(0.0, 0.0, -1.0));
+    assert_direction(edge.direction, Vector::new(1.0, 0.0, 0.0));
+    // Equidistant: half the radius is half the angle
+    assert_direction(halfway.direction, Vector::new(1.0, 0.0, -1.0));
+
+    // Corners lie outside the image circle
+    assert!(camera.generate_ray(0, 0).is_none(), "Corner pixel should not generate a ray");
+
+    let camera = parse_projection_camera(r#"<projection type="fisheye" mapping="equisolid"/>"#, 90.0);
+    let halfway = camera.generate_ray_sample(150, 50, (0.0, 0.0), (0.5, 0.5)).expect("Missing ray");
+    // Equisolid: r = 2 f sin(theta / 2) with r = 1 at 90 degrees
+    let theta = 2.0 * (0.5 * std::f64::consts::FRAC_PI_4.sin()).asin();
+    assert_direction(halfway.direction, Vector::new(theta.sin(), 0.0, -theta.cos()));
 }
 
 // use ray_tracing::models::ray::Ray;
@@ -0,0 +1,150 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use ray_tracing::models::checkpoint::{Checkpoint, DEFAULT_SECONDS};
+use ray_tracing::models::framebuffer::Framebuffer;
+use ray_tracing::models::scene::Scene;
+use ray_tracing::services::render_service::RenderService;
+
+const SCENE: &str = r#"
+    <scene output_file="checkpoint.png">
+        <background_color r="0.1" g="0.2" b="0.3"/>
+        <camera>
+            <position x="0.0" y="0.0" z="1.0"/>
+            <lookat x="0.0" y="0.0" z="-2.5"/>
+            <up x="0.0" y="1.0" z="0.0"/>
+            <horizontal_fov angle="45"/>
+            <resolution horizontal="16" vertical="12"/>
+            <max_bounces n="8"/>
+            <samples n="6"/>
+            <lens aperture="0.2" focus_distance="3.0"/>
+        </camera>
+        <lights>
+            <ambient_light>
+                <color r="1.0" g="1.0" b="1.0"/>
+            </ambient_light>
+            <point_light>
+                <color r="1.0" g="1.0" b="1.0"/>
+                <position x="0.0" y="3.0" z="0.0"/>
+            </point_light>
+        </lights>
+        <surfaces>
+            <sphere radius="1.0">
+                <position x="0.0" y="0.0" z="-3.0"/>
+                <material_solid>
+                    <color r="0.95" g="0.63" b="0.01"/>
+                    <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+                    <reflectance r="0.0"/>
+                    <transmittance t="0.0"/>
+                    <refraction iof="2.3"/>
+                </material_solid>
+            </sphere>
+        </surfaces>
+        <checkpoint passes="2"/>
+    </scene>
+"#;
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ray_tracing_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn test_parse_checkpoint() {
+    let scene = Scene::from_xml(SCENE).expect("Failed to parse Scene");
+    let checkpoint = scene.checkpoint.expect("Checkpoint settings should be parsed");
+
+    assert_eq!(checkpoint.passes, Some(2));
+    assert_eq!(checkpoint.path(&scene.output_file), Path::new("output/checkpoint.checkpoint"));
+
+    let named = Checkpoint { file: Some(String::from("long.bin")), ..Default::default() };
+    assert_eq!(named.path("image.png"), Path::new("output/long.bin"));
+    assert!(!named.should_save(1, 0.0));
+    assert!(named.should_save(1, DEFAULT_SECONDS), "A checkpoint without interval saves by default");
+}
+
+#[test]
+fn test_resumed_render_matches_uninterrupted() {
+    let scene = Scene::from_xml(SCENE).expect("Failed to parse Scene");
+    let path = temp_file("resume.checkpoint");
+
+    let mut uninterrupted = Framebuffer::new(16, 12);
+    for _ in 0..6 {
+        RenderService::render_pass(&scene, &mut uninterrupted);
+    }
+
+    // Interrupt after two passes and continue from the saved file
+    let mut interrupted = Framebuffer::new(16, 12);
+    for _ in 0..2 {
+        RenderService::render_pass(&scene, &mut interrupted);
+    }
+    interrupted.save(&path, scene.content_hash()).expect("Failed to save checkpoint");
+    drop(interrupted);
+
+    let mut resumed = Framebuffer::load(&path, scene.content_hash()).expect("Failed to load checkpoint");
+    fs::remove_file(&path).ok();
+    assert_eq!(resumed.passes(), 2);
+    for _ in resumed.passes()..6 {
+        RenderService::render_pass(&scene, &mut resumed);
+    }
+
+    assert_eq!(resumed, uninterrupted, "Resumed render must be identical");
+}
+
+#[test]
+fn test_load_rejects_other_files() {
+    let path = temp_file("invalid.checkpoint");
+    fs::write(&path, b"not a checkpoint").expect("Failed to write file");
+
+    let result = Framebuffer::load(&path, 0);
+    fs::remove_file(&path).ok();
+
+    assert!(result.is_err(), "Loading a file that is no checkpoint should fail");
+}
+
+#[test]
+fn test_load_rejects_checkpoint_of_other_scene() {
+    let scene = Scene::from_xml(SCENE).expect("Failed to parse Scene");
+    let other = Scene::from_xml(&SCENE.replace(r#"r="0.95""#, r#"r="0.5""#)).expect("Failed to parse Scene");
+    assert_ne!(scene.content_hash(), other.content_hash());
+
+    let path = temp_file("other.checkpoint");
+    let mut framebuffer = Framebuffer::new(16, 12);
+    RenderService::render_pass(&other, &mut framebuffer);
+    framebuffer.save(&path, other.content_hash()).expect("Failed to save checkpoint");
+
+    let result = Framebuffer::load(&path, scene.content_hash());
+    fs::remove_file(&path).ok();
+
+    assert!(result.is_err(), "A checkpoint of another scene must not be resumed");
+}
+
+#[test]
+fn test_load_rejects_size_beyond_file() {
+    // A header claiming a huge image must fail without allocating for it
+    let path = temp_file("huge.checkpoint");
+    let mut bytes = b"RTCKPT05".to_vec();
+    bytes.extend(0u64.to_le_bytes());
+    for value in [0u32, 0, u32::MAX, u32::MAX] {
+        bytes.extend(value.to_le_bytes());
+    }
+    fs::write(&path, bytes).expect("Failed to write file");
+
+    let result = Framebuffer::load(&path, 0);
+    fs::remove_file(&path).ok();
+
+    assert!(result.is_err(), "Loading a truncated checkpoint should fail");
+}
+
+#[test]
+fn test_content_hash() {
+    let scene = Scene::from_xml(SCENE).expect("Failed to parse Scene");
+    let again = Scene::from_xml(SCENE).expect("Failed to parse Scene");
+    assert_eq!(scene.content_hash(), again.content_hash(), "The hash only depends on the scene");
+
+    // Render settings changed after parsing are part of the hash
+    let mut more_samples = Scene::from_xml(SCENE).expect("Failed to parse Scene");
+    more_samples.camera.samples.n = 12;
+    assert_ne!(scene.content_hash(), more_samples.content_hash());
+    let mut larger = Scene::from_xml(SCENE).expect("Failed to parse Scene");
+    larger.camera.resolution.horizontal = 32;
+    assert_ne!(scene.content_hash(), larger.content_hash());
+}
@@ -0,0 +1,100 @@
+// Every test file only uses some of the helpers
+#![allow(dead_code)]
+
+use ray_tracing::models::bsdf::Bsdf;
+use ray_tracing::models::color::Color;
+use ray_tracing::models::material::{MaterialSolid, Phong, Reflectance, Refraction, Transmittance};
+use ray_tracing::models::random::Random;
+use ray_tracing::models::scene::Scene;
+use ray_tracing::models::shading::Shading;
+use ray_tracing::models::vector::Vector;
+use serde_xml_rs::from_str;
+
+/// Orange sphere in front of the camera
+pub const SPHERE: &str = r#"
+    <sphere radius="1.0">
+        <position x="0.0" y="0.0" z="-3.0"/>
+        <material_solid>
+            <color r="0.95" g="0.63" b="0.01"/>
+            <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+            <reflectance r="0.0"/>
+            <transmittance t="0.0"/>
+            <refraction iof="2.3"/>
+        </material_solid>
+    </sphere>
+"#;
+
+pub const AMBIENT_LIGHT: &str = r#"
+    <ambient_light>
+        <color r="1.0" g="1.0" b="1.0"/>
+    </ambient_light>
+"#;
+
+/// Creates a scene with a blue background, seen from the origin towards the negative z axis.
+/// `camera` holds the camera elements after the number of bounces, like the samples,
+/// and `settings` the elements after the surfaces.
+pub fn create_scene((width, height): (u32, u32), camera: &str, lights: &str, surfaces: &str, settings: &str) -> Scene {
+    let xml_data = format!(r#"
+        <scene output_file="test.png">
+            <background_color r="0.0" g="0.0" b="1.0"/>
+            <camera>
+                <position x="0.0" y="0.0" z="1.0"/>
+                <lookat x="0.0" y="0.0" z="-2.5"/>
+                <up x="0.0" y="1.0" z="0.0"/>
+                <horizontal_fov angle="45"/>
+                <resolution horizontal="{}" vertical="{}"/>
+                <max_bounces n="8"/>
+                {}
+            </camera>
+            <lights>{}</lights>
+            <surfaces>{}</surfaces>
+            {}
+        </scene>
+    "#, width, height, camera, lights, surfaces, settings);
+    from_str(&xml_data).expect("Failed to parse Scene")
+}
+
+/// Opaque, not reflecting material of the given color for surfaces built in code
+pub fn create_test_material(color: Color) -> MaterialSolid {
+    MaterialSolid {
+        color,
+        phong: Phong {
+            ka: 0.3,
+            kd: 0.7,
+            ks: 1.0,
+            exponent: 32.0,
+        },
+        reflectance: Reflectance { r: 0.0, roughness: 0.0, samples: 1 },
+        transmittance: Transmittance { t: 0.0, roughness: 0.0, samples: 1, absorption: None },
+        refraction: Refraction { iof: 1.0, cauchy: None, sellmeier: None },
+        shading: Shading::default(),
+        normal_map: None,
+        bump_map: None,
+    }
+}
+
+/// Checks that the weights of sampled directions match the BSDF and its density,
+/// and returns the average weight, the part of the light that is scattered.
+/// Directions of perfectly smooth surfaces are only counted.
+pub fn check_sampling(bsdf: &dyn Bsdf, outgoing: Vector, normal: Vector) -> Color {
+    let mut random = Random::new(7, 0);
+    let mut total = Color::BLACK;
+    let count = 20000;
+
+    for _ in 0..count {
+        let Some(sample) = bsdf.sample(outgoing, normal, random.next_2d(), random.next_f64()) else {
+            continue;
+        };
+        total += sample.weight;
+        if sample.specular {
+            continue;
+        }
+        let value = bsdf.eval(outgoing, sample.direction, normal) * (sample.direction.dot(normal).abs() / sample.pdf);
+        let pdf = bsdf.pdf(outgoing, sample.direction, normal);
+        assert!((pdf - sample.pdf).abs() <= 1e-6 * pdf.max(1.0), "Density {} but sampled with {}", pdf, sample.pdf);
+        for (a, b) in [(value.r, sample.weight.r), (value.g, sample.weight.g), (value.b, sample.weight.b)] {
+            assert!((a - b).abs() <= 1e-6 * a.max(1.0), "Weight {} does not match the BSDF {}", b, a);
+        }
+    }
+    total * (1.0 / count as f64)
+}
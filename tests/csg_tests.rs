@@ -0,0 +1,169 @@
+mod common;
+
+use ray_tracing::models::csg::{Csg, CsgOperation};
+use ray_tracing::models::mesh::Mesh;
+use ray_tracing::models::sphere::Sphere;
+use ray_tracing::models::surface::{Surface, SurfaceType};
+use ray_tracing::models::ray::Ray;
+use ray_tracing::models::point::Point;
+use ray_tracing::models::vector::Vector;
+use ray_tracing::models::color::Color;
+use serde_xml_rs::from_str;
+
+fn create_sphere(x: f64, z: f64, radius: f64, color: Color) -> SurfaceType {
+    SurfaceType::Sphere(Sphere {
+        radius,
+        position: Point::new(x, 0.0, z),
+        material_solid: Some(common::create_test_material(color)),
+        material_textured: None,
+        material_conductor: None,
+        material_dielectric: None,
+        material_principled: None,
+        motion: None,
+    })
+}
+
+/// The box model, a cube from -1 to 1 whose faces are split into two triangles along a diagonal
+fn create_box() -> SurfaceType {
+    let mut mesh = Mesh::new(String::from("box.obj"), Some(common::create_test_material(Color::WHITE)), None);
+    mesh.load_obj("assets/obj_models/box.obj").expect("Failed to load box.obj");
+    SurfaceType::Mesh(mesh)
+}
+
+fn forward_ray() -> Ray {
+    Ray::new(
+        Point::new(0.0, 0.0, 0.0),
+        Vector::new(0.0, 0.0, -1.0),
+        0.01,
+        f64::INFINITY,
+    )
+}
+
+#[test]
+fn test_parse_csg() {
+    let xml_data = r#"
+        <csg operation="difference">
+            <sphere radius="1.0">
+                <position x="0.0" y="0.0" z="-3.0"/>
+                <material_solid>
+                    <color r="0.95" g="0.63" b="0.01"/>
+                    <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+                    <reflectance r="0.0"/>
+                    <transmittance t="0.0"/>
+                    <refraction iof="2.3"/>
+                </material_solid>
+            </sphere>
+            <csg operation="union">
+                <sphere radius="0.5">
+                    <position x="0.0" y="0.0" z="-2.0"/>
+                    <material_solid>
+                        <color r="0.95" g="0.63" b="0.01"/>
+                        <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+                        <reflectance r="0.0"/>
+                        <transmittance t="0.0"/>
+                        <refraction iof="2.3"/>
+                    </material_solid>
+                </sphere>
+            </csg>
+        </csg>
+    "#;
+
+    let csg: Csg = from_str(xml_data).expect("Failed to parse Csg");
+
+    assert_eq!(csg.operation, CsgOperation::Difference);
+    assert_eq!(csg.children.len(), 2);
+    assert!(matches!(&csg.children[1], SurfaceType::Csg(inner) if inner.operation == CsgOperation::Union));
+}
+
+#[test]
+fn test_csg_intersection_lens() {
+    // Two overlapping spheres, the lens lies between z = -4.5 and z = -3.5
+    let csg = Csg::new(
+        CsgOperation::Intersection,
+        vec![
+            create_sphere(0.0, -3.0, 1.5, Color::WHITE),
+            create_sphere(0.0, -5.0, 1.5, Color::WHITE),
+        ],
+    );
+
+    let hits = csg.intersect_all(&forward_ray());
+
+    assert_eq!(hits.len(), 2, "Ray should enter and leave the lens once");
+    assert!((hits[0].t - 3.5).abs() < 1e-6, "Incorrect entry distance");
+    assert!((hits[1].t - 4.5).abs() < 1e-6, "Incorrect exit distance");
+    assert_eq!(hits[0].normal, Vector::new(0.0, 0.0, 1.0));
+    assert_eq!(hits[1].normal, Vector::new(0.0, 0.0, -1.0));
+}
+
+#[test]
+fn test_csg_difference_flips_normal_and_keeps_material() {
+    let red = Color::new(1.0, 0.0, 0.0);
+    let green = Color::new(0.0, 1.0, 0.0);
+
+    // The small sphere cuts away the front of the large one
+    let csg = Csg::new(
+        CsgOperation::Difference,
+        vec![
+            create_sphere(0.0, -5.0, 1.0, red),
+            create_sphere(0.0, -4.0, 0.5, green),
+        ],
+    );
+
+    let hit = csg.intersect(&forward_ray()).expect("Ray should hit the cut-away sphere");
+
+    // The ray passes the removed part and hits the inner wall of the cut
+    assert!((hit.t - 4.5).abs() < 1e-6, "Incorrect intersection distance");
+    assert_eq!(hit.normal, Vector::new(0.0, 0.0, 1.0), "Normal should point out of the result");
+    assert_eq!(hit.material.color(), green, "Hit should use the material of the cutting child");
+}
+
+#[test]
+fn test_csg_union_from_inside() {
+    let csg = Csg::new(
+        CsgOperation::Union,
+        vec![
+            create_sphere(0.0, 0.0, 1.0, Color::WHITE),
+            create_sphere(0.0, -1.5, 1.0, Color::WHITE),
+        ],
+    );
+
+    // The ray starts inside the first sphere, the shared interior must not produce hits
+    let hits = csg.intersect_all(&forward_ray());
+
+    assert_eq!(hits.len(), 1, "Only the far side of the union should be hit");
+    assert!((hits[0].t - 2.5).abs() < 1e-6, "Incorrect exit distance");
+}
+
+#[test]
+fn test_csg_intersection_miss() {
+    // Spheres that do not overlap have an empty intersection
+    let csg = Csg::new(
+        CsgOperation::Intersection,
+        vec![
+            create_sphere(0.0, -3.0, 1.0, Color::WHITE),
+            create_sphere(0.0, -6.0, 1.0, Color::WHITE),
+        ],
+    );
+
+    assert!(csg.intersect(&forward_ray()).is_none(), "Ray should miss the empty intersection");
+}
+
+#[test]
+fn test_csg_ray_through_mesh_edges() {
+    let csg = Csg::new(CsgOperation::Union, vec![create_box(), create_sphere(-4.0, -2.0, 1.0, Color::WHITE)]);
+
+    // The ray passes the diagonals of the front and back face, both triangles of a face are hit
+    let through = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0), 0.01, f64::INFINITY);
+    let hits = csg.intersect_all(&through);
+    assert_eq!(hits.len(), 2, "The ray should enter and leave the box once");
+    assert!((hits[0].t - 4.0).abs() < 1e-6 && (hits[1].t - 6.0).abs() < 1e-6);
+
+    // The ray touches the box along the edge between its front and left face,
+    // entering one and leaving the other at the same point, and goes on to the sphere
+    let grazing = Ray::new(Point::new(1.0, 0.0, 3.0), Vector::new(-1.0, 0.0, -1.0), 0.01, f64::INFINITY);
+    let hits = csg.intersect_all(&grazing);
+    let center = 5.0 * 2.0_f64.sqrt();
+    assert_eq!(hits.len(), 2, "Only the sphere should be entered and left");
+    assert!((hits[0].t - (center - 1.0)).abs() < 1e-6, "Incorrect entry distance {}", hits[0].t);
+    assert!((hits[1].t - (center + 1.0)).abs() < 1e-6, "Incorrect exit distance {}", hits[1].t);
+}
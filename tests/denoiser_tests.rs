@@ -0,0 +1,129 @@
+use image::{Rgb, Rgb32FImage};
+use ray_tracing::models::color::Color;
+use ray_tracing::models::denoiser::Denoiser;
+use ray_tracing::models::framebuffer::Framebuffer;
+use ray_tracing::models::random::Random;
+use ray_tracing::models::scene::Scene;
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+const SIZE: u32 = 24;
+
+/// Gray image with noise of the given amplitude, the left and right half differ in brightness
+fn noisy_image(left: f32, right: f32, amplitude: f32) -> (Rgb32FImage, Rgb32FImage) {
+    let mut random = Random::new(7, 0);
+    let color = Rgb32FImage::from_fn(SIZE, SIZE, |x, _| {
+        let base = if x < SIZE / 2 { left } else { right };
+        let value = base + amplitude * (random.next_f64() as f32 - 0.5);
+        Rgb([value; 3])
+    });
+    // Variance of a uniform distribution of the given width
+    let variance = Rgb32FImage::from_pixel(SIZE, SIZE, Rgb([amplitude * amplitude / 12.0; 3]));
+    (color, variance)
+}
+
+fn uniform(value: f32) -> Rgb32FImage {
+    Rgb32FImage::from_pixel(SIZE, SIZE, Rgb([value; 3]))
+}
+
+fn error(image: &Rgb32FImage, left: f32, right: f32) -> f32 {
+    image.enumerate_pixels()
+        .map(|(x, _, p)| (p[0] - if x < SIZE / 2 { left } else { right }).powi(2))
+        .sum::<f32>() / (SIZE * SIZE) as f32
+}
+
+#[test]
+fn test_denoise_reduces_noise() {
+    let (color, variance) = noisy_image(0.5, 0.5, 0.2);
+    let denoised = Denoiser::default().denoise(&color, &variance, &uniform(0.5), &uniform(0.0), &uniform(3.0));
+
+    assert!(error(&denoised, 0.5, 0.5) < error(&color, 0.5, 0.5) / 4.0, "Noise should be reduced");
+}
+
+#[test]
+fn test_denoise_keeps_edges_of_guides() {
+    let (color, variance) = noisy_image(0.2, 0.8, 0.1);
+    // The albedo tells the two halves apart
+    let albedo = Rgb32FImage::from_fn(SIZE, SIZE, |x, _| Rgb([if x < SIZE / 2 { 0.2 } else { 0.8 }; 3]));
+    let denoised = Denoiser::default().denoise(&color, &variance, &albedo, &uniform(0.0), &uniform(3.0));
+
+    assert!(error(&denoised, 0.2, 0.8) < error(&color, 0.2, 0.8));
+    assert!((denoised.get_pixel(SIZE / 2 - 1, 5)[0] - 0.2).abs() < 0.1, "The edge should stay sharp");
+    assert!((denoised.get_pixel(SIZE / 2, 5)[0] - 0.8).abs() < 0.1, "The edge should stay sharp");
+}
+
+#[test]
+fn test_denoise_without_noise_keeps_image() {
+    let (color, _) = noisy_image(0.2, 0.8, 0.3);
+    let denoised = Denoiser::default().denoise(&color, &uniform(0.0), &uniform(0.5), &uniform(0.0), &uniform(3.0));
+
+    for (a, b) in color.pixels().zip(denoised.pixels()) {
+        assert!((a[0] - b[0]).abs() < 2.0 / 255.0);
+    }
+}
+
+#[test]
+fn test_framebuffer_variance() {
+    let mut framebuffer = Framebuffer::new(1, 1);
+    framebuffer.add_sample(0, 0, Color::new(0.0, 1.0, 0.5));
+    assert_eq!(framebuffer.variance(0, 0), Color::BLACK, "One sample gives no estimate");
+
+    framebuffer.add_sample(0, 0, Color::new(1.0, 1.0, 0.5));
+    let variance = framebuffer.variance(0, 0);
+    assert!((variance.r - 0.25).abs() < 1e-12);
+    assert_eq!(variance.g, 0.0);
+    assert_eq!(variance.b, 0.0);
+}
+
+#[test]
+fn test_render_with_denoiser() {
+    let xml_data = r#"
+        <scene output_file="denoise.png">
+            <background_color r="0.0" g="0.0" b="0.0"/>
+            <camera>
+                <position x="0.0" y="0.0" z="1.0"/>
+                <lookat x="0.0" y="0.0" z="-2.5"/>
+                <up x="0.0" y="1.0" z="0.0"/>
+                <horizontal_fov angle="45"/>
+                <resolution horizontal="20" vertical="16"/>
+                <max_bounces n="8"/>
+                <samples n="4"/>
+                <lens aperture="0.5" focus_distance="1.5"/>
+            </camera>
+            <lights>
+                <ambient_light>
+                    <color r="1.0" g="1.0" b="1.0"/>
+                </ambient_light>
+            </lights>
+            <surfaces>
+                <sphere radius="1.0">
+                    <position x="0.0" y="0.0" z="-3.0"/>
+                    <material_solid>
+                        <color r="0.95" g="0.63" b="0.01"/>
+                        <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+                        <reflectance r="0.0"/>
+                        <transmittance t="0.0"/>
+                        <refraction iof="2.3"/>
+                    </material_solid>
+                </sphere>
+            </surfaces>
+            <denoiser radius="3"/>
+        </scene>
+    "#;
+    let mut scene: Scene = from_str(xml_data).expect("Failed to parse Scene");
+    let denoiser = scene.denoiser.clone().expect("Denoiser is missing");
+    assert_eq!(denoiser.radius, 3);
+    assert_eq!(denoiser.strength, Denoiser::default().strength);
+
+    let guides = RenderService::denoise_guides(&scene).expect("Guides are missing");
+    assert_eq!(guides.depth.dimensions(), (20, 16));
+
+    let denoised = RenderService::render_to_buffer(&scene, false).color;
+    scene.denoiser = None;
+    assert!(RenderService::denoise_guides(&scene).is_none(), "Guides are only rendered for the denoiser");
+    let noisy = RenderService::render_to_buffer(&scene, false).color;
+
+    assert_eq!(denoised.dimensions(), noisy.dimensions());
+    assert_ne!(denoised, noisy, "The out of focus sphere should be smoothed");
+    assert_eq!(*denoised.get_pixel(0, 0), Rgb([0.0, 0.0, 0.0]), "The background has no noise");
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use ray_tracing::models::material::{Gloss, Reflectance, Transmittance};
+use ray_tracing::models::random::Random;
+use ray_tracing::models::vector::Vector;
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+/// Mirror sphere in front of the camera, reflecting an orange sphere behind the camera
+fn mirror_spheres(reflectance: &str) -> String {
+    let mirror = common::SPHERE.replace(r#"<reflectance r="0.0"/>"#, reflectance);
+    format!("{}{}", mirror, common::SPHERE.replace(r#"z="-3.0""#, r#"z="3.0""#))
+}
+
+#[test]
+fn test_parse_gloss() {
+    let reflectance: Reflectance = from_str(r#"<reflectance r="0.8" roughness="0.2" samples="16"/>"#)
+        .expect("Failed to parse Reflectance");
+    assert_eq!(reflectance.gloss(), Gloss { roughness: 0.2, samples: 16 });
+
+    let transmittance: Transmittance = from_str(r#"<transmittance t="0.5"/>"#).expect("Failed to parse Transmittance");
+    assert_eq!(transmittance.gloss(), Gloss { roughness: 0.0, samples: 4 });
+}
+
+#[test]
+fn test_only_camera_rays_split() {
+    let rough = Gloss { roughness: 0.3, samples: 8 };
+    assert_eq!(rough.rays(true), 8);
+    assert_eq!(rough.rays(false), 1);
+    assert_eq!(Gloss { roughness: 0.0, samples: 8 }.rays(true), 1, "A mirror needs one ray");
+}
+
+#[test]
+fn test_perturbed_directions_stay_in_cone() {
+    let gloss = Gloss { roughness: 0.25, samples: 1 };
+    let normal = Vector::new(0.0, 1.0, 0.0);
+    let ideal = Vector::new(1.0, 0.2, 0.0).normalize();
+    let mut random = Random::new(3, 0);
+    let mut mean = Vector::new(0.0, 0.0, 0.0);
+
+    for _ in 0..2000 {
+        let direction = gloss.perturb(ideal, normal, random.next_2d());
+        assert!((direction.length() - 1.0).abs() < 1e-9);
+        assert!(direction.dot(normal) >= 0.0, "Reflected rays stay above the surface");
+        mean = mean + direction;
+        // Mirrored directions are not in the cone, but never further from it than the surface
+        if direction.dot(normal) > 0.2 {
+            assert!(direction.dot(ideal) >= 1.0 / (1.0 + 0.25_f64 * 0.25).sqrt() - 1e-9);
+        }
+    }
+    assert!(mean.normalize().dot(ideal) > 0.99, "The spread is centered on the ideal direction");
+    assert_eq!(Gloss { roughness: 0.0, samples: 1 }.perturb(ideal, normal, (0.3, 0.7)), ideal);
+}
+
+#[test]
+fn test_render_rough_reflection() {
+    let render = |reflectance: &str| {
+        let scene = common::create_scene((16, 12), r#"<samples n="4"/>"#, common::AMBIENT_LIGHT, &mirror_spheres(reflectance), "");
+        RenderService::render_to_buffer(&scene, false).color
+    };
+
+    let mirror = render(r#"<reflectance r="0.5"/>"#);
+    assert_eq!(mirror, render(r#"<reflectance r="0.5" roughness="0.0" samples="8"/>"#));
+    let rough = render(r#"<reflectance r="0.5" roughness="0.4" samples="8"/>"#);
+    assert_ne!(mirror, rough);
+    assert_eq!(rough, render(r#"<reflectance r="0.5" roughness="0.4" samples="8"/>"#), "Renders are reproducible");
+}
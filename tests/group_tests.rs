@@ -0,0 +1,207 @@
+mod common;
+
+use ray_tracing::models::group::Group;
+use ray_tracing::models::sphere::Sphere;
+use ray_tracing::models::surface::{Surface, SurfaceType};
+use ray_tracing::models::transform::{Transform, TransformOperation};
+use ray_tracing::models::ray::Ray;
+use ray_tracing::models::scene::Scene;
+use ray_tracing::models::point::Point;
+use ray_tracing::models::vector::Vector;
+use ray_tracing::models::color::Color;
+use serde_xml_rs::from_str;
+
+#[test]
+fn test_parse_nested_group_with_default_material() {
+    let xml_data = r#"
+        <group>
+            <material_solid>
+                <color r="0.5" g="0.3" b="0.1"/>
+                <phong ka="0.3" kd="0.9" ks="1.0" exponent="20"/>
+                <reflectance r="0"/>
+                <transmittance t="0"/>
+                <refraction iof="0"/>
+            </material_solid>
+            <transform>
+                <translate x="0.0" y="-1.0" z="-5.0"/>
+            </transform>
+            <surfaces>
+                <sphere radius="1.0">
+                    <position x="0.0" y="0.0" z="0.0"/>
+                </sphere>
+                <group>
+                    <transform>
+                        <rotateY theta="90"/>
+                    </transform>
+                    <surfaces>
+                        <sphere radius="0.5">
+                            <position x="1.0" y="0.0" z="0.0"/>
+                            <material_solid>
+                                <color r="1.0" g="1.0" b="1.0"/>
+                                <phong ka="0.3" kd="0.9" ks="1.0" exponent="20"/>
+                                <reflectance r="0"/>
+                                <transmittance t="0"/>
+                                <refraction iof="0"/>
+                            </material_solid>
+                        </sphere>
+                        <sphere radius="0.5">
+                            <position x="-1.0" y="0.0" z="0.0"/>
+                        </sphere>
+                    </surfaces>
+                </group>
+            </surfaces>
+        </group>
+    "#;
+
+    let mut group: Group = from_str(xml_data).expect("Failed to parse Group");
+    group.apply_default_material(None);
+
+    assert_eq!(group.surfaces.surfaces.len(), 2);
+
+    let wood = Color::new(0.5, 0.3, 0.1);
+    let SurfaceType::Sphere(sphere) = &group.surfaces.surfaces[0] else {
+        panic!("Expected a sphere");
+    };
+    assert_eq!(sphere.material().color(), wood, "Default material not applied");
+
+    let SurfaceType::Group(nested) = &group.surfaces.surfaces[1] else {
+        panic!("Expected a nested group");
+    };
+    assert_eq!(nested.transform.operations, vec![TransformOperation::RotateY { theta: 90.0 }]);
+
+    let SurfaceType::Sphere(own) = &nested.surfaces.surfaces[0] else {
+        panic!("Expected a sphere");
+    };
+    assert_eq!(own.material().color(), Color::WHITE, "Own material must not be replaced");
+
+    let SurfaceType::Sphere(inherited) = &nested.surfaces.surfaces[1] else {
+        panic!("Expected a sphere");
+    };
+    assert_eq!(inherited.material().color(), wood, "Default material not passed to nested group");
+}
+
+#[test]
+fn test_nested_group_transforms_are_composed() {
+    let wheel = SurfaceType::Sphere(Sphere {
+        radius: 0.5,
+        position: Point::new(2.0, 0.0, 0.0),
+        material_solid: Some(common::create_test_material(Color::WHITE)),
+        material_textured: None,
+        material_conductor: None,
+        material_dielectric: None,
+        material_principled: None,
+        motion: None,
+    });
+
+    // Inner group turns the wheel from +x to -z, outer group moves everything away from the camera
+    let inner = Group::new(
+        Transform { operations: vec![TransformOperation::RotateY { theta: 90.0 }] },
+        vec![wheel],
+    );
+    let outer = Group::new(
+        Transform { operations: vec![TransformOperation::Translate { x: 0.0, y: 0.0, z: -3.0 }] },
+        vec![SurfaceType::Group(Box::new(inner))],
+    );
+
+    let ray = Ray::new(
+        Point::new(0.0, 0.0, 0.0),
+        Vector::new(0.0, 0.0, -1.0),
+        0.01,
+        f64::INFINITY,
+    );
+
+    let result = outer.intersect(&ray).expect("Ray should hit the transformed wheel");
+
+    // Wheel center ends up at z = -5, so the front is hit at z = -4.5
+    assert!((result.t - 4.5).abs() < 1e-6, "Incorrect intersection distance");
+    assert!((result.point - Point::new(0.0, 0.0, -4.5)).length() < 1e-6, "Incorrect intersection point");
+    assert!((result.normal - Vector::new(0.0, 0.0, 1.0)).length() < 1e-6, "Incorrect normal");
+}
+
+fn create_scene(definitions: &str, surfaces: &str) -> Scene {
+    let xml_data = format!(r#"
+        <scene output_file="groups.png">
+            <background_color r="0.0" g="0.0" b="0.0"/>
+            <camera>
+                <position x="0.0" y="0.0" z="1.0"/>
+                <lookat x="0.0" y="0.0" z="-2.5"/>
+                <up x="0.0" y="1.0" z="0.0"/>
+                <horizontal_fov angle="45"/>
+                <resolution horizontal="64" vertical="64"/>
+                <max_bounces n="8"/>
+            </camera>
+            <lights>
+                <ambient_light>
+                    <color r="1.0" g="1.0" b="1.0"/>
+                </ambient_light>
+            </lights>
+            <definitions>{}</definitions>
+            <surfaces>{}</surfaces>
+        </scene>
+    "#, definitions, surfaces);
+    from_str(&xml_data).expect("Failed to parse Scene")
+}
+
+const RED_MATERIAL: &str = r#"
+    <material_solid>
+        <color r="1.0" g="0.0" b="0.0"/>
+        <phong ka="0.3" kd="0.9" ks="1.0" exponent="20"/>
+        <reflectance r="0"/>
+        <transmittance t="0"/>
+        <refraction iof="0"/>
+    </material_solid>
+"#;
+
+#[test]
+fn test_surface_without_material_is_rejected() {
+    let sphere = r#"<sphere radius="1.0"><position x="0.0" y="0.0" z="-5.0"/></sphere>"#;
+    let mut scene = create_scene("", sphere);
+    let error = scene.load_meshes().expect_err("A sphere outside of a group needs a material");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    let grouped = format!("<group>{}<surfaces>{}</surfaces></group>", RED_MATERIAL, sphere);
+    let mut scene = create_scene("", &grouped);
+    scene.load_meshes().expect("The group provides the material");
+}
+
+#[test]
+fn test_group_default_keeps_instance_materials() {
+    let definitions = r#"
+        <geometry name="ball">
+            <sphere radius="1.0">
+                <position x="0.0" y="0.0" z="0.0"/>
+                <material_solid>
+                    <color r="1.0" g="1.0" b="1.0"/>
+                    <phong ka="0.3" kd="0.9" ks="1.0" exponent="20"/>
+                    <reflectance r="0"/>
+                    <transmittance t="0"/>
+                    <refraction iof="0"/>
+                </material_solid>
+            </sphere>
+        </geometry>
+    "#;
+    let surfaces = format!(r#"
+        <group>
+            {}
+            <surfaces>
+                <instance geometry="ball">
+                    <transform><translate x="0.0" y="0.0" z="-5.0"/></transform>
+                </instance>
+            </surfaces>
+        </group>
+    "#, RED_MATERIAL);
+    let mut scene = create_scene(definitions, &surfaces);
+    scene.load_meshes().expect("Failed to load meshes");
+
+    let SurfaceType::Group(group) = &scene.surfaces.surfaces[0] else {
+        panic!("Expected a group");
+    };
+    let SurfaceType::Instance(instance) = &group.surfaces.surfaces[0] else {
+        panic!("Expected an instance");
+    };
+    assert!(instance.material().is_none(), "Group default must not override the definition");
+
+    let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0), 0.01, f64::INFINITY);
+    let hit = scene.surfaces.surfaces[0].intersect(&ray).expect("Ray should hit the ball");
+    assert_eq!(hit.material.color(), Color::WHITE);
+}
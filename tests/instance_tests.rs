@@ -0,0 +1,269 @@
+mod common;
+
+use std::sync::Arc;
+use ray_tracing::models::instance::{Geometry, Instance};
+use ray_tracing::models::matrix::Matrix4;
+use ray_tracing::models::mesh::Mesh;
+use ray_tracing::models::sphere::Sphere;
+use ray_tracing::models::scene::Scene;
+use ray_tracing::models::surface::{Surface, SurfaceType};
+use ray_tracing::models::transform::{Transform, TransformOperation};
+use ray_tracing::models::ray::Ray;
+use ray_tracing::models::point::Point;
+use ray_tracing::models::vector::Vector;
+use ray_tracing::models::color::Color;
+use serde_xml_rs::from_str;
+
+fn unit_sphere_geometry() -> Arc<Geometry> {
+    Arc::new(Geometry {
+        name: String::from("ball"),
+        surfaces: vec![SurfaceType::Sphere(Sphere {
+            radius: 1.0,
+            position: Point::new(0.0, 0.0, 0.0),
+            material_solid: Some(common::create_test_material(Color::WHITE)),
+            material_textured: None,
+            material_conductor: None,
+            material_dielectric: None,
+            material_principled: None,
+            motion: None,
+        })],
+    })
+}
+
+#[test]
+fn test_matrix_inverse() {
+    let m = Matrix4::translation(1.0, 2.0, 3.0)
+        * Matrix4::rotation_y(30.0)
+        * Matrix4::scaling(2.0, 0.5, 4.0);
+    let product = m * m.inverse().expect("Matrix should be invertible");
+
+    for i in 0..4 {
+        for j in 0..4 {
+            let expected = if i == j { 1.0 } else { 0.0 };
+            assert!((product.m[i][j] - expected).abs() < 1e-9, "M * M^-1 should be the identity");
+        }
+    }
+
+    assert!(Matrix4::scaling(1.0, 0.0, 1.0).inverse().is_none(), "Zero scale is not invertible");
+}
+
+#[test]
+fn test_parse_definitions_and_instances() {
+    let xml_data = r#"
+        <scene output_file="instances.png">
+            <background_color r="0.0" g="0.0" b="0.0"/>
+            <camera>
+                <position x="0.0" y="0.0" z="1.0"/>
+                <lookat x="0.0" y="0.0" z="-2.5"/>
+                <up x="0.0" y="1.0" z="0.0"/>
+                <horizontal_fov angle="45"/>
+                <resolution horizontal="512" vertical="512"/>
+                <max_bounces n="8"/>
+            </camera>
+            <lights>
+                <ambient_light>
+                    <color r="1.0" g="1.0" b="1.0"/>
+                </ambient_light>
+            </lights>
+            <definitions>
+                <geometry name="box">
+                    <mesh name="box.obj">
+                        <material_solid>
+                            <color r="1.0" g="1.0" b="1.0"/>
+                            <phong ka="0.3" kd="0.9" ks="1.0" exponent="20"/>
+                            <reflectance r="0"/>
+                            <transmittance t="0"/>
+                            <refraction iof="0"/>
+                        </material_solid>
+                    </mesh>
+                </geometry>
+            </definitions>
+            <surfaces>
+                <instance geometry="box">
+                    <transform>
+                        <translate x="-2.0" y="0.0" z="-5.0"/>
+                        <rotateY theta="30"/>
+                    </transform>
+                </instance>
+                <instance geometry="box">
+                    <material_solid>
+                        <color r="1.0" g="0.0" b="0.0"/>
+                        <phong ka="0.3" kd="0.9" ks="1.0" exponent="20"/>
+                        <reflectance r="1.0"/>
+                        <transmittance t="0"/>
+                        <refraction iof="2.3"/>
+                    </material_solid>
+                    <transform>
+                        <translate x="2.0" y="0.0" z="-5.0"/>
+                    </transform>
+                </instance>
+            </surfaces>
+        </scene>
+    "#;
+
+    let mut scene: Scene = from_str(xml_data).expect("Failed to parse Scene");
+    scene.load_meshes().expect("Failed to load meshes");
+
+    assert_eq!(scene.definitions.geometry.len(), 1);
+    assert_eq!(scene.surfaces.surfaces.len(), 2);
+
+    let SurfaceType::Instance(first) = &scene.surfaces.surfaces[0] else {
+        panic!("Expected an instance");
+    };
+    assert_eq!(
+        first.transform.operations[1],
+        TransformOperation::RotateY { theta: 30.0 }
+    );
+    assert!(first.material().is_none());
+
+    // All instances share the triangles loaded for the definition
+    assert_eq!(Arc::strong_count(&scene.definitions.geometry[0]), 3);
+    let SurfaceType::Mesh(mesh) = &scene.definitions.geometry[0].surfaces[0] else {
+        panic!("Expected a mesh");
+    };
+    assert_eq!(mesh.triangles.len(), 12);
+}
+
+#[test]
+fn test_instance_intersection_transformed() {
+    let transform = Transform {
+        operations: vec![
+            TransformOperation::Translate { x: 0.0, y: 0.0, z: -5.0 },
+            TransformOperation::Scale { x: 1.0, y: 1.0, z: 2.0 },
+        ],
+    };
+    let mut instance = Instance::new(unit_sphere_geometry(), transform);
+    instance.material_solid = Some(common::create_test_material(Color::new(1.0, 0.0, 0.0)));
+
+    let ray = Ray::new(
+        Point::new(0.0, 0.0, 0.0),
+        Vector::new(0.0, 0.0, -1.0),
+        0.01,
+        f64::INFINITY,
+    );
+
+    let result = instance.intersect(&ray).expect("Ray should hit the stretched sphere");
+
+    // The sphere is stretched to reach from z = -7 to z = -3
+    assert!((result.t - 3.0).abs() < 1e-6, "Incorrect intersection distance");
+    assert!((result.point - Point::new(0.0, 0.0, -3.0)).length() < 1e-6, "Incorrect intersection point");
+    assert!((result.normal - Vector::new(0.0, 0.0, 1.0)).length() < 1e-6, "Incorrect normal");
+    assert_eq!(result.material.color(), Color::new(1.0, 0.0, 0.0), "Material override not applied");
+
+    let miss = Ray::new(
+        Point::new(1.5, 0.0, 0.0),
+        Vector::new(0.0, 0.0, -1.0),
+        0.01,
+        f64::INFINITY,
+    );
+    assert!(instance.intersect(&miss).is_none(), "Ray should miss the instance");
+}
+
+#[test]
+fn test_mesh_bvh_matches_all_triangles() {
+    let mut mesh = Mesh::new(
+        String::from("cylinder.obj"),
+        Some(common::create_test_material(Color::WHITE)),
+        None,
+    );
+    mesh.load_obj("assets/obj_models/cylinder.obj").expect("Failed to load OBJ");
+
+    for i in 0..20 {
+        for j in 0..20 {
+            let origin = Point::new(-1.5 + 0.15 * i as f64, -1.5 + 0.15 * j as f64, 5.0);
+            let ray = Ray::new(origin, Vector::new(0.1, -0.05, -1.0), 0.01, f64::INFINITY);
+
+            let expected = mesh.intersect_all(&ray).into_iter().next().map(|hit| hit.t);
+            let actual = mesh.intersect(&ray).map(|hit| hit.t);
+            assert_eq!(actual, expected, "BVH result differs from testing all triangles");
+        }
+    }
+}
+
+fn scene_with_definitions(definitions: &str, surfaces: &str) -> Scene {
+    let xml_data = format!(r#"
+        <scene output_file="instances.png">
+            <background_color r="0.0" g="0.0" b="0.0"/>
+            <camera>
+                <position x="0.0" y="0.0" z="1.0"/>
+                <lookat x="0.0" y="0.0" z="-2.5"/>
+                <up x="0.0" y="1.0" z="0.0"/>
+                <horizontal_fov angle="45"/>
+                <resolution horizontal="64" vertical="64"/>
+                <max_bounces n="8"/>
+            </camera>
+            <lights>
+                <ambient_light>
+                    <color r="1.0" g="1.0" b="1.0"/>
+                </ambient_light>
+            </lights>
+            <definitions>{}</definitions>
+            <surfaces>{}</surfaces>
+        </scene>
+    "#, definitions, surfaces);
+    from_str(&xml_data).expect("Failed to parse Scene")
+}
+
+const BALL_DEFINITION: &str = r#"
+    <geometry name="ball">
+        <sphere radius="1.0">
+            <position x="0.0" y="0.0" z="0.0"/>
+            <material_solid>
+                <color r="1.0" g="1.0" b="1.0"/>
+                <phong ka="0.3" kd="0.9" ks="1.0" exponent="20"/>
+                <reflectance r="0"/>
+                <transmittance t="0"/>
+                <refraction iof="0"/>
+            </material_solid>
+        </sphere>
+    </geometry>
+"#;
+
+#[test]
+fn test_definition_instancing_later_definition() {
+    // The pair is listed first but needs the ball, which is loaded before it
+    let definitions = format!(r#"
+        <geometry name="pair">
+            <instance geometry="ball">
+                <transform><translate x="-1.5" y="0.0" z="0.0"/></transform>
+            </instance>
+            <instance geometry="ball">
+                <transform><translate x="1.5" y="0.0" z="0.0"/></transform>
+            </instance>
+        </geometry>
+        {}
+    "#, BALL_DEFINITION);
+    let surfaces = r#"
+        <instance geometry="pair">
+            <transform><translate x="0.0" y="0.0" z="-5.0"/></transform>
+        </instance>
+    "#;
+    let mut scene = scene_with_definitions(&definitions, surfaces);
+    scene.load_meshes().expect("Nested definitions should load");
+
+    let ray = Ray::new(Point::new(1.5, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0), 0.01, f64::INFINITY);
+    let hit = scene.surfaces.surfaces[0].intersect(&ray).expect("Ray should hit the right ball");
+    assert!((hit.t - 4.0).abs() < 1e-6, "Incorrect intersection distance");
+}
+
+#[test]
+fn test_circular_definitions_are_rejected() {
+    let definitions = r#"
+        <geometry name="a"><instance geometry="b"/></geometry>
+        <geometry name="b"><instance geometry="a"/></geometry>
+    "#;
+    let mut scene = scene_with_definitions(definitions, r#"<instance geometry="a"/>"#);
+    let error = scene.load_meshes().expect_err("Circular definitions cannot be loaded");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    let mut scene = scene_with_definitions(r#"<geometry name="a"><instance geometry="missing"/></geometry>"#, r#"<instance geometry="a"/>"#);
+    let error = scene.load_meshes().expect_err("Unknown definitions cannot be loaded");
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_loading_twice_is_an_error() {
+    let mut scene = scene_with_definitions(BALL_DEFINITION, r#"<instance geometry="ball"/>"#);
+    scene.load_meshes().expect("Failed to load meshes");
+    assert!(scene.load_meshes().is_err(), "Shared definitions cannot be loaded again");
+}
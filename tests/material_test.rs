@@ -1,4 +1,6 @@
 use ray_tracing::models::material::*;
+use ray_tracing::models::shading::Shading;
+use std::sync::Arc;
 use ray_tracing::models::color::Color;
 use serde_xml_rs::from_str;
 
@@ -39,3 +41,26 @@ fn test_parse_material_solid() {
     // Refraction
     assert_eq!(material.refraction.iof, 2.3);
 }
+
+#[test]
+fn test_textured_color() {
+    let mut data = image::RgbImage::new(2, 2);
+    data.put_pixel(0, 1, image::Rgb([255, 0, 0]));
+    data.put_pixel(1, 0, image::Rgb([0, 0, 255]));
+    let phong = Phong { ka: 0.3, kd: 0.9, ks: 1.0, exponent: 20.0 };
+    let material = Material::Textured(MaterialTextured {
+        texture: Texture { name: String::from("test.png"), data: Some(Arc::new(data)) },
+        phong,
+        reflectance: Reflectance { r: 0.0, roughness: 0.0, samples: 1 },
+        transmittance: Transmittance { t: 0.0, roughness: 0.0, samples: 1, absorption: None },
+        refraction: Refraction { iof: 1.0, cauchy: None, sellmeier: None },
+        shading: Shading::default(),
+        normal_map: None,
+        bump_map: None,
+    });
+
+    // v points up, so the bottom left texel is at (0, 0)
+    assert_eq!(material.color_at((0.25, 0.25)), Color::new(1.0, 0.0, 0.0));
+    assert_eq!(material.color_at((0.75, 0.75)), Color::new(0.0, 0.0, 1.0));
+    assert_eq!(material.color_at((1.75, -0.25)), Color::new(0.0, 0.0, 1.0), "The texture repeats");
+}
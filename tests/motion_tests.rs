@@ -0,0 +1,140 @@
+mod common;
+
+use std::sync::Arc;
+use ray_tracing::models::camera::Camera;
+use ray_tracing::models::instance::{Geometry, Instance};
+use ray_tracing::models::motion::{Motion, MotionKeyframe};
+use ray_tracing::models::sphere::Sphere;
+use ray_tracing::models::surface::{Surface, SurfaceType};
+use ray_tracing::models::transform::{Transform, TransformOperation};
+use ray_tracing::models::ray::Ray;
+use ray_tracing::models::point::Point;
+use ray_tracing::models::vector::Vector;
+use ray_tracing::models::color::Color;
+use serde_xml_rs::from_str;
+
+fn forward_ray(time: f64) -> Ray {
+    Ray::new(
+        Point::new(0.0, 0.0, 0.0),
+        Vector::new(0.0, 0.0, -1.0),
+        0.01,
+        f64::INFINITY,
+    ).with_time(time)
+}
+
+#[test]
+fn test_parse_shutter_and_motion() {
+    let xml_data = r#"
+        <camera>
+            <position x="0.0" y="0.0" z="1.0"/>
+            <lookat x="0.0" y="0.0" z="-2.5"/>
+            <up x="0.0" y="1.0" z="0.0"/>
+            <horizontal_fov angle="45"/>
+            <resolution horizontal="512" vertical="512"/>
+            <max_bounces n="8"/>
+            <shutter open="0.25" close="0.75"/>
+        </camera>
+    "#;
+
+    let camera: Camera = from_str(xml_data).expect("Failed to parse Camera");
+    assert_eq!(camera.shutter.open, 0.25);
+    assert_eq!(camera.shutter.close, 0.75);
+    assert_eq!(camera.shutter.sample_time(0.5), 0.5);
+
+    let xml_data = r#"
+        <sphere radius="1.0">
+            <position x="0.0" y="0.0" z="-3.0"/>
+            <material_solid>
+                <color r="0.95" g="0.63" b="0.01"/>
+                <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+                <reflectance r="0.0"/>
+                <transmittance t="0.0"/>
+                <refraction iof="2.3"/>
+            </material_solid>
+            <motion>
+                <keyframe time="0.0"/>
+                <keyframe time="1.0">
+                    <translate x="2.0" y="0.0" z="0.0"/>
+                    <rotate x="0.0" y="90.0" z="0.0"/>
+                </keyframe>
+            </motion>
+        </sphere>
+    "#;
+
+    let sphere: Sphere = from_str(xml_data).expect("Failed to parse Sphere");
+    let motion = sphere.motion.as_ref().expect("Motion should be parsed");
+    assert_eq!(motion.keyframe.len(), 2);
+    assert_eq!(motion.keyframe[0].translate, None);
+    assert_eq!(motion.keyframe[1].rotate, Some(Vector::new(0.0, 90.0, 0.0)));
+}
+
+#[test]
+fn test_motion_interpolation() {
+    // Keyframes are sorted by time, no matter in which order they are listed
+    let motion = Motion::new(vec![
+        MotionKeyframe { time: 1.0, translate: Some(Vector::new(4.0, 0.0, 0.0)), rotate: None },
+        MotionKeyframe { time: 0.0, translate: None, rotate: Some(Vector::new(0.0, 0.0, 90.0)) },
+    ]);
+
+    let (translation, rotation) = motion.offset_at(0.25);
+    assert_eq!(translation, Vector::new(1.0, 0.0, 0.0));
+    assert_eq!(rotation, Vector::new(0.0, 0.0, 67.5));
+
+    // Outside of the keyframes the object keeps still
+    assert_eq!(motion.translation_at(-1.0), Vector::new(0.0, 0.0, 0.0));
+    assert_eq!(motion.translation_at(2.0), Vector::new(4.0, 0.0, 0.0));
+}
+
+#[test]
+fn test_moving_sphere_depends_on_ray_time() {
+    let sphere = Sphere {
+        radius: 0.5,
+        position: Point::new(0.0, 0.0, -5.0),
+        material_solid: Some(common::create_test_material(Color::WHITE)),
+        material_textured: None,
+        material_conductor: None,
+        material_dielectric: None,
+        material_principled: None,
+        motion: Some(Motion::new(vec![
+            MotionKeyframe { time: 0.0, translate: None, rotate: None },
+            MotionKeyframe { time: 1.0, translate: Some(Vector::new(2.0, 0.0, 0.0)), rotate: None },
+        ])),
+    };
+
+    let start = sphere.intersect(&forward_ray(0.0)).expect("Ray should hit the sphere at the start");
+    assert!((start.t - 4.5).abs() < 1e-6, "Incorrect intersection distance");
+
+    assert!(sphere.intersect(&forward_ray(1.0)).is_none(), "Sphere should have moved out of the ray");
+}
+
+#[test]
+fn test_rotating_instance() {
+    // An off-center ball that swings from +x to -z around the origin of the instance
+    let geometry = Arc::new(Geometry {
+        name: String::from("ball"),
+        surfaces: vec![SurfaceType::Sphere(Sphere {
+            radius: 0.5,
+            position: Point::new(2.0, 0.0, 0.0),
+            material_solid: Some(common::create_test_material(Color::WHITE)),
+            material_textured: None,
+            material_conductor: None,
+            material_dielectric: None,
+            material_principled: None,
+            motion: None,
+        })],
+    });
+    let transform = Transform {
+        operations: vec![TransformOperation::Translate { x: 0.0, y: 0.0, z: -3.0 }],
+    };
+    let mut instance = Instance::new(geometry, transform);
+    instance.motion = Some(Motion::new(vec![
+        MotionKeyframe { time: 0.0, translate: None, rotate: None },
+        MotionKeyframe { time: 1.0, translate: None, rotate: Some(Vector::new(0.0, 90.0, 0.0)) },
+    ]));
+
+    assert!(instance.intersect(&forward_ray(0.0)).is_none(), "Ball should start beside the ray");
+
+    let end = instance.intersect(&forward_ray(1.0)).expect("Ball should have swung into the ray");
+    assert!((end.t - 4.5).abs() < 1e-6, "Incorrect intersection distance");
+    assert!((end.normal - Vector::new(0.0, 0.0, 1.0)).length() < 1e-6, "Incorrect normal");
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use std::sync::Arc;
+use ray_tracing::models::bump::{BumpMap, NormalMap};
+use ray_tracing::models::material::{MaterialSolid, Texture};
+use ray_tracing::models::point::Point;
+use ray_tracing::models::ray::Ray;
+use ray_tracing::models::sphere::Sphere;
+use ray_tracing::models::surface::Surface;
+use ray_tracing::models::triangle::Triangle;
+use ray_tracing::models::vector::Vector;
+use ray_tracing::services::obj_parser_service::read_obj_file;
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+const NORMAL: Vector = Vector { x: 0.0, y: 0.0, z: 1.0 };
+/// Tangents of a surface in the xy plane, with u along x and v along y
+const TANGENTS: (Vector, Vector) = (Vector { x: 1.0, y: 0.0, z: 0.0 }, Vector { x: 0.0, y: 1.0, z: 0.0 });
+
+const LIGHT: &str = r#"
+    <point_light>
+        <color r="1.0" g="1.0" b="1.0"/>
+        <position x="2.0" y="2.0" z="0.0"/>
+    </point_light>
+"#;
+
+/// Texture from the pixels of a single row
+fn texture(pixels: &[[u8; 3]]) -> Texture {
+    let mut data = image::RgbImage::new(pixels.len() as u32, 1);
+    for (x, pixel) in pixels.iter().enumerate() {
+        data.put_pixel(x as u32, 0, image::Rgb(*pixel));
+    }
+    Texture { name: String::from("test.png"), data: Some(Arc::new(data)) }
+}
+
+fn assert_close(actual: Vector, expected: Vector) {
+    assert!((actual - expected).length() < 1e-9, "{:?} instead of {:?}", actual, expected);
+}
+
+#[test]
+fn test_parse_maps() {
+    let xml_data = r#"
+        <material_solid>
+            <color r="0.25" g="0.18" b="0.50"/>
+            <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+            <reflectance r="0.0"/>
+            <transmittance t="0.0"/>
+            <refraction iof="1.0"/>
+            <normal_map strength="0.5">
+                <texture name="bricks_normal.png"/>
+            </normal_map>
+            <bump_map>
+                <texture name="Brick.png"/>
+            </bump_map>
+        </material_solid>
+    "#;
+    let material: MaterialSolid = from_str(xml_data).expect("Failed to parse MaterialSolid");
+
+    let normal_map = material.normal_map.expect("Normal map should be parsed");
+    assert_eq!((normal_map.texture.name.as_str(), normal_map.strength), ("bricks_normal.png", 0.5));
+    let bump_map = material.bump_map.expect("Bump map should be parsed");
+    assert_eq!((bump_map.texture.name.as_str(), bump_map.height), ("Brick.png", 0.02));
+}
+
+#[test]
+fn test_triangle_tangents() {
+    let vertices = [Point::new(0.0, 0.0, 0.0), Point::new(2.0, 0.0, 0.0), Point::new(0.0, 4.0, 0.0)];
+    let (dpdu, dpdv) = Triangle::tangents(vertices, [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]).expect("Tangents");
+    assert_close(dpdu, Vector::new(2.0, 0.0, 0.0));
+    assert_close(dpdv, Vector::new(0.0, 4.0, 0.0));
+
+    // Mirrored texture coordinates turn the bitangent around
+    let (_, dpdv) = Triangle::tangents(vertices, [(0.0, 1.0), (1.0, 1.0), (0.0, 0.0)]).expect("Tangents");
+    assert_close(dpdv, Vector::new(0.0, -4.0, 0.0));
+
+    assert_eq!(Triangle::tangents(vertices, [(0.5, 0.5); 3]), None, "The texture coordinates span no area");
+}
+
+#[test]
+fn test_obj_triangles_have_tangents() {
+    let triangles = read_obj_file("assets/obj_models/box.obj").unwrap().to_triangles().unwrap();
+    assert!(!triangles.is_empty());
+    for triangle in triangles {
+        let (dpdu, dpdv) = triangle.tangents.expect("The box has texture coordinates");
+        let normal = (triangle.v1 - triangle.v0).cross(triangle.v2 - triangle.v0).normalize();
+        assert!(dpdu.dot(normal).abs() < 1e-5 && dpdv.dot(normal).abs() < 1e-5, "Tangents lie in the triangle");
+    }
+}
+
+#[test]
+fn test_sphere_tangents() {
+    let sphere: Sphere = from_str(common::SPHERE).expect("Failed to parse Sphere");
+    let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0), 1e-6, f64::INFINITY);
+    let intersection = sphere.intersect(&ray).expect("The ray hits the sphere");
+
+    let (dpdu, dpdv) = intersection.tangents.expect("Spheres have tangents");
+    assert!(dpdu.dot(intersection.normal).abs() < 1e-9 && dpdv.dot(intersection.normal).abs() < 1e-9);
+    assert!(dpdv.y > 0.0, "v grows towards the top of the sphere");
+    assert!((dpdu.length() - 2.0 * std::f64::consts::PI).abs() < 1e-9, "u goes once around the sphere");
+}
+
+#[test]
+fn test_normal_map() {
+    let flat = NormalMap { texture: texture(&[[128, 128, 255]]), strength: 1.0 };
+    assert!((flat.perturb(NORMAL, TANGENTS, (0.5, 0.5)) - NORMAL).length() < 0.01);
+
+    // Red tilts the normal towards the tangent, green towards the bitangent
+    let tilted = NormalMap { texture: texture(&[[255, 128, 128]]), strength: 1.0 };
+    let normal = tilted.perturb(NORMAL, TANGENTS, (0.5, 0.5));
+    assert!(normal.x > 0.7 && normal.y.abs() < 0.01, "{:?}", normal);
+    assert!((normal.length() - 1.0).abs() < 1e-9);
+
+    let half = NormalMap { strength: 0.5, ..tilted.clone() }.perturb(NORMAL, TANGENTS, (0.5, 0.5));
+    assert!(half.x > 0.0 && half.x < normal.x, "Less strength tilts less");
+    let none = NormalMap { strength: 0.0, ..tilted }.perturb(NORMAL, TANGENTS, (0.5, 0.5));
+    assert_close(none, NORMAL);
+
+    let unloaded = NormalMap { texture: Texture { name: String::from("test.png"), data: None }, strength: 1.0 };
+    assert_eq!(unloaded.perturb(NORMAL, TANGENTS, (0.5, 0.5)), NORMAL);
+}
+
+#[test]
+fn test_bump_map() {
+    let flat = BumpMap { texture: texture(&[[100, 100, 100]; 4]), height: 0.1 };
+    assert_close(flat.perturb(NORMAL, TANGENTS, (0.5, 0.5)), NORMAL);
+
+    // Heights rising with u tilt the normal against the tangent
+    let ramp = BumpMap { texture: texture(&[[0, 0, 0], [85, 85, 85], [170, 170, 170], [255, 255, 255]]), height: 0.1 };
+    let normal = ramp.perturb(NORMAL, TANGENTS, (0.5, 0.5));
+    assert!(normal.x < -0.05 && normal.y.abs() < 1e-9 && normal.z > 0.9, "{:?}", normal);
+    assert!((normal.length() - 1.0).abs() < 1e-9);
+
+    let higher = BumpMap { height: 0.2, ..ramp }.perturb(NORMAL, TANGENTS, (0.5, 0.5));
+    assert!(higher.x < normal.x, "Higher bumps tilt more");
+}
+
+#[test]
+fn test_render_bump_map() {
+    let render = |maps: &str| {
+        let sphere = common::SPHERE.replace("</material_solid>", &format!("{}</material_solid>", maps));
+        let mut scene = common::create_scene((16, 12), "", LIGHT, &sphere, "");
+        scene.load_meshes().expect("Failed to load the scene");
+        RenderService::render_to_buffer(&scene, false).color
+    };
+
+    let smooth = render("");
+    assert_ne!(smooth, render(r#"<bump_map height="0.05"><texture name="Brick.png"/></bump_map>"#));
+    assert_eq!(smooth, render(r#"<normal_map strength="0.0"><texture name="Brick.png"/></normal_map>"#));
+}
@@ -0,0 +1,29 @@
+use std::fs;
+use std::path::PathBuf;
+use ray_tracing::services::obj_parser_service::read_obj_file;
+
+fn write_obj(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("ray_tracing_{}_{}", std::process::id(), name));
+    fs::write(&path, content).expect("Failed to write OBJ");
+    path
+}
+
+#[test]
+fn test_faces_without_texture_coordinates() {
+    let path = write_obj("no_uv.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.5 0.5\nf 1 2 3\nf 1/1 2/1 3/1\n");
+    let model = read_obj_file(&path).expect("Failed to read OBJ");
+    fs::remove_file(&path).ok();
+
+    let triangles = model.to_triangles().expect("Failed to convert OBJ");
+    assert_eq!(triangles[0].uvs, None, "A face without texture indices has no texture coordinates");
+    assert_eq!(triangles[1].uvs, Some([(0.5, 0.5); 3]));
+}
+
+#[test]
+fn test_missing_texture_coordinate_is_an_error() {
+    let path = write_obj("bad_uv.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.5 0.5\nf 1/1 2/2 3/1\n");
+    let model = read_obj_file(&path).expect("Failed to read OBJ");
+    fs::remove_file(&path).ok();
+
+    assert!(model.to_triangles().is_err(), "Texture coordinate 2 does not exist");
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use std::sync::Arc;
+use ray_tracing::models::bsdf::Bsdf;
+use ray_tracing::models::color::Color;
+use ray_tracing::models::material::{Material, Texture};
+use ray_tracing::models::principled::{MaterialPrincipled, Principled};
+use ray_tracing::models::vector::Vector;
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+fn principled() -> Principled {
+    Principled {
+        base_color: Color::new(0.8, 0.5, 0.2),
+        metallic: 0.0,
+        roughness: 0.5,
+        specular: 0.5,
+        clearcoat: 0.0,
+        clearcoat_roughness: 0.1,
+        sheen: 0.0,
+        transmission: 0.0,
+        ior: 1.5,
+    }
+}
+
+#[test]
+fn test_parse_principled() {
+    let xml_data = r#"
+        <material_principled metallic="1.0" roughness="0.2" clearcoat="0.5">
+            <base_color r="0.9" g="0.6" b="0.3"/>
+            <roughness_map name="roughness.png"/>
+        </material_principled>
+    "#;
+    let material: MaterialPrincipled = from_str(xml_data).expect("Failed to parse MaterialPrincipled");
+
+    assert_eq!(material.base_color, Color::new(0.9, 0.6, 0.3));
+    assert_eq!((material.metallic, material.roughness, material.clearcoat), (1.0, 0.2, 0.5));
+    assert_eq!((material.specular, material.sheen, material.transmission, material.ior), (0.5, 0.0, 0.0, 1.5));
+    assert_eq!(material.roughness_map.map(|texture| texture.name), Some(String::from("roughness.png")));
+    assert!(material.base_color_map.is_none());
+}
+
+#[test]
+fn test_maps_multiply_parameters() {
+    let mut material: MaterialPrincipled = from_str(r#"
+        <material_principled roughness="0.8">
+            <base_color r="1.0" g="0.5" b="1.0"/>
+        </material_principled>
+    "#).expect("Failed to parse MaterialPrincipled");
+
+    let mut data = image::RgbImage::new(2, 1);
+    data.put_pixel(0, 0, image::Rgb([255, 255, 255]));
+    data.put_pixel(1, 0, image::Rgb([0, 0, 0]));
+    material.roughness_map = Some(Texture { name: String::from("test.png"), data: Some(Arc::new(data.clone())) });
+    data.put_pixel(0, 0, image::Rgb([255, 0, 255]));
+    material.base_color_map = Some(Texture { name: String::from("test.png"), data: Some(Arc::new(data)) });
+
+    let left = material.at((0.25, 0.5));
+    assert_eq!(left.roughness, 0.8);
+    assert_eq!(left.base_color, Color::new(1.0, 0.0, 1.0));
+    let right = material.at((0.75, 0.5));
+    assert_eq!(right.roughness, 0.0);
+    assert_eq!(right.base_color, Color::BLACK);
+
+    let material = Material::Principled(Box::new(material));
+    assert_eq!(material.color_at((0.25, 0.5)), Color::new(1.0, 0.0, 1.0));
+}
+
+#[test]
+fn test_sampling_matches_evaluation() {
+    let normal = Vector::new(0.0, 0.0, 1.0);
+    let outgoing = Vector::new(0.0, 0.6, 0.8);
+    let variants = [
+        principled(),
+        Principled { metallic: 1.0, roughness: 0.3, ..principled() },
+        Principled { clearcoat: 1.0, clearcoat_roughness: 0.3, sheen: 1.0, ..principled() },
+        Principled { metallic: 0.5, transmission: 0.5, roughness: 0.4, ..principled() },
+    ];
+
+    for material in variants {
+        common::check_sampling(&material, outgoing, normal);
+    }
+    // Leaving a transmissive material
+    let glass = Principled { transmission: 1.0, roughness: 0.3, ..principled() };
+    common::check_sampling(&glass, Vector::new(0.0, 0.6, -0.8), normal);
+}
+
+#[test]
+fn test_energy_conservation() {
+    let normal = Vector::new(0.0, 0.0, 1.0);
+    let white = Principled { base_color: Color::WHITE, ..principled() };
+    let variants = [
+        white,
+        Principled { roughness: 1.0, sheen: 1.0, ..white },
+        Principled { roughness: 0.0, clearcoat: 1.0, clearcoat_roughness: 0.0, ..white },
+        Principled { metallic: 1.0, roughness: 0.7, ..white },
+        Principled { specular: 1.0, clearcoat: 1.0, sheen: 1.0, roughness: 0.2, ..white },
+    ];
+
+    for material in variants {
+        for cos in [1.0_f64, 0.5, 0.1] {
+            let outgoing = Vector::new((1.0 - cos * cos).sqrt(), 0.0, cos);
+            let albedo = common::check_sampling(&material, outgoing, normal);
+            assert!(albedo.r <= 1.01 && albedo.g <= 1.01 && albedo.b <= 1.01, "{:?} reflects {:?}", material, albedo);
+        }
+    }
+
+    // A white diffuse material loses little light
+    let albedo = common::check_sampling(&Principled { roughness: 1.0, ..white }, normal, normal);
+    assert!(albedo.r > 0.9, "Reflects {:?}", albedo);
+}
+
+#[test]
+fn test_metal_takes_base_color() {
+    let gold = Principled { metallic: 1.0, roughness: 0.0, ..principled() };
+    let normal = Vector::new(0.0, 0.0, 1.0);
+    let samples = gold.sample_lobes(normal, normal, (0.5, 0.5));
+
+    assert_eq!(samples.len(), 1);
+    assert!(samples[0].specular);
+    assert_eq!(samples[0].direction, normal);
+    let weight = samples[0].weight;
+    assert!((weight.r - 0.8).abs() < 1e-9 && (weight.g - 0.5).abs() < 1e-9 && (weight.b - 0.2).abs() < 1e-9);
+}
+
+#[test]
+fn test_render_diffuse_principled() {
+    let sphere = r#"
+        <sphere radius="1.0">
+            <position x="0.0" y="0.0" z="-3.0"/>
+            <material_principled specular="0.0">
+                <base_color r="0.8" g="0.5" b="0.2"/>
+            </material_principled>
+        </sphere>
+    "#;
+    let scene = common::create_scene((21, 11), "", common::AMBIENT_LIGHT, sphere, "");
+    let image = RenderService::render_to_buffer(&scene, false).color;
+
+    // Without specular reflection the ambient light shows the base color
+    let center = image.get_pixel(10, 5).0;
+    for (value, expected) in center.into_iter().zip([0.8, 0.5, 0.2]) {
+        assert!((value - expected).abs() < 1e-3, "Rendered {:?}", center);
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::Duration;
+use ray_tracing::models::progress::{CancelToken, Progress};
+use ray_tracing::models::scene::Scene;
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+const SCENE: &str = r#"
+    <scene output_file="progress.png">
+        <background_color r="0.0" g="0.0" b="0.0"/>
+        <camera>
+            <position x="0.0" y="0.0" z="1.0"/>
+            <lookat x="0.0" y="0.0" z="-2.5"/>
+            <up x="0.0" y="1.0" z="0.0"/>
+            <horizontal_fov angle="45"/>
+            <resolution horizontal="8" vertical="6"/>
+            <max_bounces n="8"/>
+            <samples n="3"/>
+        </camera>
+        <lights>
+            <point_light>
+                <color r="1.0" g="1.0" b="1.0"/>
+                <position x="0.0" y="3.0" z="0.0"/>
+            </point_light>
+        </lights>
+        <surfaces>
+            <sphere radius="1.0">
+                <position x="0.0" y="0.0" z="-3.0"/>
+                <material_solid>
+                    <color r="0.95" g="0.63" b="0.01"/>
+                    <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+                    <reflectance r="0.0"/>
+                    <transmittance t="0.0"/>
+                    <refraction iof="2.3"/>
+                </material_solid>
+            </sphere>
+        </surfaces>
+    </scene>
+"#;
+
+#[test]
+fn test_progress_is_reported_for_every_row() {
+    let scene: Scene = from_str(SCENE).expect("Failed to parse Scene");
+    let mut reports: Vec<Progress> = Vec::new();
+
+    let framebuffer = RenderService::render_with_progress(&scene, &mut |p| reports.push(*p), &CancelToken::new())
+        .expect("Render was not cancelled");
+
+    // Six rows in each of three passes
+    assert_eq!(reports.len(), 18);
+    assert!(reports.windows(2).all(|w| w[1].pixels_done == w[0].pixels_done + 8));
+
+    let last = reports.last().unwrap();
+    assert_eq!(last.pixels_done, last.pixels_total);
+    assert_eq!(last.pixels_total, 8 * 6 * 3);
+    assert_eq!((last.passes_done, last.passes_total), (3, 3));
+    assert!(last.rays >= last.pixels_total, "Every pixel sample traces at least one ray");
+    assert_eq!(reports[6].passes_done, 1);
+
+    assert_eq!(RenderService::to_image(&framebuffer), RenderService::render_image(&scene));
+}
+
+#[test]
+fn test_cancel_stops_render() {
+    let scene: Scene = from_str(SCENE).expect("Failed to parse Scene");
+    let cancel = CancelToken::new();
+    let handle = cancel.clone();
+    let mut rows = 0;
+
+    let result = RenderService::render_with_progress(
+        &scene,
+        &mut |_| {
+            rows += 1;
+            if rows == 2 {
+                handle.cancel();
+            }
+        },
+        &cancel,
+    );
+
+    assert!(result.is_none(), "Cancelled render should not return an image");
+    assert_eq!(rows, 2, "Render should stop right after cancelling");
+    assert!(cancel.is_cancelled());
+}
+
+#[test]
+fn test_cancel_stops_render_in_passes() {
+    let xml_data = SCENE.replace("</surfaces>", r#"</surfaces><progressive passes="1"/>"#);
+    let scene: Scene = from_str(&xml_data).expect("Failed to parse Scene");
+    let cancel = CancelToken::new();
+    let handle = cancel.clone();
+    let mut reports = Vec::new();
+
+    // Cancelled in the first pass, before anything is written
+    let finished = RenderService::generate_in_passes(
+        &scene,
+        &mut |p| {
+            reports.push(*p);
+            if reports.len() == 2 {
+                handle.cancel();
+            }
+        },
+        &cancel,
+    );
+
+    assert!(!finished, "Cancelled render should not finish");
+    assert_eq!(reports.len(), 2, "Render should stop right after cancelling");
+    assert_eq!(reports[1].pixels_total, 8 * 6 * 3);
+}
+
+#[test]
+fn test_progress_estimates() {
+    let progress = Progress {
+        pixels_done: 25,
+        pixels_total: 100,
+        passes_done: 0,
+        passes_total: 1,
+        elapsed: Duration::from_secs(10),
+        rays: 5_000,
+    };
+
+    assert_eq!(progress.fraction(), 0.25);
+    assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+    assert_eq!(progress.rays_per_second(), 500.0);
+
+    let started = Progress { pixels_done: 0, elapsed: Duration::ZERO, ..progress };
+    assert_eq!(started.eta(), None);
+    assert_eq!(started.rays_per_second(), 0.0);
+
+    // Adaptive sampling can take more samples than estimated
+    let overrun = Progress { pixels_done: 120, ..progress };
+    assert_eq!(overrun.eta(), Some(Duration::ZERO));
+}
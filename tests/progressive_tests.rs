@@ -0,0 +1,92 @@
+use ray_tracing::models::color::Color;
+use ray_tracing::models::framebuffer::Framebuffer;
+use ray_tracing::models::progressive::Progressive;
+use ray_tracing::models::scene::Scene;
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+const SCENE: &str = r#"
+    <scene output_file="progressive.png">
+        <background_color r="0.1" g="0.2" b="0.3"/>
+        <camera>
+            <position x="0.0" y="0.0" z="1.0"/>
+            <lookat x="0.0" y="0.0" z="-2.5"/>
+            <up x="0.0" y="1.0" z="0.0"/>
+            <horizontal_fov angle="45"/>
+            <resolution horizontal="16" vertical="16"/>
+            <max_bounces n="8"/>
+            <samples n="4"/>
+        </camera>
+        <lights>
+            <ambient_light>
+                <color r="1.0" g="1.0" b="1.0"/>
+            </ambient_light>
+        </lights>
+        <surfaces>
+            <sphere radius="1.0">
+                <position x="0.0" y="0.0" z="-3.0"/>
+                <material_solid>
+                    <color r="0.95" g="0.63" b="0.01"/>
+                    <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+                    <reflectance r="0.0"/>
+                    <transmittance t="0.0"/>
+                    <refraction iof="2.3"/>
+                </material_solid>
+            </sphere>
+        </surfaces>
+        <progressive seconds="5" passes="2" time_limit="60"/>
+    </scene>
+"#;
+
+#[test]
+fn test_parse_progressive() {
+    let scene: Scene = from_str(SCENE).expect("Failed to parse Scene");
+    let progressive = scene.progressive.expect("Progressive settings should be parsed");
+
+    assert_eq!(progressive.seconds, Some(5.0));
+    assert_eq!(progressive.passes, Some(2));
+    assert_eq!(progressive.time_limit, Some(60.0));
+}
+
+#[test]
+fn test_progressive_write_and_time_limit() {
+    let progressive = Progressive { seconds: Some(5.0), passes: Some(3), time_limit: Some(10.0) };
+
+    assert!(!progressive.should_write(1, 1.0));
+    assert!(progressive.should_write(3, 1.0), "Every third pass should be written");
+    assert!(progressive.should_write(4, 6.0), "Image should be written after five seconds");
+    assert!(!progressive.out_of_time(9.0));
+    assert!(progressive.out_of_time(10.0));
+
+    let never = Progressive::default();
+    assert!(!never.should_write(100, 1000.0));
+    assert!(!never.out_of_time(1000.0));
+}
+
+#[test]
+fn test_framebuffer_accumulates_samples() {
+    let mut framebuffer = Framebuffer::new(2, 2);
+
+    assert_eq!(framebuffer.color(1, 1), Color::BLACK, "Pixels without samples are black");
+
+    framebuffer.add_sample(1, 0, Color::new(1.0, 0.0, 0.0));
+    framebuffer.add_sample(1, 0, Color::new(0.0, 0.0, 1.0));
+
+    assert_eq!(framebuffer.samples[framebuffer.index(1, 0)], 2);
+    assert_eq!(framebuffer.color(1, 0), Color::new(0.5, 0.0, 0.5));
+}
+
+#[test]
+fn test_passes_match_full_render() {
+    let scene: Scene = from_str(SCENE).expect("Failed to parse Scene");
+
+    let mut framebuffer = Framebuffer::new(16, 16);
+    for _ in 0..4 {
+        RenderService::render_pass(&scene, &mut framebuffer);
+    }
+
+    assert!(framebuffer.samples.iter().all(|&n| n == 4), "Every pass adds one sample per pixel");
+    assert_eq!(RenderService::to_image(&framebuffer), RenderService::render_image(&scene));
+    // Corner pixels only see the background
+    assert_eq!(framebuffer.color(0, 0), Color::new(0.1, 0.2, 0.3));
+}
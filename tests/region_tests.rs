@@ -0,0 +1,55 @@
+mod common;
+
+use image::{imageops, Rgb};
+use ray_tracing::models::camera::{Region, Resolution};
+use ray_tracing::models::scene::Scene;
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+fn create_scene(region: &str) -> Scene {
+    let camera = format!(r#"<samples n="4"/><lens aperture="0.1" focus_distance="2.0"/>{}"#, region);
+    common::create_scene((24, 16), &camera, common::AMBIENT_LIGHT, common::SPHERE, "")
+}
+
+#[test]
+fn test_region_pixels() {
+    let resolution = Resolution { horizontal: 200, vertical: 100 };
+
+    let rect: Region = from_str(r#"<region x="10" y="20" width="30" height="40" crop="true"/>"#)
+        .expect("Failed to parse Region");
+    assert!(rect.crop);
+    assert_eq!(rect.pixels(&resolution), (10, 20, 30, 40));
+
+    let window: Region = from_str(r#"<region left="0.25" top="0.5" right="0.75"/>"#)
+        .expect("Failed to parse Region");
+    assert!(!window.crop);
+    assert_eq!(window.pixels(&resolution), (50, 50, 100, 50));
+
+    // Regions are limited to the image
+    let outside = Region { x: 190, y: 0, width: Some(50), height: Some(500), ..Default::default() };
+    assert_eq!(outside.pixels(&resolution), (190, 0, 10, 100));
+}
+
+#[test]
+fn test_cropped_region_matches_full_render() {
+    let full = RenderService::render_image(&create_scene(""));
+    let crop = RenderService::render_image(&create_scene(r#"<region x="6" y="4" width="10" height="7" crop="true"/>"#));
+
+    assert_eq!(crop.dimensions(), (10, 7));
+    assert_eq!(crop, imageops::crop_imm(&full, 6, 4, 10, 7).to_image());
+}
+
+#[test]
+fn test_uncropped_region_keeps_background() {
+    let full = RenderService::render_image(&create_scene(""));
+    let scene = create_scene(r#"<region left="0.25" top="0.25" right="0.75" bottom="0.75"/>"#);
+    let img = RenderService::render_image(&scene);
+
+    assert_eq!(img.dimensions(), (24, 16));
+    assert_eq!(*img.get_pixel(12, 8), *full.get_pixel(12, 8), "Region should be rendered");
+    assert_eq!(*img.get_pixel(20, 8), Rgb([0, 0, 255]), "Outside of the region only the background is shown");
+
+    let buffers = RenderService::render_to_buffer(&scene, false);
+    assert_eq!(buffers.color.dimensions(), (24, 16));
+    assert_eq!(*buffers.color.get_pixel(2, 2), Rgb([0.0, 0.0, 1.0]));
+}
@@ -0,0 +1,64 @@
+use image::Rgb;
+use ray_tracing::models::scene::Scene;
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+const SCENE: &str = r#"
+    <scene output_file="never_written.png">
+        <background_color r="0.25" g="0.5" b="2.0"/>
+        <camera>
+            <position x="0.0" y="0.0" z="1.0"/>
+            <lookat x="0.0" y="0.0" z="-2.5"/>
+            <up x="0.0" y="1.0" z="0.0"/>
+            <horizontal_fov angle="45"/>
+            <resolution horizontal="20" vertical="10"/>
+            <max_bounces n="8"/>
+        </camera>
+        <lights>
+            <ambient_light>
+                <color r="1.0" g="1.0" b="1.0"/>
+            </ambient_light>
+        </lights>
+        <surfaces>
+            <sphere radius="1.0">
+                <position x="0.0" y="0.0" z="-3.0"/>
+                <material_solid>
+                    <color r="0.95" g="0.63" b="0.01"/>
+                    <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+                    <reflectance r="0.0"/>
+                    <transmittance t="0.0"/>
+                    <refraction iof="2.3"/>
+                </material_solid>
+            </sphere>
+        </surfaces>
+    </scene>
+"#;
+
+#[test]
+fn test_render_to_buffer() {
+    let scene: Scene = from_str(SCENE).expect("Failed to parse Scene");
+
+    let buffers = RenderService::render_to_buffer(&scene, true);
+
+    assert_eq!(buffers.color.dimensions(), (20, 10));
+    // Float colors are not clamped
+    assert_eq!(*buffers.color.get_pixel(0, 0), Rgb([0.25, 0.5, 2.0]));
+
+    let rgb = buffers.rgb.expect("8-bit image was requested");
+    assert_eq!(rgb, RenderService::render_image(&scene));
+    assert_eq!(*rgb.get_pixel(0, 0), Rgb([63, 127, 255]));
+    // The sphere is hit in the image center
+    assert_ne!(*rgb.get_pixel(10, 5), *rgb.get_pixel(0, 0));
+
+    assert!(!std::path::Path::new("output/never_written.png").exists(), "Nothing should be written to disk");
+}
+
+#[test]
+fn test_render_to_buffer_without_rgb() {
+    let scene: Scene = from_str(SCENE).expect("Failed to parse Scene");
+
+    let buffers = RenderService::render_to_buffer(&scene, false);
+
+    assert!(buffers.rgb.is_none());
+    assert_eq!(buffers.color.dimensions(), (20, 10));
+}
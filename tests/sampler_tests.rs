@@ -0,0 +1,137 @@
+mod common;
+
+use ray_tracing::models::sampler::{Sampler, SamplerSettings, SamplerType};
+use ray_tracing::models::scene::Scene;
+use ray_tracing::services::render_service::RenderService;
+
+const ALL_TYPES: [SamplerType; 5] = [
+    SamplerType::Independent,
+    SamplerType::Stratified,
+    SamplerType::Halton,
+    SamplerType::Sobol,
+    SamplerType::BlueNoise,
+];
+
+fn create_sampler(kind: SamplerType, samples: u32) -> Box<dyn Sampler> {
+    SamplerSettings { kind, seed: 0 }.create(samples)
+}
+
+/// Takes the first `count` two dimensional samples of the pixel, skipping `skip` dimension pairs first
+fn samples_2d(sampler: &mut dyn Sampler, x: u32, y: u32, count: u32, skip: u32) -> Vec<(f64, f64)> {
+    (0..count)
+        .map(|index| {
+            sampler.start_sample(x, y, index);
+            for _ in 0..skip {
+                sampler.next_2d();
+            }
+            sampler.next_2d()
+        })
+        .collect()
+}
+
+fn create_scene(sampler: &str) -> Scene {
+    let camera = format!(r#"<samples n="8"/><lens aperture="0.2" focus_distance="4.0"/>{}"#, sampler);
+    common::create_scene((16, 12), &camera, common::AMBIENT_LIGHT, common::SPHERE, "")
+}
+
+#[test]
+fn test_parse_sampler() {
+    let scene = create_scene(r#"<sampler type="bluenoise" seed="3"/>"#);
+    assert_eq!(scene.camera.sampler, SamplerSettings { kind: SamplerType::BlueNoise, seed: 3 });
+
+    let scene = create_scene("");
+    assert_eq!(scene.camera.sampler.kind, SamplerType::Independent);
+}
+
+#[test]
+fn test_samples_are_reproducible_in_any_order() {
+    for kind in ALL_TYPES {
+        let mut sampler = create_sampler(kind, 16);
+        let forward: Vec<f64> = (0..16)
+            .map(|index| {
+                sampler.start_sample(5, 7, index);
+                sampler.next_1d()
+            })
+            .collect();
+
+        // Other pixels in between and a new sampler must not change the values
+        let mut other = create_sampler(kind, 16);
+        for index in (0..16).rev() {
+            other.start_sample(6, 7, index);
+            other.next_2d();
+            other.start_sample(5, 7, index);
+            let value = other.next_1d();
+            assert_eq!(value, forward[index as usize], "{:?} sample {}", kind, index);
+            assert!((0.0..1.0).contains(&value), "{:?} value {} out of range", kind, value);
+        }
+    }
+}
+
+#[test]
+fn test_stratified_samples_fill_grid_cells() {
+    for kind in [SamplerType::Stratified, SamplerType::Sobol, SamplerType::BlueNoise] {
+        let mut sampler = create_sampler(kind, 16);
+        for skip in 0..3 {
+            let mut cells: Vec<usize> = samples_2d(sampler.as_mut(), 3, 4, 16, skip)
+                .into_iter()
+                .map(|(x, y)| (y * 4.0) as usize * 4 + (x * 4.0) as usize)
+                .collect();
+            cells.sort();
+            cells.dedup();
+            assert_eq!(cells.len(), 16, "{:?} leaves cells empty in dimension pair {}", kind, skip);
+        }
+    }
+}
+
+#[test]
+fn test_low_discrepancy_samplers_converge_faster() {
+    // Integral of x * y over the unit square is 0.25
+    let error = |kind: SamplerType| {
+        let mut sampler = create_sampler(kind, 16);
+        let squared_error: f64 = (0..64)
+            .map(|pixel| {
+                let samples = samples_2d(sampler.as_mut(), pixel % 8, pixel / 8, 16, 0);
+                let estimate = samples.iter().map(|(x, y)| x * y).sum::<f64>() / 16.0;
+                (estimate - 0.25).powi(2)
+            })
+            .sum();
+        (squared_error / 64.0).sqrt()
+    };
+
+    let independent = error(SamplerType::Independent);
+    for kind in [SamplerType::Stratified, SamplerType::Halton, SamplerType::Sobol, SamplerType::BlueNoise] {
+        assert!(error(kind) < independent * 0.5, "{:?}: {} vs {}", kind, error(kind), independent);
+    }
+}
+
+#[test]
+fn test_blue_noise_errors_cancel_between_neighbors() {
+    // Error of the pixels with 4 samples each after averaging blocks of 4 x 4 pixels, as the eye does
+    let block_error = |kind: SamplerType| {
+        let mut sampler = create_sampler(kind, 4);
+        let mut squared_error = 0.0;
+        for block in 0..64 {
+            let (left, top) = (block % 8 * 4, block / 8 * 4);
+            let mut sum = 0.0;
+            for pixel in 0..16 {
+                let samples = samples_2d(sampler.as_mut(), left + pixel % 4, top + pixel / 4, 4, 0);
+                sum += samples.iter().map(|(x, y)| x * y).sum::<f64>() / 4.0;
+            }
+            squared_error += (sum / 16.0 - 0.25).powi(2);
+        }
+        (squared_error / 64.0).sqrt()
+    };
+
+    let blue_noise = block_error(SamplerType::BlueNoise);
+    assert!(blue_noise < block_error(SamplerType::Sobol) * 0.5);
+}
+
+#[test]
+fn test_seed_changes_render() {
+    let first = RenderService::render_to_buffer(&create_scene(r#"<sampler type="sobol"/>"#), false).color;
+    let again = RenderService::render_to_buffer(&create_scene(r#"<sampler type="sobol"/>"#), false).color;
+    let seeded = RenderService::render_to_buffer(&create_scene(r#"<sampler type="sobol" seed="1"/>"#), false).color;
+
+    assert_eq!(first, again);
+    assert_ne!(first, seeded);
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use ray_tracing::models::material::MaterialSolid;
+use ray_tracing::models::shading::{DiffuseModel, Shading, SpecularModel};
+use ray_tracing::models::vector::Vector;
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+const NORMAL: Vector = Vector { x: 0.0, y: 0.0, z: 1.0 };
+
+fn direction(x: f64, y: f64, z: f64) -> Vector {
+    Vector::new(x, y, z).normalize()
+}
+
+fn specular(model: SpecularModel) -> Shading {
+    Shading { specular: model, ..Shading::default() }
+}
+
+#[test]
+fn test_parse_shading() {
+    let xml_data = r#"
+        <material_solid>
+            <color r="0.25" g="0.18" b="0.50"/>
+            <phong ka="0.3" kd="0.9" ks="1.0" exponent="200"/>
+            <reflectance r="0.0"/>
+            <transmittance t="0.0"/>
+            <refraction iof="2.3"/>
+            <shading diffuse="orennayar" specular="ward" roughness="0.5" roughness_y="0.1"/>
+        </material_solid>
+    "#;
+    let material: MaterialSolid = from_str(xml_data).expect("Failed to parse MaterialSolid");
+
+    assert_eq!(material.shading.diffuse, DiffuseModel::OrenNayar);
+    assert_eq!(material.shading.specular, SpecularModel::Ward);
+    assert_eq!(material.shading.roughness, 0.5);
+    assert_eq!((material.shading.roughness_x, material.shading.roughness_y), (None, Some(0.1)));
+
+    let plain: MaterialSolid = from_str(&xml_data.replace(r#"<shading diffuse="orennayar" specular="ward" roughness="0.5" roughness_y="0.1"/>"#, ""))
+        .expect("Failed to parse MaterialSolid");
+    assert_eq!(plain.shading, Shading::default());
+}
+
+#[test]
+fn test_default_is_lambert_and_phong() {
+    let shading = Shading::default();
+    let light = direction(0.3, 0.0, 1.0);
+    let view = direction(-0.2, 0.1, 1.0);
+
+    assert_eq!(shading.diffuse(NORMAL, light, view), light.z);
+    let reflection = (NORMAL * (2.0 * light.z) - light).normalize();
+    assert_eq!(shading.specular(NORMAL, light, view, 20.0, 1.5), view.dot(reflection).powf(20.0));
+    assert_eq!(shading.diffuse(NORMAL, -light, view), 0.0, "Light from behind");
+}
+
+#[test]
+fn test_oren_nayar() {
+    let light = direction(1.0, 0.0, 0.5);
+    let smooth = Shading { diffuse: DiffuseModel::OrenNayar, roughness: 0.0, ..Shading::default() };
+    assert!((smooth.diffuse(NORMAL, light, NORMAL) - light.z).abs() < 1e-12, "Without roughness it is Lambert");
+
+    // Rough surfaces reflect more back towards the light and less away from it
+    let rough = Shading { roughness: 0.5, ..smooth };
+    let lambert = Shading::default();
+    assert!(rough.diffuse(NORMAL, light, light) > lambert.diffuse(NORMAL, light, light) * 0.9);
+    let away = direction(-1.0, 0.0, 0.5);
+    assert!(rough.diffuse(NORMAL, light, away) < rough.diffuse(NORMAL, light, light));
+}
+
+#[test]
+fn test_blinn_phong_peaks_at_mirror_direction() {
+    let shading = specular(SpecularModel::BlinnPhong);
+    let light = direction(0.5, 0.0, 1.0);
+    let mirror = direction(-0.5, 0.0, 1.0);
+
+    assert!((shading.specular(NORMAL, light, mirror, 50.0, 1.5) - 1.0).abs() < 1e-12);
+    assert!(shading.specular(NORMAL, light, direction(-0.2, 0.3, 1.0), 50.0, 1.5) < 0.5);
+}
+
+#[test]
+fn test_microfacet_models_are_reciprocal() {
+    // The terms include the cosine to the light, the BRDFs themselves do not depend on the order
+    let a = direction(0.4, 0.2, 1.0);
+    let b = direction(-0.3, -0.1, 1.0);
+    let anisotropic = Shading { roughness_x: Some(0.1), roughness_y: Some(0.4), ..specular(SpecularModel::Ward) };
+
+    for shading in [specular(SpecularModel::CookTorrance), anisotropic] {
+        let forward = shading.specular(NORMAL, a, b, 1.0, 1.5) / a.z;
+        let backward = shading.specular(NORMAL, b, a, 1.0, 1.5) / b.z;
+        assert!(forward > 0.0);
+        assert!((forward - backward).abs() < 1e-9 * forward, "{:?}: {} vs {}", shading.specular, forward, backward);
+    }
+}
+
+#[test]
+fn test_ward_highlight_is_anisotropic() {
+    let shading = Shading { roughness_x: Some(0.05), roughness_y: Some(0.4), ..specular(SpecularModel::Ward) };
+    let light = direction(0.0, 0.0, 1.0);
+
+    // The tangent of the z normal runs along the x axis
+    let along_tangent = shading.specular(NORMAL, light, direction(0.3, 0.0, 1.0), 1.0, 1.5);
+    let along_bitangent = shading.specular(NORMAL, light, direction(0.0, 0.3, 1.0), 1.0, 1.5);
+    assert!(along_bitangent > along_tangent * 10.0, "{} vs {}", along_bitangent, along_tangent);
+}
+
+#[test]
+fn test_render_with_shading_model() {
+    let lights = r#"
+        <point_light>
+            <color r="1.0" g="1.0" b="1.0"/>
+            <position x="0.0" y="3.0" z="0.0"/>
+        </point_light>
+    "#;
+    let render = |shading: &str| {
+        let sphere = common::SPHERE.replace("</material_solid>", &format!("{}</material_solid>", shading));
+        let scene = common::create_scene((16, 12), "", lights, &sphere, "");
+        RenderService::render_to_buffer(&scene, false).color
+    };
+
+    let phong = render("");
+    assert_eq!(phong, render(r#"<shading diffuse="lambert" specular="phong"/>"#));
+    assert_ne!(phong, render(r#"<shading diffuse="orennayar" specular="cooktorrance"/>"#));
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use ray_tracing::services::render_service::RenderService;
+
+const LIGHT: &str = r#"
+    <point_light>
+        <color r="1.0" g="1.0" b="1.0"/>
+        <position x="-4.0" y="0.0" z="-1.0"/>
+    </point_light>
+"#;
+
+/// Diffuse white wall behind the center of the image
+const WALL: &str = r#"
+    <sphere radius="100.0">
+        <position x="0.0" y="0.0" z="-105.0"/>
+        <material_solid>
+            <color r="1.0" g="1.0" b="1.0"/>
+            <phong ka="0.0" kd="0.9" ks="0.0" exponent="1"/>
+            <reflectance r="0.0"/>
+            <transmittance t="0.0"/>
+            <refraction iof="1.0"/>
+        </material_solid>
+    </sphere>
+"#;
+
+fn solid_occluder(color: &str, t: f64) -> String {
+    format!(r#"
+        <material_solid>
+            <color {}/>
+            <phong ka="0.0" kd="0.9" ks="0.0" exponent="1"/>
+            <reflectance r="0.0"/>
+            <transmittance t="{}"/>
+            <refraction iof="1.5"/>
+        </material_solid>
+    "#, color, t)
+}
+
+/// Renders the wall with a sphere of the material between the light and the center of the wall,
+/// and returns the color of the wall in the center of the image
+fn wall_behind(material: &str) -> [f32; 3] {
+    let occluder = format!(r#"
+        <sphere radius="0.5">
+            <position x="-2.0" y="0.0" z="-3.0"/>
+            {}
+        </sphere>
+    "#, material);
+    let scene = common::create_scene((21, 11), "", LIGHT, &format!("{}{}", WALL, occluder), "");
+    RenderService::render_to_buffer(&scene, false).color.get_pixel(10, 5).0
+}
+
+fn unshadowed() -> [f32; 3] {
+    let scene = common::create_scene((21, 11), "", LIGHT, WALL, "");
+    RenderService::render_to_buffer(&scene, false).color.get_pixel(10, 5).0
+}
+
+fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+    for (a, e) in actual.into_iter().zip(expected) {
+        assert!((a - e).abs() < 1e-3, "{:?} instead of {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn test_opaque_objects_block_the_light() {
+    let lit = unshadowed();
+    assert!(lit[0] > 0.1, "The wall is lit, {:?}", lit);
+    assert_eq!(wall_behind(&solid_occluder(r#"r="1.0" g="1.0" b="1.0""#, 0.0)), [0.0; 3]);
+    assert_eq!(wall_behind(r#"<material_conductor><eta r="0.2" g="0.4" b="1.4"/><k r="3.9" g="2.4" b="1.9"/></material_conductor>"#), [0.0; 3]);
+}
+
+#[test]
+fn test_transmissive_objects_let_light_through() {
+    let lit = unshadowed();
+
+    // The shadow ray enters and leaves the sphere
+    let glass = wall_behind(&solid_occluder(r#"r="1.0" g="1.0" b="1.0""#, 0.9));
+    assert_close(glass, lit.map(|channel| channel * 0.81));
+
+    let tinted = wall_behind(&solid_occluder(r#"r="1.0" g="0.5" b="0.5""#, 1.0));
+    assert_close(tinted, [lit[0], lit[1] * 0.25, lit[2] * 0.25]);
+
+    assert_close(wall_behind(r#"<material_dielectric ior="1.5"/>"#), lit);
+}
+
+#[test]
+fn test_absorption_tints_shadows() {
+    let lit = unshadowed();
+    // The shadow ray travels a distance of 1 through the center of the sphere
+    let absorbing = wall_behind(r#"
+        <material_dielectric ior="1.5">
+            <absorption><color r="1.0" g="0.5" b="0.25"/></absorption>
+        </material_dielectric>
+    "#);
+    assert_close(absorbing, [lit[0], lit[1] * 0.5, lit[2] * 0.25]);
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use ray_tracing::models::material::{Material, MaterialDielectric, Refraction};
+use ray_tracing::models::random::Random;
+use ray_tracing::models::spectrum::{self, Cauchy, Sellmeier, Spectral};
+use ray_tracing::services::render_service::RenderService;
+use serde_xml_rs::from_str;
+
+/// Borosilicate crown glass, with an index of refraction of 1.5168 at the helium d-line
+const BK7: Sellmeier = Sellmeier {
+    b1: 1.03961212,
+    b2: 0.231792344,
+    b3: 1.01046945,
+    c1: 0.00600069867,
+    c2: 0.0200179144,
+    c3: 103.560653,
+};
+
+/// Glass sphere between the camera and the sphere of the test scene
+fn glass_in_front(dispersion: &str) -> String {
+    format!(r#"
+        <sphere radius="0.6">
+            <position x="0.3" y="0.0" z="-1.5"/>
+            <material_dielectric ior="1.5">{}</material_dielectric>
+        </sphere>
+        {}
+    "#, dispersion, common::SPHERE)
+}
+
+#[test]
+fn test_parse_dispersion() {
+    let glass: MaterialDielectric = from_str(r#"
+        <material_dielectric ior="1.5">
+            <cauchy a="1.5046" b="0.0042"/>
+        </material_dielectric>
+    "#).expect("Failed to parse MaterialDielectric");
+    assert_eq!(glass.cauchy, Some(Cauchy { a: 1.5046, b: 0.0042, c: 0.0 }));
+    assert!(glass.sellmeier.is_none());
+
+    let refraction: Refraction = from_str(r#"
+        <refraction iof="1.5">
+            <sellmeier b1="1.03961212" b2="0.231792344" b3="1.01046945" c1="0.00600069867" c2="0.0200179144" c3="103.560653"/>
+        </refraction>
+    "#).expect("Failed to parse Refraction");
+    assert_eq!(refraction.sellmeier, Some(BK7));
+}
+
+#[test]
+fn test_dispersion_models() {
+    let cauchy = Cauchy { a: 1.5046, b: 0.0042, c: 0.0 };
+    for ior in [cauchy.ior(587.6), BK7.ior(587.6)] {
+        assert!((ior - 1.5168).abs() < 1e-3, "Index at the d-line is {}", ior);
+    }
+    assert!(cauchy.ior(450.0) > cauchy.ior(650.0), "Blue light refracts more than red light");
+    assert!(BK7.ior(450.0) > BK7.ior(650.0), "Blue light refracts more than red light");
+
+    assert_eq!(spectrum::ior_at(&None, &None, 500.0), None);
+    assert_eq!(spectrum::ior_at(&Some(cauchy.clone()), &Some(BK7), 500.0), Some(cauchy.ior(500.0)), "Cauchy comes first");
+}
+
+#[test]
+fn test_disperse_material() {
+    let glass = MaterialDielectric { ior: 1.5, roughness: 0.0, absorption: None, cauchy: None, sellmeier: Some(BK7) };
+    let mut material = Material::Dielectric(glass.clone());
+    material.disperse(450.0);
+    assert_eq!(material, Material::Dielectric(MaterialDielectric { ior: BK7.ior(450.0), ..glass.clone() }));
+
+    let clear = Material::Dielectric(MaterialDielectric { ior: 1.5, sellmeier: None, ..glass });
+    let mut dispersed = clear.clone();
+    dispersed.disperse(450.0);
+    assert_eq!(dispersed, clear, "Without a dispersion model the index stays the same");
+}
+
+#[test]
+fn test_color_matching() {
+    let dominant = |wavelength: f64| {
+        let rgb = spectrum::wavelength_to_rgb(wavelength);
+        [rgb.r, rgb.g, rgb.b].iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0
+    };
+    assert_eq!((dominant(450.0), dominant(530.0), dominant(650.0)), (2, 1, 0));
+
+    let (_, y, _) = spectrum::cie_xyz(555.0);
+    assert!((y - 1.0).abs() < 0.02, "The eye is most sensitive to green light, y = {}", y);
+    let (x, y, z) = spectrum::cie_xyz(800.0);
+    assert!(x + y + z < 1e-3, "Infrared light is invisible");
+}
+
+#[test]
+fn test_weights_average_to_white() {
+    let mut random = Random::new(11, 0);
+    let count = 100000;
+    let mut sum = [0.0; 3];
+    for _ in 0..count {
+        let weight = Spectral::weight(Spectral::sample_wavelength(random.next_f64()));
+        assert!(weight.r >= 0.0 && weight.g >= 0.0 && weight.b >= 0.0);
+        sum = [sum[0] + weight.r, sum[1] + weight.g, sum[2] + weight.b];
+    }
+    for channel in sum {
+        assert!((channel / count as f64 - 1.0).abs() < 0.02, "Averages to {}", channel / count as f64);
+    }
+}
+
+#[test]
+fn test_render_spectral() {
+    let render = |camera: &str, surfaces: &str| {
+        let scene = common::create_scene((16, 12), camera, common::AMBIENT_LIGHT, surfaces, "");
+        RenderService::render_to_buffer(&scene, false).color
+    };
+    let mean_blue = |image: &image::Rgb32FImage| image.pixels().map(|pixel| pixel.0[2] as f64).sum::<f64>() / 192.0;
+
+    // Without dispersion the spectral render converges to the RGB render
+    let rgb = render(r#"<samples n="16"/>"#, common::SPHERE);
+    let spectral = render(r#"<samples n="16"/><spectral/>"#, common::SPHERE);
+    assert_ne!(rgb, spectral);
+    assert!((mean_blue(&rgb) - mean_blue(&spectral)).abs() < 0.05, "{} vs {}", mean_blue(&rgb), mean_blue(&spectral));
+
+    // Dispersion only changes spectral renders
+    let prism = glass_in_front(r#"<cauchy a="1.3" b="0.1"/>"#);
+    assert_eq!(render(r#"<samples n="4"/>"#, &prism), render(r#"<samples n="4"/>"#, &glass_in_front("")));
+    let dispersed = render(r#"<samples n="4"/><spectral/>"#, &prism);
+    assert_ne!(dispersed, render(r#"<samples n="4"/><spectral/>"#, &glass_in_front("")));
+    assert_eq!(dispersed, render(r#"<samples n="4"/><spectral/>"#, &prism), "Renders are reproducible");
+}
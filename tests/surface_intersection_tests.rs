@@ -1,5 +1,7 @@
+mod common;
+
 use ray_tracing::models::sphere::Sphere;
-use ray_tracing::models::material::{MaterialSolid, Phong, Reflectance, Transmittance, Refraction};
+use ray_tracing::models::material::{MaterialSolid, Reflectance};
 use ray_tracing::models::ray::Ray;
 use ray_tracing::models::point::Point;
 use ray_tracing::models::vector::Vector;
@@ -8,16 +10,8 @@ use ray_tracing::models::surface::Surface;
 
 fn create_test_material() -> MaterialSolid {
     MaterialSolid {
-        color: Color { r: 1.0, g: 0.0, b: 0.0 },
-        phong: Phong {
-            ka: 0.3,
-            kd: 0.7,
-            ks: 1.0,
-            exponent: 32.0,
-        },
-        reflectance: Reflectance { r: 0.5 },
-        transmittance: Transmittance { t: 0.0 },
-        refraction: Refraction { iof: 1.0 },
+        reflectance: Reflectance { r: 0.5, roughness: 0.0, samples: 1 },
+        ..common::create_test_material(Color { r: 1.0, g: 0.0, b: 0.0 })
     }
 }
 
@@ -26,7 +20,12 @@ fn test_sphere_intersection_hit() {
     let sphere = Sphere {
         radius: 1.0,
         position: Point::new(0.0, 0.0, -5.0),
-        material_solid: create_test_material(),
+        material_solid: Some(create_test_material()),
+        material_textured: None,
+        material_conductor: None,
+        material_dielectric: None,
+        material_principled: None,
+        motion: None,
     };
 
     let ray = Ray::new(
@@ -56,7 +55,7 @@ fn test_sphere_intersection_hit() {
 
     // Check material
     assert_eq!(
-        result.material.color,
+        result.material.color(),
         Color { r: 1.0, g: 0.0, b: 0.0 },
         "Incorrect material color"
     );
@@ -67,7 +66,12 @@ fn test_sphere_intersection_miss() {
     let sphere = Sphere {
         radius: 1.0,
         position: Point::new(0.0, 0.0, -5.0),
-        material_solid: create_test_material(),
+        material_solid: Some(create_test_material()),
+        material_textured: None,
+        material_conductor: None,
+        material_dielectric: None,
+        material_principled: None,
+        motion: None,
     };
 
     let ray = Ray::new(
@@ -86,7 +90,12 @@ fn test_sphere_intersection_inside() {
     let sphere = Sphere {
         radius: 1.0,
         position: Point::new(0.0, 0.0, -1.0),
-        material_solid: create_test_material(),
+        material_solid: Some(create_test_material()),
+        material_textured: None,
+        material_conductor: None,
+        material_dielectric: None,
+        material_principled: None,
+        motion: None,
     };
 
     let ray = Ray::new(
@@ -110,7 +119,12 @@ fn test_sphere_intersection_behind_ray() {
     let sphere = Sphere {
         radius: 1.0,
         position: Point::new(0.0, 0.0, 5.0),
-        material_solid: create_test_material(),
+        material_solid: Some(create_test_material()),
+        material_textured: None,
+        material_conductor: None,
+        material_dielectric: None,
+        material_principled: None,
+        motion: None,
     };
 
     let ray = Ray::new(
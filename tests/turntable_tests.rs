@@ -0,0 +1,73 @@
+use image::{Rgb, RgbImage};
+use ray_tracing::models::camera::Camera;
+use ray_tracing::models::point::Point;
+use ray_tracing::models::turntable::{Turntable, TurntableFormat};
+use ray_tracing::services::image_export_service::ImageExportService;
+use serde_xml_rs::from_str;
+
+fn create_camera() -> Camera {
+    let xml_data = r#"
+        <camera>
+            <position x="0.0" y="1.0" z="2.0"/>
+            <lookat x="0.0" y="0.0" z="-3.0"/>
+            <up x="0.0" y="1.0" z="0.0"/>
+            <horizontal_fov angle="45"/>
+            <resolution horizontal="512" vertical="512"/>
+            <max_bounces n="8"/>
+        </camera>
+    "#;
+    from_str(xml_data).expect("Failed to parse Camera")
+}
+
+#[test]
+fn test_parse_turntable() {
+    let turntable: Turntable = from_str(r#"<turntable frames="36" format="apng" contact_sheet="true"/>"#)
+        .expect("Failed to parse Turntable");
+
+    assert_eq!(turntable.frames, 36);
+    assert_eq!(turntable.format, TurntableFormat::Apng);
+    assert_eq!(turntable.delay, 100);
+    assert!(turntable.contact_sheet);
+    assert_eq!(turntable.sheet_columns(), 6);
+
+    let turntable: Turntable = from_str(r#"<turntable frames="10" columns="4"/>"#)
+        .expect("Failed to parse Turntable");
+    assert_eq!(turntable.format, TurntableFormat::Gif);
+    assert!(!turntable.contact_sheet);
+    assert_eq!(turntable.sheet_columns(), 4);
+    assert!(turntable.validate().is_ok());
+
+    let turntable: Turntable = from_str(r#"<turntable frames="0"/>"#).expect("Failed to parse Turntable");
+    assert!(turntable.validate().is_err(), "A turntable without frames cannot be saved");
+}
+
+#[test]
+fn test_turntable_orbits_look_at() {
+    let camera = create_camera();
+    let turntable: Turntable = from_str(r#"<turntable frames="4"/>"#).expect("Failed to parse Turntable");
+
+    assert!((turntable.camera_position(&camera, 0) - camera.position).length() < 1e-9, "First frame should keep the camera");
+
+    // A quarter turn around the up vector keeps the height and the distance to the look at point
+    let quarter = turntable.camera_position(&camera, 1);
+    assert!((quarter - Point::new(5.0, 1.0, -3.0)).length() < 1e-9, "Incorrect camera position");
+
+    let half = turntable.camera_position(&camera, 2);
+    assert!((half - Point::new(0.0, 1.0, -8.0)).length() < 1e-9, "Incorrect camera position");
+}
+
+#[test]
+fn test_contact_sheet_layout() {
+    let frames: Vec<RgbImage> = (0..5u8)
+        .map(|i| RgbImage::from_pixel(4, 3, Rgb([i * 50, 0, 0])))
+        .collect();
+
+    let sheet = ImageExportService::contact_sheet(&frames, 3);
+
+    // Five frames in rows of three leave the last cell empty
+    assert_eq!(sheet.dimensions(), (12, 6));
+    assert_eq!(*sheet.get_pixel(0, 0), Rgb([0, 0, 0]));
+    assert_eq!(*sheet.get_pixel(8, 2), Rgb([100, 0, 0]));
+    assert_eq!(*sheet.get_pixel(5, 4), Rgb([200, 0, 0]));
+    assert_eq!(*sheet.get_pixel(11, 5), Rgb([0, 0, 0]));
+}